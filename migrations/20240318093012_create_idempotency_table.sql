@@ -0,0 +1,14 @@
+create type header_pair as (
+   name text,
+   value bytea
+);
+
+create table idempotency(
+   user_id uuid not null references users (user_id),
+   idempotency_key text not null,
+   response_status_code smallint null,
+   response_headers header_pair[] null,
+   response_body bytea null,
+   created_at timestamptz not null,
+   primary key (user_id, idempotency_key)
+);
@@ -0,0 +1,4 @@
+alter table issue_delivery_queue
+   add column n_attempts integer not null default 0,
+   add column execute_after timestamptz not null default now(),
+   add column last_error text null;
@@ -0,0 +1,9 @@
+create table issue_delivery_dead_letters(
+   newsletter_issue_id uuid not null
+      references newsletter_issues (newsletter_issue_id),
+   subscriber_email text not null,
+   n_attempts integer not null,
+   last_error text not null,
+   failed_at timestamptz not null,
+   primary key (newsletter_issue_id, subscriber_email)
+);
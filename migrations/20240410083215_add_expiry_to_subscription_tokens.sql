@@ -0,0 +1,10 @@
+begin;
+    alter table subscription_tokens
+        add column created_at timestamptz not null default now(),
+        add column expires_at timestamptz null;
+    -- give historical tokens the default lifetime
+    update subscription_tokens
+        set expires_at = created_at + interval '24 hours'
+        where expires_at is null;
+    alter table subscription_tokens alter column expires_at set not null;
+commit;
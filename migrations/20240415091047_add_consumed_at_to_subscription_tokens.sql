@@ -0,0 +1 @@
+alter table subscription_tokens add column consumed_at timestamptz null;
@@ -0,0 +1,12 @@
+alter table newsletter_issues
+   add column author_id uuid null references users (user_id) on delete set null,
+   add column created_at timestamptz null,
+   add column recipient_count integer not null default 0,
+   add column delivered_count integer not null default 0,
+   add column failed_count integer not null default 0;
+
+update newsletter_issues set created_at = published_at;
+
+alter table newsletter_issues
+   alter column created_at set not null,
+   alter column created_at set default now();
@@ -0,0 +1,18 @@
+alter table newsletter_issues
+   add column status text null,
+   add column scheduled_for timestamptz null,
+   alter column published_at drop not null;
+
+update newsletter_issues i
+set status = case
+   when exists (
+      select 1 from issue_delivery_queue q
+      where q.newsletter_issue_id = i.newsletter_issue_id
+   ) then 'sending'
+   else 'sent'
+end;
+
+alter table newsletter_issues
+   alter column status set not null,
+   add constraint newsletter_issues_status_check
+      check (status in ('draft', 'scheduled', 'sending', 'sent', 'cancelled'));
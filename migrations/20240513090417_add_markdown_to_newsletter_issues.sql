@@ -0,0 +1 @@
+alter table newsletter_issues add column markdown_content text null;
@@ -0,0 +1,52 @@
+create table lists(
+   list_id uuid not null,
+   primary key (list_id),
+   slug text not null unique,
+   name text not null,
+   created_at timestamptz not null default now()
+);
+
+-- The list of every subscriber and issue created before lists existed, and the one
+-- subscribers join when they don't pick a list.
+insert into lists (list_id, slug, name)
+values ('4c7d8ad2-1e8f-4c4e-b0e5-3b0f6f0d9a15', 'newsletter', 'Newsletter');
+
+-- The status of a subscriber on each list they joined. `subscriptions.status` is kept
+-- as a summary: `confirmed` on any list, else `pending_confirmation` on any list, else
+-- `unsubscribed`.
+create table list_memberships(
+   subscriber_id uuid not null
+      references subscriptions (id) on delete cascade,
+   list_id uuid not null
+      references lists (list_id) on delete cascade,
+   primary key (subscriber_id, list_id),
+   status text not null
+      check (status in ('pending_confirmation', 'confirmed', 'unsubscribed')),
+   subscribed_at timestamptz not null
+);
+
+insert into list_memberships (subscriber_id, list_id, status, subscribed_at)
+select id, '4c7d8ad2-1e8f-4c4e-b0e5-3b0f6f0d9a15', status, subscribed_at
+from subscriptions;
+
+-- Confirmation links confirm a single list.
+alter table subscription_tokens
+   add column list_id uuid null
+      references lists (list_id) on delete cascade;
+
+update subscription_tokens set list_id = '4c7d8ad2-1e8f-4c4e-b0e5-3b0f6f0d9a15';
+
+alter table subscription_tokens alter column list_id set not null;
+
+-- The lists an issue is sent to.
+create table newsletter_issue_lists(
+   newsletter_issue_id uuid not null
+      references newsletter_issues (newsletter_issue_id) on delete cascade,
+   list_id uuid not null
+      references lists (list_id),
+   primary key (newsletter_issue_id, list_id)
+);
+
+insert into newsletter_issue_lists (newsletter_issue_id, list_id)
+select newsletter_issue_id, '4c7d8ad2-1e8f-4c4e-b0e5-3b0f6f0d9a15'
+from newsletter_issues;
@@ -0,0 +1,9 @@
+-- Addresses that must not be added back, e.g. by an import, stored as a keyed hash so
+-- that erased subscribers are not identifiable from it.
+create table suppressions(
+   email_hash text not null,
+   primary key (email_hash),
+   reason text not null
+      check (reason in ('erasure')),
+   suppressed_at timestamptz not null default now()
+);
@@ -0,0 +1,6 @@
+-- Addresses that hard-bounced or reported an email as spam must not be mailed again.
+alter table suppressions drop constraint suppressions_reason_check;
+
+alter table suppressions
+   add constraint suppressions_reason_check
+      check (reason in ('erasure', 'hard_bounce', 'spam_complaint'));
@@ -0,0 +1,27 @@
+-- Opens and clicks are only recorded for the issues that ask for it, and never for the
+-- subscribers who opted out.
+alter table newsletter_issues add column tracking boolean not null default false;
+
+alter table subscriptions add column tracking_opt_out boolean not null default false;
+
+-- The first time each subscriber opened an issue.
+create table issue_opens(
+   newsletter_issue_id uuid not null
+      references newsletter_issues (newsletter_issue_id) on delete cascade,
+   subscriber_id uuid not null
+      references subscriptions (id) on delete cascade,
+   primary key (newsletter_issue_id, subscriber_id),
+   opened_at timestamptz not null default now()
+);
+
+-- How many times each subscriber followed each link of an issue.
+create table issue_clicks(
+   newsletter_issue_id uuid not null
+      references newsletter_issues (newsletter_issue_id) on delete cascade,
+   subscriber_id uuid not null
+      references subscriptions (id) on delete cascade,
+   url text not null,
+   primary key (newsletter_issue_id, subscriber_id, url),
+   n_clicks integer not null default 1,
+   first_clicked_at timestamptz not null default now()
+);
@@ -0,0 +1,23 @@
+-- Every attempt to send an issue to a subscriber, so that support can tell whether it
+-- reached them.
+create table deliveries(
+   delivery_id uuid not null,
+   primary key (delivery_id),
+   newsletter_issue_id uuid not null
+      references newsletter_issues (newsletter_issue_id) on delete cascade,
+   subscriber_id uuid null
+      references subscriptions (id) on delete cascade,
+   -- The address the issue was sent to, which the subscriber may have changed since.
+   subscriber_email text not null,
+   n_attempts integer not null,
+   status text not null
+      check (status in ('sent', 'deferred', 'failed', 'suppressed')),
+   -- The id the email provider gave the message, when it was sent.
+   provider_message_id text null,
+   error text null,
+   attempted_at timestamptz not null default now()
+);
+
+create index deliveries_subscriber_id_idx on deliveries (subscriber_id);
+
+create index deliveries_newsletter_issue_id_idx on deliveries (newsletter_issue_id);
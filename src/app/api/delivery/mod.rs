@@ -0,0 +1,9 @@
+use crate::app::AppState;
+use axum::{routing::get, Router};
+
+pub mod route;
+pub mod schema;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/deliveries", get(route::list_deliveries))
+}
@@ -0,0 +1,115 @@
+use anyhow::Context;
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::schema;
+use crate::app::{error::AppResult, extractor::api_user::ApiUser, AppState};
+
+/// The log of the attempts to send issues, newest first, to find out whether a
+/// subscriber got an issue.
+#[tracing::instrument(name = "List deliveries", skip(user, state, params), fields(user_id = %user.user_id))]
+pub async fn list_deliveries(
+    user: ApiUser,
+    State(state): State<AppState>,
+    Query(params): Query<schema::ListDeliveriesParams>,
+) -> AppResult<Json<schema::ListDeliveriesResponseBody>> {
+    params.validate()?;
+
+    let deliveries = sqlx::query_as!(
+        schema::Delivery,
+        r#"
+        select
+            d.delivery_id,
+            d.newsletter_issue_id,
+            i.title,
+            d.subscriber_id,
+            d.subscriber_email,
+            d.n_attempts,
+            d.status,
+            d.provider_message_id,
+            d.error,
+            d.attempted_at
+        from deliveries d
+        join newsletter_issues i on i.newsletter_issue_id = d.newsletter_issue_id
+        where ($1::uuid is null or d.subscriber_id = $1)
+            and ($2::text is null or lower(d.subscriber_email) = lower($2))
+            and ($3::uuid is null or d.newsletter_issue_id = $3)
+            and ($4::text is null or d.status = $4)
+        order by d.attempted_at desc, d.delivery_id
+        limit $5
+        offset $6
+        "#,
+        params.subscriber_id,
+        params.email,
+        params.newsletter_issue_id,
+        params.status,
+        params.per_page,
+        params.offset(),
+    )
+    .fetch_all(&state.db)
+    .await
+    .context("Failed to retrieve the deliveries.")?;
+
+    let total = sqlx::query_scalar!(
+        r#"
+        select count(*) as "total!"
+        from deliveries d
+        where ($1::uuid is null or d.subscriber_id = $1)
+            and ($2::text is null or lower(d.subscriber_email) = lower($2))
+            and ($3::uuid is null or d.newsletter_issue_id = $3)
+            and ($4::text is null or d.status = $4)
+        "#,
+        params.subscriber_id,
+        params.email,
+        params.newsletter_issue_id,
+        params.status,
+    )
+    .fetch_one(&state.db)
+    .await
+    .context("Failed to count the deliveries.")?;
+
+    Ok(Json(schema::ListDeliveriesResponseBody {
+        deliveries,
+        page: params.page,
+        per_page: params.per_page,
+        total,
+    }))
+}
+
+/// Every attempt to send an issue to the subscriber, newest first.
+#[tracing::instrument(skip(pool))]
+pub async fn get_subscriber_deliveries(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Vec<schema::Delivery>, anyhow::Error> {
+    let deliveries = sqlx::query_as!(
+        schema::Delivery,
+        r#"
+        select
+            d.delivery_id,
+            d.newsletter_issue_id,
+            i.title,
+            d.subscriber_id,
+            d.subscriber_email,
+            d.n_attempts,
+            d.status,
+            d.provider_message_id,
+            d.error,
+            d.attempted_at
+        from deliveries d
+        join newsletter_issues i on i.newsletter_issue_id = d.newsletter_issue_id
+        where d.subscriber_id = $1
+        order by d.attempted_at desc, d.delivery_id
+        "#,
+        subscriber_id,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the deliveries of the subscriber.")?;
+
+    Ok(deliveries)
+}
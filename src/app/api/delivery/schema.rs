@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    app::{
+        api::subscriber::schema::{default_per_page, first_page, validate_page, validate_per_page},
+        error::FieldErrors,
+    },
+    domain::validation::ValidationError,
+};
+
+const STATUSES: [&str; 4] = ["sent", "deferred", "failed", "suppressed"];
+
+#[derive(Deserialize)]
+pub struct ListDeliveriesParams {
+    pub subscriber_id: Option<Uuid>,
+    /// Only keep the deliveries to this address, ignoring case.
+    pub email: Option<String>,
+    pub newsletter_issue_id: Option<Uuid>,
+    pub status: Option<String>,
+    /// The page to return, starting at 1.
+    #[serde(default = "first_page")]
+    pub page: i64,
+    #[serde(default = "default_per_page")]
+    pub per_page: i64,
+}
+
+impl ListDeliveriesParams {
+    /// Check every parameter, reporting all the rejected ones at once.
+    pub fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::default();
+        if let Some(status) = &self.status {
+            errors.check("status", validate_status(status));
+        }
+        errors.check("page", validate_page(self.page));
+        errors.check("per_page", validate_per_page(self.per_page));
+        errors.into_result()
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page - 1).saturating_mul(self.per_page)
+    }
+}
+
+fn validate_status(status: &str) -> Result<(), ValidationError> {
+    if !STATUSES.contains(&status) {
+        return Err(ValidationError::new(
+            "unknown_status",
+            format!("status must be one of: {}", STATUSES.join(", ")),
+        ));
+    }
+
+    Ok(())
+}
+
+/// An attempt to send an issue to a subscriber.
+#[derive(Serialize)]
+pub struct Delivery {
+    pub delivery_id: Uuid,
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    /// Missing when the address was not subscribed when the issue was sent.
+    pub subscriber_id: Option<Uuid>,
+    /// The address the issue was sent to.
+    pub subscriber_email: String,
+    /// How many times the issue was attempted, this attempt included.
+    pub n_attempts: i32,
+    /// `sent` once the email provider accepted the email, `deferred` when the attempt
+    /// failed and will be retried, `failed` when it won't, and `suppressed` when nothing
+    /// was sent because the address is suppressed.
+    pub status: String,
+    /// The id the email provider gave the message, to look it up in its logs.
+    pub provider_message_id: Option<String>,
+    pub error: Option<String>,
+    pub attempted_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct ListDeliveriesResponseBody {
+    pub deliveries: Vec<Delivery>,
+    pub page: i64,
+    pub per_page: i64,
+    /// How many deliveries match the filters, across all pages.
+    pub total: i64,
+}
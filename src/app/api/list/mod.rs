@@ -0,0 +1,14 @@
+use crate::app::AppState;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+pub mod route;
+pub mod schema;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/lists", post(route::create_list))
+        .route("/lists", get(route::list_lists))
+}
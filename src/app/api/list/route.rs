@@ -0,0 +1,146 @@
+use anyhow::Context;
+use axum::{extract::State, http::StatusCode, Json};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::schema;
+use crate::{
+    app::{
+        error::{AppError, AppResult},
+        extractor::{api_json::ApiJson, api_user::ApiUser},
+        AppState,
+    },
+    domain::validation::ValidationError,
+};
+
+/// The list subscribers join when they don't pick one.
+pub const DEFAULT_LIST: &str = "newsletter";
+
+#[tracing::instrument(name = "Create mailing list", skip(user, state, body), fields(user_id = %user.user_id))]
+pub async fn create_list(
+    user: ApiUser,
+    State(state): State<AppState>,
+    ApiJson(body): ApiJson<schema::CreateListRequestBody>,
+) -> AppResult<(StatusCode, Json<schema::MailingList>)> {
+    body.validate()?;
+
+    let list = sqlx::query_as!(
+        schema::MailingList,
+        r#"
+        insert into lists (list_id, slug, name)
+        values ($1, $2, $3)
+        on conflict (slug) do nothing
+        returning list_id, slug, name, created_at, 0::bigint as "confirmed_count!"
+        "#,
+        Uuid::new_v4(),
+        body.slug,
+        body.name.trim(),
+    )
+    .fetch_optional(&state.db)
+    .await
+    .context("Failed to store the mailing list.")?;
+
+    match list {
+        Some(list) => Ok((StatusCode::CREATED, Json(list))),
+        None => Err(AppError::invalid_field(
+            "slug",
+            ValidationError::new("taken", "another list already uses this slug"),
+        )),
+    }
+}
+
+#[tracing::instrument(name = "List mailing lists", skip(user, state), fields(user_id = %user.user_id))]
+pub async fn list_lists(
+    user: ApiUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<schema::ListListsResponseBody>> {
+    let lists = get_lists(&state.db).await?;
+
+    Ok(Json(schema::ListListsResponseBody { lists }))
+}
+
+/// Every mailing list, in alphabetical order.
+#[tracing::instrument(skip_all)]
+pub async fn get_lists(pool: &PgPool) -> Result<Vec<schema::MailingList>, anyhow::Error> {
+    let lists = sqlx::query_as!(
+        schema::MailingList,
+        r#"
+        select
+            l.list_id,
+            l.slug,
+            l.name,
+            l.created_at,
+            count(m.subscriber_id) filter (where m.status = 'confirmed') as "confirmed_count!"
+        from lists l
+        left join list_memberships m on m.list_id = l.list_id
+        group by l.list_id
+        order by l.name
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the mailing lists.")?;
+
+    Ok(lists)
+}
+
+/// A list referred to by its slug.
+pub struct ListRef {
+    pub list_id: Uuid,
+    pub name: String,
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_list(pool: &PgPool, slug: &str) -> Result<Option<ListRef>, anyhow::Error> {
+    let list = sqlx::query_as!(
+        ListRef,
+        r#"select list_id, name from lists where slug = $1"#,
+        slug,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to look up the mailing list.")?;
+
+    Ok(list)
+}
+
+/// Look up the lists an issue targets, the default one when none is given.
+///
+/// Duplicates are ignored; an unknown slug rejects the `lists` field.
+pub async fn resolve_lists(pool: &PgPool, slugs: &[String]) -> AppResult<Vec<Uuid>> {
+    let slugs = match slugs {
+        [] => vec![DEFAULT_LIST.to_owned()],
+        slugs => slugs.to_vec(),
+    };
+    let lists = sqlx::query!(
+        r#"select list_id, slug from lists where slug = any($1)"#,
+        &slugs,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to look up the mailing lists.")?;
+
+    if let Some(unknown) = slugs
+        .iter()
+        .find(|slug| !lists.iter().any(|list| &list.slug == *slug))
+    {
+        return Err(AppError::invalid_field(
+            "lists",
+            ValidationError::new("unknown_list", format!("there is no list {:?}", unknown)),
+        ));
+    }
+
+    Ok(lists.into_iter().map(|list| list.list_id).collect())
+}
+
+/// Look up the list a subscriber asked to join, the default one when none is given.
+pub async fn resolve_list(pool: &PgPool, slug: Option<&str>) -> AppResult<ListRef> {
+    get_list(pool, slug.unwrap_or(DEFAULT_LIST))
+        .await?
+        .ok_or_else(|| {
+            AppError::invalid_field(
+                "list",
+                ValidationError::new("unknown_list", "there is no such list"),
+            )
+        })
+}
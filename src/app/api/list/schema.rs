@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+use crate::{app::error::FieldErrors, domain::validation::ValidationError};
+
+const MAX_SLUG_LENGTH: usize = 64;
+const MAX_NAME_LENGTH: usize = 256;
+
+#[derive(Deserialize)]
+pub struct CreateListRequestBody {
+    /// The identifier subscribers and issues refer to the list with.
+    pub slug: String,
+    pub name: String,
+}
+
+impl CreateListRequestBody {
+    /// Check every field, reporting all the rejected ones at once.
+    pub fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::default();
+        errors.check("slug", validate_slug(&self.slug));
+        errors.check("name", validate_name(&self.name));
+        errors.into_result()
+    }
+}
+
+/// Slugs end up in URLs and request bodies: lowercase letters, digits and dashes only.
+fn validate_slug(slug: &str) -> Result<(), ValidationError> {
+    if slug.is_empty() {
+        return Err(ValidationError::new("empty", "slug is empty"));
+    }
+
+    if slug.len() > MAX_SLUG_LENGTH {
+        return Err(ValidationError::new(
+            "too_long",
+            format!("slug must be at most {} characters", MAX_SLUG_LENGTH),
+        ));
+    }
+
+    if !slug
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return Err(ValidationError::new(
+            "invalid_characters",
+            "slug must only contain lowercase letters, digits and dashes",
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<(), ValidationError> {
+    if name.trim().is_empty() {
+        return Err(ValidationError::new("empty", "name is empty"));
+    }
+
+    if name.graphemes(true).count() > MAX_NAME_LENGTH {
+        return Err(ValidationError::new(
+            "too_long",
+            format!("name must be at most {} characters", MAX_NAME_LENGTH),
+        ));
+    }
+
+    Ok(())
+}
+
+/// A mailing list, with how many subscribers confirmed they want to receive it.
+#[derive(Serialize)]
+pub struct MailingList {
+    pub list_id: Uuid,
+    pub slug: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub confirmed_count: i64,
+}
+
+#[derive(Serialize)]
+pub struct ListListsResponseBody {
+    pub lists: Vec<MailingList>,
+}
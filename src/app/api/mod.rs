@@ -1,4 +1,10 @@
+pub mod delivery;
 pub mod health;
+pub mod list;
 pub mod newsletter;
+pub mod personal_data;
+pub mod subscriber;
 pub mod subscription;
+pub mod tracking;
 pub mod user;
+pub mod webhook;
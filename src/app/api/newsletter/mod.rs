@@ -1,10 +1,28 @@
 use crate::app::AppState;
-use axum::{routing::post, Router};
+use axum::{
+    routing::{get, post},
+    Router,
+};
 
 pub mod route;
 pub mod schema;
 
 pub fn router() -> Router<AppState> {
     // TODO improve module naming
-    Router::new().route("/newsletters", post(route::publish_newsletter))
+    Router::new()
+        .route("/newsletters", post(route::publish_newsletter))
+        .route("/newsletters", get(route::list_newsletter_issues))
+        .route(
+            "/newsletters/:newsletter_issue_id",
+            get(route::get_newsletter_issue),
+        )
+        .route(
+            "/newsletters/:newsletter_issue_id/links",
+            get(route::list_newsletter_issue_links),
+        )
+        .route("/newsletters/dead_letters", get(route::list_dead_letters))
+        .route(
+            "/newsletters/dead_letters/redrive",
+            post(route::redrive_dead_letters),
+        )
 }
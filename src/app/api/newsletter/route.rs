@@ -1,73 +1,485 @@
 use anyhow::Context;
-use axum::{extract::State, http::StatusCode, Json};
-use sqlx::PgPool;
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
 
 use super::schema;
 use crate::{
-    app::{error::AppResult, AppState},
-    domain::subscriber::email::Email,
+    app::{
+        api::list::route::resolve_lists,
+        error::{AppError, AppResult},
+        extractor::{api_json::ApiJson, api_user::ApiUser},
+        idempotency::{
+            key::{IdempotencyKey, IDEMPOTENCY_KEY_HEADER},
+            persistence::{save_response, try_processing, NextAction},
+        },
+        AppState,
+    },
+    issue_scheduling_worker::start_delivery,
 };
 
-#[tracing::instrument(name = "Publish newsletter", skip(state, body))]
+#[tracing::instrument(name = "Publish newsletter", skip(user, state, headers, body), fields(user_id = %user.user_id))]
 pub async fn publish_newsletter(
+    user: ApiUser,
     State(state): State<AppState>,
-    Json(body): Json<schema::PublishNewsletterRequestBody>,
-) -> AppResult<StatusCode> {
-    let subscribers = get_confirmed_subscribers(&state.db)
-        .await
-        .context("Failed to retrieve confirmed subscribers.")?;
-
-    for subscriber in subscribers {
-        match subscriber {
-            Ok(subscriber) => {
-                state
-                    .email_client
-                    .send_email(
-                        &subscriber.email,
-                        body.title.as_str(),
-                        body.content.html.as_str(),
-                        body.content.text.as_str(),
-                    )
-                    .await
-                    .with_context(|| {
-                        format!("Failed to send newsletter issue to {}.", subscriber.email)
-                    })?;
-            }
-            Err(e) => {
-                tracing::warn!(
-                    details = ?e,
-                    "Skipping a confirmed subscriber. Their stored contact details are invalid."
-                );
+    headers: HeaderMap,
+    ApiJson(mut body): ApiJson<schema::PublishNewsletterRequestBody>,
+) -> AppResult<Response> {
+    body.validate()?;
+    body.render_markdown();
+    let list_ids = resolve_lists(&state.db, &body.lists).await?;
+    let idempotency_key = IdempotencyKey::from_headers(&headers)
+        .map_err(|e| AppError::invalid_field(IDEMPOTENCY_KEY_HEADER, e))?;
+    let mut transaction = match idempotency_key {
+        Some(ref idempotency_key) => {
+            match try_processing(&state.db, idempotency_key, user.user_id).await? {
+                NextAction::StartProcessing(transaction) => *transaction,
+                NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
             }
         }
+        None => state
+            .db
+            .begin()
+            .await
+            .context("Failed to acquire a Postgres connection from the pool")?,
+    };
+
+    create_newsletter_issue(&mut transaction, user.user_id, &body, &list_ids).await?;
+
+    let response = StatusCode::ACCEPTED.into_response();
+    match idempotency_key {
+        Some(idempotency_key) => {
+            Ok(save_response(transaction, &idempotency_key, user.user_id, response).await?)
+        }
+        None => {
+            transaction
+                .commit()
+                .await
+                .context("Failed to commit SQL transaction to publish a newsletter issue.")?;
+            Ok(response)
+        }
+    }
+}
+
+/// The editable part of a newsletter issue.
+pub struct IssueDraft<'a> {
+    pub title: &'a str,
+    pub text_content: &'a str,
+    pub html_content: &'a str,
+    pub markdown_content: Option<&'a str>,
+    /// The lists the issue is sent to.
+    pub list_ids: &'a [Uuid],
+    /// When set, the issue is `scheduled` for publication rather than a `draft`.
+    pub scheduled_for: Option<DateTime<Utc>>,
+    pub tracking: bool,
+}
+
+impl<'a> IssueDraft<'a> {
+    /// The draft of an issue whose markdown, if any, was already rendered.
+    pub fn new(
+        issue: &'a schema::PublishNewsletterRequestBody,
+        list_ids: &'a [Uuid],
+        scheduled_for: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            title: &issue.title,
+            text_content: &issue.content.text,
+            html_content: &issue.content.html,
+            markdown_content: issue.content.markdown.as_deref(),
+            list_ids,
+            scheduled_for,
+            tracking: issue.tracking,
+        }
     }
 
-    Ok(StatusCode::OK)
+    fn status(&self) -> &'static str {
+        match self.scheduled_for {
+            Some(_) => "scheduled",
+            None => "draft",
+        }
+    }
 }
 
-struct ConfirmedSubscriber {
-    email: Email,
+/// Store a newsletter issue and queue its delivery to the confirmed subscribers of its
+/// lists.
+#[tracing::instrument(skip_all)]
+pub async fn create_newsletter_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    author_id: Uuid,
+    issue: &schema::PublishNewsletterRequestBody,
+    list_ids: &[Uuid],
+) -> Result<Uuid, anyhow::Error> {
+    let draft = IssueDraft::new(issue, list_ids, None);
+    let issue_id = insert_issue(transaction, author_id, &draft).await?;
+    start_delivery(transaction, issue_id).await?;
+
+    Ok(issue_id)
 }
 
-#[tracing::instrument(name = "Get confirmed subscribers", skip(pool))]
-async fn get_confirmed_subscribers(
+/// Store a new draft or scheduled issue.
+#[tracing::instrument(skip(transaction, draft))]
+pub async fn insert_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    author_id: Uuid,
+    draft: &IssueDraft<'_>,
+) -> Result<Uuid, anyhow::Error> {
+    let newsletter_issue_id = Uuid::new_v4();
+    let query = sqlx::query!(
+        r#"
+        insert into newsletter_issues (
+            newsletter_issue_id,
+            author_id,
+            title,
+            text_content,
+            html_content,
+            markdown_content,
+            status,
+            scheduled_for,
+            tracking
+        )
+        values ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+        newsletter_issue_id,
+        author_id,
+        draft.title,
+        draft.text_content,
+        draft.html_content,
+        draft.markdown_content,
+        draft.status(),
+        draft.scheduled_for,
+        draft.tracking,
+    );
+
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to store newsletter issue details.")?;
+    set_issue_lists(transaction, newsletter_issue_id, draft.list_ids).await?;
+
+    Ok(newsletter_issue_id)
+}
+
+/// Replace the content and schedule of an issue.
+///
+/// Returns `false` when the issue does not exist or can no longer be edited, because
+/// it was cancelled or its delivery has started.
+#[tracing::instrument(skip(transaction, draft))]
+pub async fn update_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    draft: &IssueDraft<'_>,
+) -> Result<bool, anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        update newsletter_issues
+        set
+            title = $2,
+            text_content = $3,
+            html_content = $4,
+            markdown_content = $5,
+            status = $6,
+            scheduled_for = $7,
+            tracking = $8
+        where
+            newsletter_issue_id = $1 and
+            status in ('draft', 'scheduled')
+        "#,
+        newsletter_issue_id,
+        draft.title,
+        draft.text_content,
+        draft.html_content,
+        draft.markdown_content,
+        draft.status(),
+        draft.scheduled_for,
+        draft.tracking,
+    );
+
+    let n_updated = transaction
+        .execute(query)
+        .await
+        .context("Failed to update newsletter issue details.")?
+        .rows_affected();
+    if n_updated == 0 {
+        return Ok(false);
+    }
+    set_issue_lists(transaction, newsletter_issue_id, draft.list_ids).await?;
+
+    Ok(true)
+}
+
+/// Replace the lists an issue is sent to.
+#[tracing::instrument(skip(transaction))]
+async fn set_issue_lists(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    list_ids: &[Uuid],
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        with removed as (
+            delete from newsletter_issue_lists
+            where newsletter_issue_id = $1 and list_id <> all($2)
+        )
+        insert into newsletter_issue_lists (newsletter_issue_id, list_id)
+        select $1, list_id from unnest($2::uuid[]) as list_id
+        on conflict do nothing
+        "#,
+        newsletter_issue_id,
+        list_ids,
+    );
+
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to store the lists of the newsletter issue.")?;
+
+    Ok(())
+}
+
+/// Cancel a draft or scheduled issue.
+///
+/// Returns `false` when the issue does not exist or its delivery has already started.
+#[tracing::instrument(skip(pool))]
+pub async fn cancel_issue(pool: &PgPool, newsletter_issue_id: Uuid) -> Result<bool, anyhow::Error> {
+    let n_cancelled = sqlx::query!(
+        r#"
+        update newsletter_issues
+        set status = 'cancelled', scheduled_for = null
+        where
+            newsletter_issue_id = $1 and
+            status in ('draft', 'scheduled')
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to cancel the newsletter issue.")?
+    .rows_affected();
+
+    Ok(n_cancelled > 0)
+}
+
+#[tracing::instrument(name = "List newsletter issues", skip(user, state), fields(user_id = %user.user_id))]
+pub async fn list_newsletter_issues(
+    user: ApiUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<schema::ListIssuesResponseBody>> {
+    let issues = list_issues(&state.db).await?;
+
+    Ok(Json(schema::ListIssuesResponseBody { issues }))
+}
+
+#[tracing::instrument(name = "Get newsletter issue", skip(user, state), fields(user_id = %user.user_id))]
+pub async fn get_newsletter_issue(
+    user: ApiUser,
+    State(state): State<AppState>,
+    Path(newsletter_issue_id): Path<Uuid>,
+) -> AppResult<Response> {
+    match get_issue(&state.db, newsletter_issue_id).await? {
+        Some(issue) => Ok(Json(issue).into_response()),
+        None => Ok(StatusCode::NOT_FOUND.into_response()),
+    }
+}
+
+/// Every newsletter issue with its delivery counts, most recent first.
+#[tracing::instrument(skip_all)]
+pub async fn list_issues(pool: &PgPool) -> Result<Vec<schema::IssueSummary>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        schema::IssueSummary,
+        r#"
+        select
+            i.newsletter_issue_id,
+            i.title,
+            i.author_id,
+            u.username as "author?",
+            array(
+                select l.slug
+                from newsletter_issue_lists il
+                join lists l on l.list_id = il.list_id
+                where il.newsletter_issue_id = i.newsletter_issue_id
+                order by l.slug
+            ) as "lists!",
+            i.status,
+            i.created_at,
+            i.scheduled_for,
+            i.published_at,
+            i.recipient_count,
+            i.delivered_count,
+            i.failed_count,
+            i.tracking,
+            (
+                select count(*) from issue_opens o
+                where o.newsletter_issue_id = i.newsletter_issue_id
+            ) as "unique_opens!",
+            (
+                select coalesce(sum(c.n_clicks), 0) from issue_clicks c
+                where c.newsletter_issue_id = i.newsletter_issue_id
+            ) as "clicks!"
+        from newsletter_issues i
+        left join users u on u.user_id = i.author_id
+        order by coalesce(i.published_at, i.scheduled_for, i.created_at) desc
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve newsletter issues.")?;
+
+    Ok(issues)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_issue(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<schema::IssueDetails>, anyhow::Error> {
+    let issue = sqlx::query_as!(
+        schema::IssueDetails,
+        r#"
+        select
+            i.newsletter_issue_id,
+            i.title,
+            i.author_id,
+            u.username as "author?",
+            array(
+                select l.slug
+                from newsletter_issue_lists il
+                join lists l on l.list_id = il.list_id
+                where il.newsletter_issue_id = i.newsletter_issue_id
+                order by l.slug
+            ) as "lists!",
+            i.status,
+            i.created_at,
+            i.scheduled_for,
+            i.published_at,
+            i.recipient_count,
+            i.delivered_count,
+            i.failed_count,
+            i.tracking,
+            (
+                select count(*) from issue_opens o
+                where o.newsletter_issue_id = i.newsletter_issue_id
+            ) as "unique_opens!",
+            (
+                select coalesce(sum(c.n_clicks), 0) from issue_clicks c
+                where c.newsletter_issue_id = i.newsletter_issue_id
+            ) as "clicks!",
+            i.text_content,
+            i.html_content,
+            i.markdown_content
+        from newsletter_issues i
+        left join users u on u.user_id = i.author_id
+        where i.newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the newsletter issue.")?;
+
+    Ok(issue)
+}
+
+#[tracing::instrument(name = "List the clicks of a newsletter issue", skip(user, state), fields(user_id = %user.user_id))]
+pub async fn list_newsletter_issue_links(
+    user: ApiUser,
+    State(state): State<AppState>,
+    Path(newsletter_issue_id): Path<Uuid>,
+) -> AppResult<Json<schema::ListLinkClicksResponseBody>> {
+    let links = get_link_clicks(&state.db, newsletter_issue_id).await?;
+
+    Ok(Json(schema::ListLinkClicksResponseBody { links }))
+}
+
+/// The links of an issue that were followed, most clicked first.
+#[tracing::instrument(skip(pool))]
+pub async fn get_link_clicks(
     pool: &PgPool,
-) -> Result<Vec<Result<ConfirmedSubscriber, anyhow::Error>>, anyhow::Error> {
-    let confirmed_subscribers = sqlx::query!(
+    newsletter_issue_id: Uuid,
+) -> Result<Vec<schema::LinkClicks>, anyhow::Error> {
+    let links = sqlx::query_as!(
+        schema::LinkClicks,
         r#"
-        SELECT email
-        FROM subscriptions
-        WHERE status = 'confirmed'
+        select
+            url,
+            sum(n_clicks) as "clicks!",
+            count(*) as "unique_clicks!"
+        from issue_clicks
+        where newsletter_issue_id = $1
+        group by url
+        order by 2 desc, url
         "#,
+        newsletter_issue_id,
     )
     .fetch_all(pool)
-    .await?
-    .into_iter()
-    .map(|r| match r.email.try_into() {
-        Ok(email) => Ok(ConfirmedSubscriber { email }),
-        Err(e) => Err(anyhow::anyhow!(e)),
-    })
-    .collect();
-
-    Ok(confirmed_subscribers)
+    .await
+    .context("Failed to retrieve the clicks of the newsletter issue.")?;
+
+    Ok(links)
+}
+
+#[tracing::instrument(name = "List dead letters", skip(user, state), fields(user_id = %user.user_id))]
+pub async fn list_dead_letters(
+    user: ApiUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<schema::ListDeadLettersResponseBody>> {
+    let dead_letters = sqlx::query_as!(
+        schema::DeadLetter,
+        r#"
+        select newsletter_issue_id, subscriber_email, n_attempts, last_error, failed_at
+        from issue_delivery_dead_letters
+        order by failed_at desc
+        "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    .context("Failed to retrieve dead letters.")?;
+
+    Ok(Json(schema::ListDeadLettersResponseBody { dead_letters }))
+}
+
+/// Move dead letters back to the delivery queue, resetting their attempt counters.
+#[tracing::instrument(name = "Redrive dead letters", skip(user, state, body), fields(user_id = %user.user_id))]
+pub async fn redrive_dead_letters(
+    user: ApiUser,
+    State(state): State<AppState>,
+    ApiJson(body): ApiJson<schema::RedriveDeadLettersRequestBody>,
+) -> AppResult<Json<schema::RedriveDeadLettersResponseBody>> {
+    let redriven = sqlx::query!(
+        r#"
+        with redriven as (
+            delete from issue_delivery_dead_letters
+            where $1::uuid is null or newsletter_issue_id = $1
+            returning newsletter_issue_id, subscriber_email
+        ),
+        uncounted as (
+            update newsletter_issues i
+            set
+                failed_count = greatest(i.failed_count - r.n, 0),
+                status = 'sending'
+            from (
+                select newsletter_issue_id, count(*) as n
+                from redriven
+                group by newsletter_issue_id
+            ) r
+            where i.newsletter_issue_id = r.newsletter_issue_id
+        )
+        insert into issue_delivery_queue (newsletter_issue_id, subscriber_email)
+        select newsletter_issue_id, subscriber_email
+        from redriven
+        on conflict do nothing
+        "#,
+        body.newsletter_issue_id,
+    )
+    .execute(&state.db)
+    .await
+    .context("Failed to redrive dead letters.")?
+    .rows_affected();
+
+    Ok(Json(schema::RedriveDeadLettersResponseBody { redriven }))
 }
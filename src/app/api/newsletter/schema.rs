@@ -1,13 +1,224 @@
-use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+use crate::{
+    app::error::FieldErrors,
+    domain::{markdown, validation::ValidationError},
+    email::template::check_variables,
+};
+
+const MAX_TITLE_LENGTH: usize = 256;
 
 #[derive(serde::Deserialize)]
 pub struct PublishNewsletterRequestBody {
     pub title: String,
     pub content: NewsletterContent,
+    /// The slugs of the lists to send the issue to, the default list when empty.
+    #[serde(default)]
+    pub lists: Vec<String>,
+    /// Record when subscribers open the issue and follow its links.
+    #[serde(default)]
+    pub tracking: bool,
+}
+
+impl PublishNewsletterRequestBody {
+    /// Check every field, reporting all the rejected ones at once.
+    pub fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::default();
+        errors.check("title", validate_title(&self.title));
+        match &self.content.markdown {
+            Some(markdown) => {
+                errors.check("content.markdown", validate_content(markdown));
+                errors.check("content", validate_single_source(&self.content));
+            }
+            None => {
+                errors.check("content.text", validate_content(&self.content.text));
+                errors.check("content.html", validate_content(&self.content.html));
+            }
+        }
+        self.check_variables(&mut errors);
+        errors.into_result()
+    }
+
+    /// Drafts only need a title, their content can be written later.
+    pub fn validate_draft(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::default();
+        errors.check("title", validate_title(&self.title));
+        if self.content.markdown.is_some() {
+            errors.check("content", validate_single_source(&self.content));
+        }
+        self.check_variables(&mut errors);
+        errors.into_result()
+    }
+
+    /// Personalisation variables are filled at delivery time, reject unknown ones now.
+    fn check_variables(&self, errors: &mut FieldErrors) {
+        let fields = [
+            ("title", Some(&self.title)),
+            ("content.text", Some(&self.content.text)),
+            ("content.html", Some(&self.content.html)),
+            ("content.markdown", self.content.markdown.as_ref()),
+        ];
+        for (field, value) in fields {
+            if let Some(value) = value {
+                if errors.iter().all(|e| e.field != field) {
+                    errors.check(field, check_variables(value));
+                }
+            }
+        }
+    }
+
+    /// Generate both versions of the content from the markdown source, if there is one.
+    pub fn render_markdown(&mut self) {
+        if let Some(source) = &self.content.markdown {
+            let rendered = markdown::render(source);
+            self.content.html = rendered.html;
+            self.content.text = rendered.text;
+        }
+    }
 }
 
+fn validate_title(title: &str) -> Result<(), ValidationError> {
+    if title.trim().is_empty() {
+        return Err(ValidationError::new("empty", "title is empty"));
+    }
+
+    if title.graphemes(true).count() > MAX_TITLE_LENGTH {
+        return Err(ValidationError::new(
+            "too_long",
+            format!("title must be at most {} characters", MAX_TITLE_LENGTH),
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_content(content: &str) -> Result<(), ValidationError> {
+    if content.trim().is_empty() {
+        return Err(ValidationError::new("empty", "content is empty"));
+    }
+
+    Ok(())
+}
+
+/// The HTML and plain-text versions are generated from markdown, so they can't be
+/// given as well.
+fn validate_single_source(content: &NewsletterContent) -> Result<(), ValidationError> {
+    if !content.html.is_empty() || !content.text.is_empty() {
+        return Err(ValidationError::new(
+            "conflicting",
+            "markdown can't be combined with html or text",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Either both versions of an issue, or the markdown source they are generated from.
 #[derive(Deserialize)]
 pub struct NewsletterContent {
+    #[serde(default)]
     pub html: String,
+    #[serde(default)]
     pub text: String,
+    #[serde(default)]
+    pub markdown: Option<String>,
+}
+
+/// A newsletter issue and the outcome of its delivery so far.
+#[derive(Serialize)]
+pub struct IssueSummary {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub author_id: Option<Uuid>,
+    /// The username of the author, unless their account was deleted.
+    pub author: Option<String>,
+    /// The slugs of the lists the issue is sent to.
+    pub lists: Vec<String>,
+    /// One of `draft`, `scheduled`, `sending`, `sent` or `cancelled`.
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub scheduled_for: Option<DateTime<Utc>>,
+    /// When the delivery started.
+    pub published_at: Option<DateTime<Utc>>,
+    /// How many confirmed subscribers the issue was queued for.
+    pub recipient_count: i32,
+    pub delivered_count: i32,
+    /// How many deliveries ended in the dead letters.
+    pub failed_count: i32,
+    /// Whether opens and clicks are recorded.
+    pub tracking: bool,
+    /// How many subscribers opened the issue, as far as tracking can tell.
+    pub unique_opens: i64,
+    /// How many times the links of the issue were followed.
+    pub clicks: i64,
+}
+
+#[derive(Serialize)]
+pub struct ListIssuesResponseBody {
+    pub issues: Vec<IssueSummary>,
+}
+
+#[derive(Serialize)]
+pub struct IssueDetails {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub author_id: Option<Uuid>,
+    pub author: Option<String>,
+    pub lists: Vec<String>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub scheduled_for: Option<DateTime<Utc>>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub recipient_count: i32,
+    pub delivered_count: i32,
+    pub failed_count: i32,
+    pub tracking: bool,
+    pub unique_opens: i64,
+    pub clicks: i64,
+    pub text_content: String,
+    pub html_content: String,
+    /// The source of both versions, for issues written in markdown.
+    pub markdown_content: Option<String>,
+}
+
+/// How often a link of an issue was followed.
+#[derive(Serialize)]
+pub struct LinkClicks {
+    pub url: String,
+    pub clicks: i64,
+    /// How many subscribers followed the link.
+    pub unique_clicks: i64,
+}
+
+#[derive(Serialize)]
+pub struct ListLinkClicksResponseBody {
+    pub links: Vec<LinkClicks>,
+}
+
+#[derive(Serialize)]
+pub struct DeadLetter {
+    pub newsletter_issue_id: Uuid,
+    pub subscriber_email: String,
+    pub n_attempts: i32,
+    pub last_error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct ListDeadLettersResponseBody {
+    pub dead_letters: Vec<DeadLetter>,
+}
+
+/// Re-drive all dead letters, or only those of a single issue.
+#[derive(Deserialize)]
+pub struct RedriveDeadLettersRequestBody {
+    pub newsletter_issue_id: Option<Uuid>,
+}
+
+#[derive(Serialize)]
+pub struct RedriveDeadLettersResponseBody {
+    pub redriven: u64,
 }
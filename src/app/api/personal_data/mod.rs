@@ -0,0 +1,21 @@
+use crate::app::AppState;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+pub mod route;
+pub mod schema;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/subscriptions/data", get(route::export_personal_data))
+        .route(
+            "/subscriptions/data/request",
+            post(route::request_data_link),
+        )
+        .route(
+            "/subscriptions/data/erase",
+            post(route::erase_personal_data),
+        )
+}
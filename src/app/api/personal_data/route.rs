@@ -0,0 +1,297 @@
+use anyhow::Context;
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use secrecy::Secret;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::schema;
+use crate::{
+    app::{
+        api::{
+            delivery::route::get_subscriber_deliveries,
+            subscriber::route::{get_subscriber_details, remove_subscriber},
+        },
+        error::{AppError, AppResult},
+        extractor::api_json::ApiJson,
+        AppState,
+    },
+    domain::subscriber::{data_request_token::DataRequestToken, email::Email},
+    email::{
+        suppression::{suppress, SuppressionReason},
+        template::DataRequestEmail,
+    },
+};
+
+/// How long the links to download or delete personal data work.
+const DATA_LINK_TTL_HOURS: i64 = 24;
+
+/// Email a subscriber the link to their data.
+///
+/// The response is the same whether or not the address is subscribed, so that it can't
+/// be used to find out who is.
+#[tracing::instrument(name = "Request a personal data link", skip(state, body))]
+pub async fn request_data_link(
+    State(state): State<AppState>,
+    ApiJson(body): ApiJson<schema::DataLinkRequestBody>,
+) -> AppResult<StatusCode> {
+    let email = Email::try_from(body.email).map_err(|e| AppError::invalid_field("email", e))?;
+
+    send_data_link(&state, &email).await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Send the link to the personal data of a subscriber, if there is one with this
+/// address.
+pub async fn send_data_link(state: &AppState, email: &Email) -> Result<(), anyhow::Error> {
+    let subscriber = sqlx::query!(
+        r#"select id, name from subscriptions where email = $1"#,
+        email.as_ref(),
+    )
+    .fetch_optional(&state.db)
+    .await
+    .context("Failed to look up the subscriber in the database.")?;
+    let Some(subscriber) = subscriber else {
+        tracing::info!("There is no subscriber with this email.");
+        return Ok(());
+    };
+
+    let expires_at = Utc::now() + Duration::hours(DATA_LINK_TTL_HOURS);
+    let token = DataRequestToken::generate(subscriber.id, expires_at, &state.hmac_key);
+    let data_link = format!(
+        "{}/my-data?subscriber_id={}&token={}",
+        state.base_url, subscriber.id, token
+    );
+    let message = DataRequestEmail {
+        name: &subscriber.name,
+        data_link: &data_link,
+    }
+    .render()
+    .context("Failed to render the personal data email.")?;
+
+    state
+        .email_client
+        .send_email(
+            email,
+            &message.subject,
+            &message.html_body,
+            &message.text_body,
+        )
+        .await
+        .context("Failed to send the personal data email.")?;
+
+    Ok(())
+}
+
+/// Download everything stored about the subscriber, as a JSON file.
+#[tracing::instrument(name = "Export personal data", skip(state, params), fields(subscriber_id = %params.subscriber_id))]
+pub async fn export_personal_data(
+    State(state): State<AppState>,
+    Query(params): Query<schema::DataParams>,
+) -> AppResult<Response> {
+    if !is_valid_data_request(&params, &state.hmac_key) {
+        return Ok(StatusCode::UNAUTHORIZED.into_response());
+    }
+
+    match get_personal_data(&state.db, params.subscriber_id).await? {
+        Some(data) => Ok((
+            [(
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"personal-data.json\"",
+            )],
+            Json(data),
+        )
+            .into_response()),
+        None => Ok(StatusCode::NOT_FOUND.into_response()),
+    }
+}
+
+/// Delete everything stored about the subscriber. Erasing twice is not an error.
+#[tracing::instrument(name = "Erase personal data", skip(state, params), fields(subscriber_id = %params.subscriber_id))]
+pub async fn erase_personal_data(
+    State(state): State<AppState>,
+    Query(params): Query<schema::DataParams>,
+) -> AppResult<StatusCode> {
+    if !is_valid_data_request(&params, &state.hmac_key) {
+        return Ok(StatusCode::UNAUTHORIZED);
+    }
+
+    erase_subscriber(&state.db, params.subscriber_id, &state.hmac_key).await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Check that the token was issued for the subscriber and is still valid.
+pub fn is_valid_data_request(params: &schema::DataParams, hmac_key: &Secret<String>) -> bool {
+    DataRequestToken::try_from(params.token.clone())
+        .map(|token| token.is_valid_for(params.subscriber_id, hmac_key, Utc::now()))
+        .unwrap_or(false)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_personal_data(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<schema::PersonalData>, anyhow::Error> {
+    let Some(subscriber) = get_subscriber_details(pool, subscriber_id).await? else {
+        return Ok(None);
+    };
+
+    let subscription_tokens = sqlx::query_as!(
+        schema::TokenRecord,
+        r#"
+        select t.subscription_token, l.slug as list, t.created_at, t.expires_at, t.consumed_at
+        from subscription_tokens t
+        join lists l on l.list_id = t.list_id
+        where t.subscriber_id = $1
+        order by t.created_at
+        "#,
+        subscriber_id,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the subscription tokens.")?;
+
+    let deliveries = sqlx::query_as!(
+        schema::DeliveryRecord,
+        r#"
+        select
+            q.newsletter_issue_id as "newsletter_issue_id!",
+            i.title as "title!",
+            'pending' as "status!",
+            q.n_attempts as "n_attempts!",
+            q.last_error,
+            null::timestamptz as failed_at
+        from issue_delivery_queue q
+        join newsletter_issues i on i.newsletter_issue_id = q.newsletter_issue_id
+        where q.subscriber_email = $1
+        union all
+        select
+            d.newsletter_issue_id,
+            i.title,
+            'failed',
+            d.n_attempts,
+            d.last_error,
+            d.failed_at
+        from issue_delivery_dead_letters d
+        join newsletter_issues i on i.newsletter_issue_id = d.newsletter_issue_id
+        where d.subscriber_email = $1
+        "#,
+        subscriber.email,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the deliveries.")?;
+
+    let delivery_log = get_subscriber_deliveries(pool, subscriber_id).await?;
+
+    Ok(Some(schema::PersonalData {
+        subscriber,
+        subscription_tokens,
+        deliveries,
+        delivery_log,
+    }))
+}
+
+/// Delete a subscriber and every delivery addressed to them, keeping only the hash of
+/// their address so that they are not imported again.
+#[tracing::instrument(skip(pool, hmac_key))]
+pub async fn erase_subscriber(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    hmac_key: &Secret<String>,
+) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let email = sqlx::query_scalar!(
+        r#"select email from subscriptions where id = $1 for update"#,
+        subscriber_id,
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to look up the subscriber in the database.")?;
+    let Some(email) = email else {
+        return Ok(());
+    };
+
+    if let Ok(address) = Email::try_from(email.clone()) {
+        suppress(
+            &mut *transaction,
+            &address,
+            SuppressionReason::Erasure,
+            hmac_key,
+        )
+        .await?;
+    }
+    delete_deliveries(&mut transaction, &email).await?;
+    remove_subscriber(&mut transaction, subscriber_id).await?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to erase a subscriber.")?;
+
+    Ok(())
+}
+
+/// Drop the pending, failed and logged deliveries to an address, marking the issues left
+/// with nothing to deliver as `sent`.
+#[tracing::instrument(skip(transaction, email))]
+async fn delete_deliveries(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        with dropped as (
+            delete from issue_delivery_queue
+            where subscriber_email = $1
+            returning newsletter_issue_id
+        )
+        update newsletter_issues i
+        set status = 'sent'
+        where i.newsletter_issue_id in (select newsletter_issue_id from dropped)
+            and i.status = 'sending'
+            and not exists (
+                select 1 from issue_delivery_queue q
+                where q.newsletter_issue_id = i.newsletter_issue_id
+                    and q.subscriber_email <> $1
+            )
+        "#,
+        email,
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to delete the pending deliveries.")?;
+
+    let query = sqlx::query!(
+        r#"delete from issue_delivery_dead_letters where subscriber_email = $1"#,
+        email,
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to delete the failed deliveries.")?;
+
+    // The attempts logged without a subscriber are not deleted along with them.
+    let query = sqlx::query!(
+        r#"delete from deliveries where subscriber_email = $1"#,
+        email,
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to delete the delivery log.")?;
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::app::api::{delivery::schema::Delivery, subscriber::schema::SubscriberDetails};
+
+#[derive(Debug, Deserialize)]
+pub struct DataLinkRequestBody {
+    pub email: String,
+}
+
+/// The query parameters of the link emailed to a subscriber.
+#[derive(Debug, Deserialize)]
+pub struct DataParams {
+    pub subscriber_id: Uuid,
+    pub token: String,
+}
+
+/// Everything stored about a subscriber.
+#[derive(Serialize)]
+pub struct PersonalData {
+    pub subscriber: SubscriberDetails,
+    pub subscription_tokens: Vec<TokenRecord>,
+    pub deliveries: Vec<DeliveryRecord>,
+    /// Every attempt to send an issue to the subscriber.
+    pub delivery_log: Vec<Delivery>,
+}
+
+/// A confirmation link sent to the subscriber.
+#[derive(Serialize)]
+pub struct TokenRecord {
+    pub subscription_token: String,
+    /// The slug of the list the link confirms.
+    pub list: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub consumed_at: Option<DateTime<Utc>>,
+}
+
+/// An issue waiting to be delivered to the subscriber, or that could not be.
+#[derive(Serialize)]
+pub struct DeliveryRecord {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    /// `pending` or `failed`.
+    pub status: String,
+    pub n_attempts: i32,
+    pub last_error: Option<String>,
+    pub failed_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,33 @@
+use crate::app::AppState;
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{get, post},
+    Router,
+};
+
+pub mod route;
+pub mod schema;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/subscribers", get(route::list_subscribers))
+        .route(
+            "/subscribers/import",
+            post(route::import_subscribers).layer(DefaultBodyLimit::max(route::MAX_IMPORT_SIZE)),
+        )
+        .route("/subscribers/export", get(route::export_subscribers))
+        .route(
+            "/subscribers/:subscriber_id",
+            get(route::get_subscriber)
+                .patch(route::update_subscriber)
+                .delete(route::delete_subscriber),
+        )
+        .route(
+            "/subscribers/:subscriber_id/confirm",
+            post(route::confirm_subscriber),
+        )
+        .route(
+            "/subscribers/:subscriber_id/unsubscribe",
+            post(route::unsubscribe_subscriber),
+        )
+}
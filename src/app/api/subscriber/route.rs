@@ -0,0 +1,556 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::schema;
+use crate::{
+    app::{
+        api::{
+            list::route::{resolve_list, ListRef},
+            subscription,
+        },
+        error::{AppError, AppResult},
+        extractor::{api_json::ApiJson, api_user::ApiUser},
+        AppState,
+    },
+    domain::{
+        subscriber::{email::Email, name::Name},
+        validation::ValidationError,
+    },
+    email::suppression::{suppress, SuppressionList},
+};
+
+/// The largest CSV file accepted by imports, in bytes.
+pub const MAX_IMPORT_SIZE: usize = 10 * 1024 * 1024;
+
+/// How many subscribers are read from the database for each chunk of an export.
+const EXPORT_CHUNK_SIZE: i64 = 500;
+
+#[tracing::instrument(name = "List subscribers", skip(user, state, params), fields(user_id = %user.user_id))]
+pub async fn list_subscribers(
+    user: ApiUser,
+    State(state): State<AppState>,
+    Query(params): Query<schema::ListSubscribersParams>,
+) -> AppResult<Json<schema::ListSubscribersResponseBody>> {
+    params.validate()?;
+    let search = params.search_pattern();
+
+    let subscribers = sqlx::query_as!(
+        schema::SubscriberSummary,
+        r#"
+        select id, email, name, status, subscribed_at
+        from subscriptions
+        where ($1::text is null or status = $1)
+            and ($2::text is null or email ilike $2 or name ilike $2)
+        order by subscribed_at desc, id
+        limit $3
+        offset $4
+        "#,
+        params.status,
+        search,
+        params.per_page,
+        params.offset(),
+    )
+    .fetch_all(&state.db)
+    .await
+    .context("Failed to retrieve the subscribers.")?;
+
+    let total = sqlx::query_scalar!(
+        r#"
+        select count(*) as "total!"
+        from subscriptions
+        where ($1::text is null or status = $1)
+            and ($2::text is null or email ilike $2 or name ilike $2)
+        "#,
+        params.status,
+        search,
+    )
+    .fetch_one(&state.db)
+    .await
+    .context("Failed to count the subscribers.")?;
+
+    Ok(Json(schema::ListSubscribersResponseBody {
+        subscribers,
+        page: params.page,
+        per_page: params.per_page,
+        total,
+    }))
+}
+
+#[tracing::instrument(name = "Get subscriber", skip(user, state), fields(user_id = %user.user_id))]
+pub async fn get_subscriber(
+    user: ApiUser,
+    State(state): State<AppState>,
+    Path(subscriber_id): Path<Uuid>,
+) -> AppResult<Response> {
+    details_response(&state.db, subscriber_id).await
+}
+
+#[tracing::instrument(name = "Update subscriber", skip(user, state, body), fields(user_id = %user.user_id))]
+pub async fn update_subscriber(
+    user: ApiUser,
+    State(state): State<AppState>,
+    Path(subscriber_id): Path<Uuid>,
+    ApiJson(body): ApiJson<schema::UpdateSubscriberRequestBody>,
+) -> AppResult<Response> {
+    let name = Name::try_from(body.name).map_err(|e| AppError::invalid_field("name", e))?;
+
+    sqlx::query!(
+        r#"update subscriptions set name = $2 where id = $1"#,
+        subscriber_id,
+        name.as_ref(),
+    )
+    .execute(&state.db)
+    .await
+    .context("Failed to update the subscriber.")?;
+
+    details_response(&state.db, subscriber_id).await
+}
+
+/// Confirm every list the subscriber is waiting to join, as if they had clicked the
+/// confirmation links. Lists they left stay unsubscribed.
+#[tracing::instrument(name = "Confirm subscriber manually", skip(user, state), fields(user_id = %user.user_id))]
+pub async fn confirm_subscriber(
+    user: ApiUser,
+    State(state): State<AppState>,
+    Path(subscriber_id): Path<Uuid>,
+) -> AppResult<Response> {
+    let mut transaction = state
+        .db
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let query = sqlx::query!(
+        r#"
+        with pending_tokens as (
+            delete from subscription_tokens
+            where subscriber_id = $1 and consumed_at is null
+        )
+        update list_memberships set status = 'confirmed'
+        where subscriber_id = $1 and status = 'pending_confirmation'
+        "#,
+        subscriber_id,
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to confirm the memberships of the subscriber.")?;
+    subscription::route::refresh_subscriber_status(&mut transaction, subscriber_id)
+        .await
+        .context("Failed to update the status of the subscriber.")?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to confirm a subscriber.")?;
+
+    details_response(&state.db, subscriber_id).await
+}
+
+#[tracing::instrument(name = "Unsubscribe subscriber manually", skip(user, state), fields(user_id = %user.user_id))]
+pub async fn unsubscribe_subscriber(
+    user: ApiUser,
+    State(state): State<AppState>,
+    Path(subscriber_id): Path<Uuid>,
+) -> AppResult<Response> {
+    subscription::route::unsubscribe_subscriber(&state.db, subscriber_id)
+        .await
+        .context("Failed to unsubscribe the subscriber.")?;
+
+    details_response(&state.db, subscriber_id).await
+}
+
+#[tracing::instrument(name = "Delete subscriber", skip(user, state), fields(user_id = %user.user_id))]
+pub async fn delete_subscriber(
+    user: ApiUser,
+    State(state): State<AppState>,
+    Path(subscriber_id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    let mut transaction = state
+        .db
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let deleted = remove_subscriber(&mut transaction, subscriber_id).await?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to delete a subscriber.")?;
+
+    if deleted {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Ok(StatusCode::NOT_FOUND)
+    }
+}
+
+/// Delete a subscriber along with their tokens and list memberships.
+///
+/// Returns whether the subscriber existed.
+#[tracing::instrument(skip(transaction))]
+pub async fn remove_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let query = sqlx::query!(
+        r#"delete from subscription_tokens where subscriber_id = $1"#,
+        subscriber_id,
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to delete the subscription tokens.")?;
+
+    let query = sqlx::query!(r#"delete from subscriptions where id = $1"#, subscriber_id);
+    let n_deleted = transaction
+        .execute(query)
+        .await
+        .context("Failed to delete the subscriber.")?
+        .rows_affected();
+
+    Ok(n_deleted > 0)
+}
+
+async fn details_response(pool: &PgPool, subscriber_id: Uuid) -> AppResult<Response> {
+    match get_subscriber_details(pool, subscriber_id).await? {
+        Some(subscriber) => Ok(Json(subscriber).into_response()),
+        None => Ok(StatusCode::NOT_FOUND.into_response()),
+    }
+}
+
+/// A subscriber and their status on each list they joined.
+#[tracing::instrument(skip(pool))]
+pub async fn get_subscriber_details(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<schema::SubscriberDetails>, anyhow::Error> {
+    let Some(subscriber) = sqlx::query_as!(
+        schema::SubscriberSummary,
+        r#"select id, email, name, status, subscribed_at from subscriptions where id = $1"#,
+        subscriber_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the subscriber.")?
+    else {
+        return Ok(None);
+    };
+
+    let lists = sqlx::query_as!(
+        schema::Membership,
+        r#"
+        select l.slug, l.name, m.status, m.subscribed_at
+        from list_memberships m
+        join lists l on l.list_id = m.list_id
+        where m.subscriber_id = $1
+        order by l.name
+        "#,
+        subscriber_id,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the lists of the subscriber.")?;
+
+    Ok(Some(schema::SubscriberDetails {
+        id: subscriber.id,
+        email: subscriber.email,
+        name: subscriber.name,
+        status: subscriber.status,
+        subscribed_at: subscriber.subscribed_at,
+        lists,
+    }))
+}
+
+/// Import subscribers from a CSV file sent as the body of the request.
+#[tracing::instrument(name = "Import subscribers", skip(user, state, params, body), fields(user_id = %user.user_id))]
+pub async fn import_subscribers(
+    user: ApiUser,
+    State(state): State<AppState>,
+    Query(params): Query<schema::ImportParams>,
+    body: String,
+) -> AppResult<Json<schema::ImportReport>> {
+    let list = resolve_list(&state.db, params.list.as_deref()).await?;
+    let report = run_import(&state.db, &state.hmac_key, &body, &list, params.dry_run).await?;
+
+    Ok(Json(report))
+}
+
+/// Validate every row of a CSV file and add the subscribers who are not known yet to
+/// a list, unless their address is suppressed. Nothing is stored in a dry run.
+#[tracing::instrument(skip(pool, hmac_key, csv, list), fields(list_id = %list.list_id))]
+pub async fn run_import(
+    pool: &PgPool,
+    hmac_key: &Secret<String>,
+    csv: &str,
+    list: &ListRef,
+    dry_run: bool,
+) -> AppResult<schema::ImportReport> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(csv.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| {
+            AppError::invalid_field("csv", ValidationError::new("malformed", e.to_string()))
+        })?
+        .clone();
+    for column in ["email", "name"] {
+        if !headers.iter().any(|header| header == column) {
+            return Err(AppError::invalid_field(
+                "csv",
+                ValidationError::new(
+                    "missing_column",
+                    format!("the header has no {:?} column", column),
+                ),
+            ));
+        }
+    }
+
+    let mut errors = vec![];
+    let mut subscribers = vec![];
+    // Lowercased, like the suppression hashes, so that case alone does not make a new
+    // subscriber.
+    let mut emails = HashSet::new();
+    for (index, record) in reader.records().enumerate() {
+        let row = index + 1;
+        let parsed = record
+            .and_then(|record| record.deserialize::<schema::ImportRow>(Some(&headers)))
+            .map_err(|e| ValidationError::new("malformed", e.to_string()));
+        let subscriber = match parsed {
+            Ok(parsed) => schema::ImportedSubscriber::try_from(parsed),
+            Err(e) => {
+                errors.push(schema::RowError::new(row, "row", e));
+                continue;
+            }
+        };
+        match subscriber {
+            Ok(subscriber) if !emails.insert(subscriber.email.as_ref().to_lowercase()) => {
+                errors.push(schema::RowError::new(
+                    row,
+                    "email",
+                    ValidationError::new("duplicate", "email appears earlier in the file"),
+                ));
+            }
+            Ok(subscriber) => subscribers.push(subscriber),
+            Err(field_errors) => errors.extend(
+                field_errors
+                    .iter()
+                    .map(|e| schema::RowError::new(row, &e.field, e.error.clone())),
+            ),
+        }
+    }
+
+    let emails: Vec<String> = emails.into_iter().collect();
+    let existing: HashSet<String> = sqlx::query_scalar!(
+        r#"select lower(email) as "email!" from subscriptions where lower(email) = any($1)"#,
+        &emails,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to look up existing subscribers.")?
+    .into_iter()
+    .collect();
+    subscribers.retain(|subscriber| !existing.contains(&subscriber.email.as_ref().to_lowercase()));
+
+    let recipients: Vec<&Email> = subscribers
+        .iter()
+        .map(|subscriber| &subscriber.email)
+        .collect();
+    let is_suppressed = SuppressionList::new(pool.clone(), hmac_key.clone())
+        .check_all(&recipients)
+        .await?;
+    let suppressed = is_suppressed
+        .iter()
+        .filter(|suppressed| **suppressed)
+        .count();
+    let mut is_suppressed = is_suppressed.into_iter();
+    subscribers.retain(|_| !is_suppressed.next().unwrap_or_default());
+
+    let imported = if dry_run || subscribers.is_empty() {
+        subscribers.len()
+    } else {
+        insert_subscribers(pool, hmac_key, &subscribers, list.list_id).await?
+    };
+
+    Ok(schema::ImportReport {
+        dry_run,
+        imported,
+        // Subscribers who joined since the lookup are skipped as well.
+        existing: existing.len() + subscribers.len() - imported,
+        suppressed,
+        errors,
+    })
+}
+
+/// Store new subscribers along with their membership of the list, skipping the ones
+/// whose email is taken. Returns how many were stored.
+///
+/// Bounced and complaining subscribers are unsubscribed from the list, and their
+/// address is suppressed.
+#[tracing::instrument(skip(pool, hmac_key, subscribers))]
+async fn insert_subscribers(
+    pool: &PgPool,
+    hmac_key: &Secret<String>,
+    subscribers: &[schema::ImportedSubscriber],
+    list_id: Uuid,
+) -> Result<usize, anyhow::Error> {
+    let now = Utc::now();
+    let mut ids = Vec::with_capacity(subscribers.len());
+    let mut emails = Vec::with_capacity(subscribers.len());
+    let mut names = Vec::with_capacity(subscribers.len());
+    let mut statuses = Vec::with_capacity(subscribers.len());
+    let mut consented_at: Vec<DateTime<Utc>> = Vec::with_capacity(subscribers.len());
+    for subscriber in subscribers {
+        ids.push(Uuid::new_v4());
+        emails.push(subscriber.email.as_ref().to_owned());
+        names.push(subscriber.name.as_ref().to_owned());
+        statuses.push(subscriber.status.clone());
+        consented_at.push(subscriber.consented_at.unwrap_or(now));
+    }
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let n_inserted = sqlx::query!(
+        r#"
+        with new_subscribers as (
+            insert into subscriptions (id, email, name, status, subscribed_at)
+            select * from unnest($1::uuid[], $2::text[], $3::text[], $4::text[], $5::timestamptz[])
+            on conflict (email) do nothing
+            returning id, status, subscribed_at
+        )
+        insert into list_memberships (subscriber_id, list_id, status, subscribed_at)
+        select
+            id,
+            $6,
+            case when status in ('bounced', 'complained') then 'unsubscribed' else status end,
+            subscribed_at
+        from new_subscribers
+        "#,
+        &ids,
+        &emails,
+        &names,
+        &statuses,
+        &consented_at,
+        list_id,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to store the imported subscribers.")?
+    .rows_affected();
+
+    for subscriber in subscribers {
+        if let Some(reason) = subscriber.suppression_reason() {
+            suppress(&mut *transaction, &subscriber.email, reason, hmac_key).await?;
+        }
+    }
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to import subscribers.")?;
+
+    Ok(n_inserted as usize)
+}
+
+#[tracing::instrument(name = "Export subscribers", skip(user, state), fields(user_id = %user.user_id))]
+pub async fn export_subscribers(user: ApiUser, State(state): State<AppState>) -> Response {
+    export_response(state.db.clone())
+}
+
+/// Every subscriber as a CSV file, streamed in chunks rather than loaded at once.
+pub fn export_response(pool: PgPool) -> Response {
+    let chunks = futures::stream::try_unfold(ExportCursor::new(pool), ExportCursor::next_chunk);
+
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"subscribers.csv\"",
+            ),
+        ],
+        Body::from_stream(chunks),
+    )
+        .into_response()
+}
+
+/// Walks through the subscribers in the order of their ids.
+struct ExportCursor {
+    pool: PgPool,
+    /// The id of the last exported subscriber, `None` before the first chunk.
+    after: Option<Uuid>,
+    done: bool,
+}
+
+impl ExportCursor {
+    fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            after: None,
+            done: false,
+        }
+    }
+
+    /// The next rows of the file, starting with the header.
+    async fn next_chunk(mut self) -> Result<Option<(Vec<u8>, Self)>, anyhow::Error> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let subscribers = sqlx::query!(
+            r#"
+            select id, email, name, status, subscribed_at
+            from subscriptions
+            where $1::uuid is null or id > $1
+            order by id
+            limit $2
+            "#,
+            self.after,
+            EXPORT_CHUNK_SIZE,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to retrieve the subscribers to export.")?;
+
+        let mut writer = csv::Writer::from_writer(vec![]);
+        if self.after.is_none() {
+            writer.write_record(["email", "name", "status", "consented_at"])?;
+        }
+        for subscriber in &subscribers {
+            writer.write_record([
+                subscriber.email.as_str(),
+                subscriber.name.as_str(),
+                subscriber.status.as_str(),
+                subscriber.subscribed_at.to_rfc3339().as_str(),
+            ])?;
+        }
+        let chunk = writer
+            .into_inner()
+            .map_err(|e| e.into_error())
+            .context("Failed to write the exported subscribers.")?;
+
+        self.done = (subscribers.len() as i64) < EXPORT_CHUNK_SIZE;
+        if let Some(last) = subscribers.last() {
+            self.after = Some(last.id);
+        }
+
+        Ok(Some((chunk, self)))
+    }
+}
@@ -0,0 +1,295 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    app::error::FieldErrors,
+    domain::{
+        subscriber::{email::Email, name::Name},
+        validation::ValidationError,
+    },
+    email::suppression::SuppressionReason,
+};
+
+const DEFAULT_PER_PAGE: i64 = 50;
+const MAX_PER_PAGE: i64 = 100;
+/// The statuses a subscriber can have, on top of the ones they can have on a list.
+const SUBSCRIBER_STATUSES: [&str; 5] = [
+    "pending_confirmation",
+    "confirmed",
+    "unsubscribed",
+    "bounced",
+    "complained",
+];
+
+#[derive(Deserialize)]
+pub struct ListSubscribersParams {
+    /// Only keep the subscribers with this status.
+    pub status: Option<String>,
+    /// Only keep the subscribers whose email or name contains this text, ignoring case.
+    pub search: Option<String>,
+    /// The page to return, starting at 1.
+    #[serde(default = "first_page")]
+    pub page: i64,
+    #[serde(default = "default_per_page")]
+    pub per_page: i64,
+}
+
+pub fn first_page() -> i64 {
+    1
+}
+
+pub fn default_per_page() -> i64 {
+    DEFAULT_PER_PAGE
+}
+
+impl ListSubscribersParams {
+    /// Check every parameter, reporting all the rejected ones at once.
+    pub fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::default();
+        if let Some(status) = &self.status {
+            errors.check("status", validate_subscriber_status(status));
+        }
+        errors.check("page", validate_page(self.page));
+        errors.check("per_page", validate_per_page(self.per_page));
+        errors.into_result()
+    }
+
+    /// The pattern matching the searched text anywhere, with the wildcards it contains
+    /// escaped.
+    pub fn search_pattern(&self) -> Option<String> {
+        let search = self.search.as_deref()?.trim();
+        if search.is_empty() {
+            return None;
+        }
+
+        let mut pattern = String::with_capacity(search.len() + 2);
+        pattern.push('%');
+        for c in search.chars() {
+            if matches!(c, '%' | '_' | '\\') {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+        }
+        pattern.push('%');
+        Some(pattern)
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page - 1).saturating_mul(self.per_page)
+    }
+}
+
+fn validate_subscriber_status(status: &str) -> Result<(), ValidationError> {
+    if !SUBSCRIBER_STATUSES.contains(&status) {
+        return Err(ValidationError::new(
+            "unknown_status",
+            format!("status must be one of: {}", SUBSCRIBER_STATUSES.join(", ")),
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_import_status(status: &str) -> Result<(), ValidationError> {
+    if status == "pending_confirmation" {
+        return Err(ValidationError::new(
+            "unconfirmed",
+            "subscribers pending confirmation must subscribe again",
+        ));
+    }
+
+    validate_subscriber_status(status)
+}
+
+pub fn validate_page(page: i64) -> Result<(), ValidationError> {
+    if page < 1 {
+        return Err(ValidationError::new(
+            "out_of_range",
+            "page must be at least 1",
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn validate_per_page(per_page: i64) -> Result<(), ValidationError> {
+    if !(1..=MAX_PER_PAGE).contains(&per_page) {
+        return Err(ValidationError::new(
+            "out_of_range",
+            format!("per_page must be between 1 and {}", MAX_PER_PAGE),
+        ));
+    }
+
+    Ok(())
+}
+
+/// A subscriber, with their status summarised across lists.
+#[derive(Serialize)]
+pub struct SubscriberSummary {
+    pub id: Uuid,
+    pub email: String,
+    pub name: String,
+    /// `confirmed` when confirmed on any list, `pending_confirmation` when waiting
+    /// for a confirmation only, `unsubscribed` otherwise. `bounced` and `complained`
+    /// when the address hard-bounced or reported an email as spam, which unsubscribes
+    /// it from every list.
+    pub status: String,
+    pub subscribed_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct ListSubscribersResponseBody {
+    pub subscribers: Vec<SubscriberSummary>,
+    pub page: i64,
+    pub per_page: i64,
+    /// How many subscribers match the filters, across all pages.
+    pub total: i64,
+}
+
+/// The status of a subscriber on one list.
+#[derive(Serialize)]
+pub struct Membership {
+    pub slug: String,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct SubscriberDetails {
+    pub id: Uuid,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: DateTime<Utc>,
+    pub lists: Vec<Membership>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateSubscriberRequestBody {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct ImportParams {
+    /// Report what would be imported without storing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// The slug of the list the subscribers join, the default list when missing.
+    pub list: Option<String>,
+}
+
+/// A row of an imported CSV file, keyed by the names in its header.
+///
+/// Only the `email` and `name` columns are required; the same columns come out of
+/// the export, so exported files can be imported back. Subscribers pending confirmation
+/// are rejected: nothing proves they agreed to receive the newsletter.
+#[derive(Deserialize)]
+pub struct ImportRow {
+    pub email: String,
+    pub name: String,
+    /// The status of the subscriber, `confirmed` when missing. `bounced` and
+    /// `complained` subscribers are imported unsubscribed, with their address suppressed.
+    #[serde(default)]
+    pub status: Option<String>,
+    /// When the subscriber agreed to receive the newsletter, as RFC 3339. Defaults to
+    /// the time of the import.
+    #[serde(default)]
+    pub consented_at: Option<String>,
+}
+
+/// A row that passed validation.
+pub struct ImportedSubscriber {
+    pub email: Email,
+    pub name: Name,
+    pub status: String,
+    pub consented_at: Option<DateTime<Utc>>,
+}
+
+impl ImportedSubscriber {
+    /// Why the address must be suppressed, for subscribers who bounced or complained.
+    pub fn suppression_reason(&self) -> Option<SuppressionReason> {
+        match self.status.as_str() {
+            "bounced" => Some(SuppressionReason::HardBounce),
+            "complained" => Some(SuppressionReason::SpamComplaint),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<ImportRow> for ImportedSubscriber {
+    type Error = FieldErrors;
+    fn try_from(value: ImportRow) -> Result<Self, Self::Error> {
+        let mut errors = FieldErrors::default();
+        let email = errors.check("email", Email::try_from(value.email));
+        let name = errors.check("name", Name::try_from(value.name));
+        let status = value.status.unwrap_or_else(|| "confirmed".to_owned());
+        errors.check("status", validate_import_status(&status));
+        let consented_at = match value.consented_at {
+            Some(consented_at) => errors
+                .check("consented_at", parse_timestamp(&consented_at))
+                .map(Some),
+            None => Some(None),
+        };
+
+        match (email, name, consented_at) {
+            (Some(email), Some(name), Some(consented_at)) => {
+                errors.into_result()?;
+                Ok(Self {
+                    email,
+                    name,
+                    status,
+                    consented_at,
+                })
+            }
+            _ => Err(errors),
+        }
+    }
+}
+
+fn parse_timestamp(timestamp: &str) -> Result<DateTime<Utc>, ValidationError> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|_| {
+            ValidationError::new(
+                "invalid_format",
+                "timestamp must be in RFC 3339 format, e.g. 2024-05-27T09:30:00Z",
+            )
+        })
+}
+
+/// Why a row of an imported file was rejected.
+#[derive(Serialize)]
+pub struct RowError {
+    /// The position of the row in the file, after the header, starting at 1.
+    pub row: usize,
+    pub field: String,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl RowError {
+    pub fn new(row: usize, field: &str, error: ValidationError) -> Self {
+        Self {
+            row,
+            field: field.to_owned(),
+            code: error.code,
+            message: error.message,
+        }
+    }
+}
+
+/// The outcome of an import. Rejected rows are skipped, the others are imported.
+#[derive(Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// How many subscribers were added, or would be in a dry run.
+    pub imported: usize,
+    /// How many rows were skipped because their email is already subscribed.
+    pub existing: usize,
+    /// How many rows were skipped because their address is suppressed: the subscriber
+    /// asked to be erased, the address bounced or reported an email as spam.
+    pub suppressed: usize,
+    pub errors: Vec<RowError>,
+}
@@ -1,5 +1,6 @@
-use crate::app::AppState;
+use crate::app::{rate_limit::limit_subscriptions, AppState};
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
@@ -7,9 +8,23 @@ use axum::{
 pub mod route;
 pub mod schema;
 
-pub fn router() -> Router<AppState> {
+pub fn router(state: &AppState) -> Router<AppState> {
     // TODO improve module naming
     Router::new()
-        .route("/subscriptions", post(route::subscribe))
+        .route(
+            "/subscriptions",
+            post(route::subscribe).layer(middleware::from_fn_with_state(
+                state.clone(),
+                limit_subscriptions,
+            )),
+        )
         .route("/subscriptions/confirm", get(route::confirm))
+        .route(
+            "/subscriptions/confirm/resend",
+            post(route::resend_confirmation).layer(middleware::from_fn_with_state(
+                state.clone(),
+                limit_subscriptions,
+            )),
+        )
+        .route("/subscriptions/unsubscribe", post(route::unsubscribe))
 }
@@ -4,38 +4,108 @@ use anyhow::Context;
 use axum::{
     extract::{Query, State},
     http::StatusCode,
-    Json,
 };
+use chrono::{DateTime, Utc};
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use secrecy::Secret;
 use sqlx::{Executor, PgPool, Postgres, Transaction};
 use tracing::instrument;
 use uuid::Uuid;
 
-use super::schema::{self, ConfirmParams};
-use crate::app::error::{AppError, AppResult};
-use crate::{app::AppState, domain::subscriber::NewSubscriber, email::EmailClient};
+use super::schema::{self, ConfirmParams, ResendConfirmationBody, UnsubscribeParams};
+use crate::app::{
+    api::list::route::{resolve_list, ListRef},
+    error::{AppError, AppResult},
+    extractor::api_json::ApiJson,
+};
+use crate::{
+    app::AppState,
+    config::SubscriptionSettings,
+    domain::subscriber::{email::Email, unsubscribe_token::UnsubscribeToken, NewSubscriber},
+    email::{
+        template::{ConfirmationEmail, WelcomeEmail},
+        EmailClient,
+    },
+};
 
 #[instrument(name = "adding a new subscriber", skip(state, body), fields(email = %body.email, name = %body.name))]
 pub async fn subscribe(
     State(state): State<AppState>,
-    Json(body): Json<schema::SubscribeBody>,
+    ApiJson(body): ApiJson<schema::SubscribeBody>,
 ) -> AppResult<StatusCode> {
-    let new_subscriber = NewSubscriber::try_from(body).map_err(AppError::Validation)?;
+    let list = body.list.clone();
+    let new_subscriber = NewSubscriber::try_from(body)?;
+    let list = resolve_list(&state.db, list.as_deref()).await?;
 
+    register_subscriber(&state, &new_subscriber, &list).await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Store a new subscriber, or reset an existing one, and send them a link confirming
+/// they want to join the list.
+///
+/// Subscribers already confirmed on the list are left untouched and get no email.
+pub async fn register_subscriber(
+    state: &AppState,
+    new_subscriber: &NewSubscriber,
+    list: &ListRef,
+) -> Result<(), anyhow::Error> {
     let mut transaction = state
         .db
         .begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool")?;
 
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
+    let inserted_id = insert_subscriber(&mut transaction, new_subscriber)
         .await
         .context("Failed to insert new subscriber in the database.")?;
 
-    let subscription_token = generate_subscription_token();
-    store_token(&mut transaction, subscriber_id, &subscription_token)
+    let subscriber_id = match inserted_id {
+        Some(subscriber_id) => subscriber_id,
+        // The address is already known, possibly from a concurrent request that was
+        // first to insert it.
+        None => {
+            let subscriber = get_subscriber_by_email(&mut transaction, &new_subscriber.email)
+                .await
+                .context("Failed to look up the subscriber in the database.")?
+                .context("The subscriber was deleted while subscribing again.")?;
+            let status = get_membership_status(&mut transaction, subscriber.id, list.list_id)
+                .await
+                .context("Failed to look up the list membership in the database.")?;
+            // Answer as for a new subscriber, so that the form does not reveal who is
+            // subscribed.
+            if status.as_deref() == Some("confirmed") {
+                tracing::info!("The subscriber is already confirmed on this list.");
+                return Ok(());
+            }
+            // Pending subscribers get a new confirmation link, unsubscribed ones go
+            // through the double opt-in again.
+            reset_subscriber(
+                &mut transaction,
+                subscriber.id,
+                list.list_id,
+                new_subscriber,
+            )
+            .await
+            .context("Failed to reset the subscriber in the database.")?;
+            subscriber.id
+        }
+    };
+
+    join_list(&mut transaction, subscriber_id, list.list_id)
+        .await
+        .context("Failed to add the subscriber to the list.")?;
+    refresh_subscriber_status(&mut transaction, subscriber_id)
         .await
-        .context("Failed to store the confirmation token for a new subscriber.")?;
+        .context("Failed to update subscriber status.")?;
+    let subscription_token = issue_confirmation_token(
+        &mut transaction,
+        subscriber_id,
+        list.list_id,
+        &state.subscriptions,
+    )
+    .await?;
 
     transaction
         .commit()
@@ -44,85 +114,429 @@ pub async fn subscribe(
 
     send_confirmation_email(
         &state.email_client,
-        &new_subscriber,
+        &new_subscriber.email,
+        new_subscriber.name.as_ref(),
+        &list.name,
         &state.base_url,
         &subscription_token,
     )
     .await
     .context("Failed to send confirmation email.")?;
 
+    Ok(())
+}
+
+/// Send a new confirmation link to a pending subscriber, e.g. after theirs expired.
+///
+/// Succeeds whatever the state of the email address, so that the endpoint does not
+/// reveal who is subscribed.
+#[tracing::instrument(name = "Resend a confirmation email", skip(state, body))]
+pub async fn resend_confirmation(
+    State(state): State<AppState>,
+    ApiJson(body): ApiJson<ResendConfirmationBody>,
+) -> AppResult<StatusCode> {
+    let email = Email::try_from(body.email).map_err(|e| AppError::invalid_field("email", e))?;
+
+    request_new_confirmation(&state, &email).await?;
+
     Ok(StatusCode::OK)
 }
 
+/// Rotate the confirmation tokens of a pending subscriber and email them the new links,
+/// one for each list they have yet to confirm.
+///
+/// Does nothing when there is no pending subscription for the email address.
+pub async fn request_new_confirmation(
+    state: &AppState,
+    email: &Email,
+) -> Result<(), anyhow::Error> {
+    let mut transaction = state
+        .db
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let subscriber = get_subscriber_by_email(&mut transaction, email)
+        .await
+        .context("Failed to look up the subscriber in the database.")?;
+    let Some(subscriber) = subscriber else {
+        tracing::info!("There is no subscriber with this email.");
+        return Ok(());
+    };
+    let pending_lists = get_pending_lists(&mut transaction, subscriber.id)
+        .await
+        .context("Failed to look up the pending list memberships.")?;
+    if pending_lists.is_empty() {
+        tracing::info!("There is no pending subscription for this email.");
+        return Ok(());
+    }
+
+    let mut confirmations = Vec::with_capacity(pending_lists.len());
+    for list in pending_lists {
+        revoke_tokens(&mut transaction, subscriber.id, list.list_id)
+            .await
+            .context("Failed to revoke the previous confirmation tokens.")?;
+        let subscription_token = issue_confirmation_token(
+            &mut transaction,
+            subscriber.id,
+            list.list_id,
+            &state.subscriptions,
+        )
+        .await?;
+        confirmations.push((list, subscription_token));
+    }
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store a new confirmation token.")?;
+
+    for (list, subscription_token) in confirmations {
+        send_confirmation_email(
+            &state.email_client,
+            email,
+            &subscriber.name,
+            &list.name,
+            &state.base_url,
+            &subscription_token,
+        )
+        .await
+        .context("Failed to send confirmation email.")?;
+    }
+
+    Ok(())
+}
+
 #[tracing::instrument(name = "Confirm a pending subscriber", skip(state, params))]
 pub async fn confirm(
     State(state): State<AppState>,
     Query(params): Query<ConfirmParams>,
 ) -> AppResult<StatusCode> {
-    let id = match get_subscriber_id_from_token(&state.db, &params.subscription_token).await {
-        Ok(id) => id,
-        Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR),
-    };
-
-    match id {
+    match confirm_subscription(&state, &params.subscription_token).await? {
+        Confirmation::Confirmed | Confirmation::AlreadyConfirmed => Ok(StatusCode::OK),
         // Non-existing token!
-        None => Ok(StatusCode::UNAUTHORIZED),
-        Some(subscriber_id) => {
-            confirm_subscriber(&state.db, subscriber_id)
+        Confirmation::InvalidToken => Ok(StatusCode::UNAUTHORIZED),
+        Confirmation::ExpiredToken => Ok(StatusCode::GONE),
+    }
+}
+
+/// The outcome of following a confirmation link.
+pub enum Confirmation {
+    Confirmed,
+    /// The link was already used by a subscriber who is still confirmed.
+    AlreadyConfirmed,
+    InvalidToken,
+    ExpiredToken,
+}
+
+/// Consume a confirmation token, confirm the subscriber it was issued for and send them
+/// a welcome email.
+///
+/// Tokens are single-use: a consumed token is kept until it expires, only to tell an
+/// already confirmed subscriber apart from an invalid link. It never confirms again,
+/// e.g. after the subscriber unsubscribed.
+#[tracing::instrument(name = "Confirm a subscription", skip(state, subscription_token))]
+pub async fn confirm_subscription(
+    state: &AppState,
+    subscription_token: &str,
+) -> Result<Confirmation, anyhow::Error> {
+    let mut transaction = state
+        .db
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let token = get_token(&mut transaction, subscription_token)
+        .await
+        .context("Failed to retrieve the confirmation token.")?;
+
+    let mut confirmed = None;
+    let confirmation = match token {
+        None => Confirmation::InvalidToken,
+        Some(token) if token.consumed_at.is_some() => {
+            if token.membership_status == "confirmed" {
+                Confirmation::AlreadyConfirmed
+            } else {
+                Confirmation::InvalidToken
+            }
+        }
+        Some(token) if token.expires_at < Utc::now() => Confirmation::ExpiredToken,
+        Some(token) => {
+            confirm_subscriber(&mut transaction, token.subscriber_id, token.list_id)
                 .await
                 .context("Failed to update subscriber status.")?;
-            Ok(StatusCode::OK)
+            consume_token(&mut transaction, &token, subscription_token)
+                .await
+                .context("Failed to consume the confirmation token.")?;
+            confirmed = Some(token);
+            Confirmation::Confirmed
+        }
+    };
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to confirm a subscriber.")?;
+
+    // The subscription is confirmed whether or not the welcome email goes through.
+    if let Some(token) = confirmed {
+        if let Err(e) = send_welcome_email(state, &token).await {
+            tracing::error!(error.cause_chain = ?e, "Failed to send the welcome email");
         }
     }
+
+    Ok(confirmation)
 }
 
-#[instrument(name = "inserting new subscriber into the database", skip(transaction, subscriber), fields(email = %subscriber.email, name = %subscriber.name))]
-async fn insert_subscriber(
+/// One-click unsubscribe, as advertised by the `List-Unsubscribe` header of newsletters.
+///
+/// Mail clients POST `List-Unsubscribe=One-Click` to this endpoint (RFC 8058): the body
+/// is ignored and unsubscribing twice is not an error.
+#[tracing::instrument(name = "Unsubscribe a subscriber", skip(state, params), fields(subscriber_id = %params.subscriber_id))]
+pub async fn unsubscribe(
+    State(state): State<AppState>,
+    Query(params): Query<UnsubscribeParams>,
+) -> AppResult<StatusCode> {
+    if !is_valid_unsubscribe_request(&params, &state.hmac_key) {
+        return Ok(StatusCode::UNAUTHORIZED);
+    }
+
+    unsubscribe_subscriber(&state.db, params.subscriber_id)
+        .await
+        .context("Failed to update subscriber status.")?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Check that the unsubscribe token was issued for the subscriber.
+pub fn is_valid_unsubscribe_request(params: &UnsubscribeParams, hmac_key: &Secret<String>) -> bool {
+    UnsubscribeToken::try_from(params.token.clone())
+        .map(|token| token.is_valid_for(params.subscriber_id, hmac_key))
+        .unwrap_or(false)
+}
+
+struct ExistingSubscriber {
+    id: Uuid,
+    name: String,
+}
+
+#[tracing::instrument(name = "Get subscriber by email", skip(transaction, email))]
+async fn get_subscriber_by_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &Email,
+) -> Result<Option<ExistingSubscriber>, sqlx::Error> {
+    sqlx::query_as!(
+        ExistingSubscriber,
+        r#"select id, name from subscriptions where email = $1 for update"#,
+        email.as_ref(),
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+}
+
+/// Update an existing subscriber who asks to join a list and revoke their previous
+/// confirmation tokens for that list.
+///
+/// Unless they are confirmed on another list, their name is replaced and `subscribed_at`
+/// is reset as well, so that the new request is not purged as an old unconfirmed
+/// subscription.
+#[tracing::instrument(
+    name = "Reset subscriber to pending confirmation",
+    skip(transaction, subscriber)
+)]
+async fn reset_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    list_id: Uuid,
     subscriber: &NewSubscriber,
-) -> Result<Uuid, sqlx::Error> {
-    let subscriber_id = Uuid::new_v4();
+) -> Result<(), sqlx::Error> {
     let query = sqlx::query!(
-        r#"insert into subscriptions (id, email, name, subscribed_at, status) values ($1, $2, $3, $4, 'pending_confirmation')"#,
+        r#"update subscriptions
+        set
+            name = case when status = 'confirmed' then name else $2 end,
+            subscribed_at = case when status = 'confirmed' then subscribed_at else now() end
+        where id = $1"#,
         subscriber_id,
-        subscriber.email.as_ref(),
         subscriber.name.as_ref(),
-        chrono::Utc::now(),
+    );
+    transaction.execute(query).await?;
+
+    revoke_tokens(transaction, subscriber_id, list_id).await
+}
+
+#[tracing::instrument(name = "Get list membership status", skip(transaction))]
+async fn get_membership_status(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    list_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"select status from list_memberships
+        where subscriber_id = $1 and list_id = $2
+        for update"#,
+        subscriber_id,
+        list_id,
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+}
+
+/// Add the subscriber to the list, or put them back to `pending_confirmation` on it.
+#[tracing::instrument(name = "Add subscriber to a list", skip(transaction))]
+async fn join_list(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    list_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"insert into list_memberships (subscriber_id, list_id, status, subscribed_at)
+        values ($1, $2, 'pending_confirmation', now())
+        on conflict (subscriber_id, list_id) do update
+        set status = 'pending_confirmation', subscribed_at = now()"#,
+        subscriber_id,
+        list_id,
+    );
+
+    transaction.execute(query).await?;
+
+    Ok(())
+}
+
+/// Sum the list memberships of a subscriber up in `subscriptions.status`: `confirmed` on
+/// any list, else `pending_confirmation` on any list, else `unsubscribed`.
+#[tracing::instrument(name = "Refresh subscriber status", skip(transaction))]
+pub async fn refresh_subscriber_status(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"update subscriptions
+        set status = (
+            select case
+                when bool_or(m.status = 'confirmed') then 'confirmed'
+                when bool_or(m.status = 'pending_confirmation') then 'pending_confirmation'
+                else 'unsubscribed'
+            end
+            from list_memberships m
+            where m.subscriber_id = $1
+        )
+        where id = $1"#,
+        subscriber_id,
     );
 
     transaction.execute(query).await?;
 
-    Ok(subscriber_id)
+    Ok(())
+}
+
+/// The lists a subscriber asked to join but did not confirm yet.
+#[tracing::instrument(name = "Get pending lists", skip(transaction))]
+async fn get_pending_lists(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<Vec<ListRef>, sqlx::Error> {
+    sqlx::query_as!(
+        ListRef,
+        r#"select l.list_id, l.name
+        from list_memberships m
+        join lists l on l.list_id = m.list_id
+        where m.subscriber_id = $1 and m.status = 'pending_confirmation'
+        order by l.name"#,
+        subscriber_id,
+    )
+    .fetch_all(&mut **transaction)
+    .await
+}
+
+/// Insert the subscriber, returning `None` when the email address is already taken.
+///
+/// A concurrent request inserting the same address makes this wait until it commits,
+/// instead of failing on the unique constraint.
+#[instrument(name = "inserting new subscriber into the database", skip(transaction, subscriber), fields(email = %subscriber.email, name = %subscriber.name))]
+async fn insert_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber: &NewSubscriber,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"insert into subscriptions (id, email, name, subscribed_at, status)
+        values ($1, $2, $3, $4, 'pending_confirmation')
+        on conflict (email) do nothing
+        returning id"#,
+        Uuid::new_v4(),
+        subscriber.email.as_ref(),
+        subscriber.name.as_ref(),
+        chrono::Utc::now(),
+    )
+    .fetch_optional(&mut **transaction)
+    .await
 }
 
 #[tracing::instrument(
     name = "Send a confirmation email to a new subscriber",
-    skip(email_client, new_subscriber, base_url, subscription_token)
+    skip(email_client, recipient, name, base_url, subscription_token)
 )]
 pub async fn send_confirmation_email(
     email_client: &EmailClient,
-    new_subscriber: &NewSubscriber,
+    recipient: &Email,
+    name: &str,
+    list_name: &str,
     base_url: &str,
     subscription_token: &str,
-) -> Result<(), reqwest::Error> {
+) -> Result<(), anyhow::Error> {
     let confirmation_link = format!(
-        "{}/api/v1/subscriptions/confirm?subscription_token={}",
+        "{}/subscriptions/confirm?subscription_token={}",
         base_url, subscription_token
     );
 
-    let plain_body = format!(
-        "Welcome to our newsletter!\nVisit {} to confirm your subscription.",
-        confirmation_link
-    );
+    let email = ConfirmationEmail {
+        name,
+        list_name,
+        confirmation_link: &confirmation_link,
+    }
+    .render()
+    .context("Failed to render the confirmation email.")?;
+
+    email_client
+        .send_email(
+            recipient,
+            &email.subject,
+            &email.html_body,
+            &email.text_body,
+        )
+        .await?;
+
+    Ok(())
+}
 
-    let html_body = format!(
-        "Welcome to our newsletter!<br />\
-        Click <a href=\"{}\">here</a> to confirm your subscription.",
-        confirmation_link
+#[tracing::instrument(
+    name = "Send a welcome email to a confirmed subscriber",
+    skip(state, token),
+    fields(subscriber_id = %token.subscriber_id)
+)]
+async fn send_welcome_email(state: &AppState, token: &StoredToken) -> Result<(), anyhow::Error> {
+    let recipient = Email::try_from(token.subscriber_email.clone())?;
+    let unsubscribe_token = UnsubscribeToken::generate(token.subscriber_id, &state.hmac_key);
+    let unsubscribe_url = format!(
+        "{}/unsubscribe?subscriber_id={}&token={}",
+        state.base_url, token.subscriber_id, unsubscribe_token
     );
 
-    email_client
-        .send_email(&new_subscriber.email, "Welcome!", &html_body, &plain_body)
+    let email = WelcomeEmail {
+        name: &token.subscriber_name,
+        list_name: &token.list_name,
+        unsubscribe_url: &unsubscribe_url,
+    }
+    .render()
+    .context("Failed to render the welcome email.")?;
+
+    state
+        .email_client
+        .send_email(
+            &recipient,
+            &email.subject,
+            &email.html_body,
+            &email.text_body,
+        )
         .await?;
 
     Ok(())
@@ -137,6 +551,31 @@ fn generate_subscription_token() -> String {
         .collect()
 }
 
+/// Generate a token confirming the subscriber on the list and store it with its expiry.
+async fn issue_confirmation_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    list_id: Uuid,
+    settings: &SubscriptionSettings,
+) -> Result<String, anyhow::Error> {
+    let subscription_token = generate_subscription_token();
+    let expires_at = Utc::now()
+        + chrono::Duration::from_std(settings.confirmation_token_ttl())
+            .context("The confirmation token TTL is out of range.")?;
+
+    store_token(
+        transaction,
+        subscriber_id,
+        list_id,
+        &subscription_token,
+        expires_at,
+    )
+    .await
+    .context("Failed to store the confirmation token for a subscriber.")?;
+
+    Ok(subscription_token)
+}
+
 #[tracing::instrument(
     name = "Store subscription token in the database",
     skip(transaction, subscription_token)
@@ -144,13 +583,34 @@ fn generate_subscription_token() -> String {
 pub async fn store_token(
     transaction: &mut Transaction<'_, Postgres>,
     subscriber_id: Uuid,
+    list_id: Uuid,
     subscription_token: &str,
+    expires_at: DateTime<Utc>,
 ) -> Result<(), sqlx::Error> {
     let query = sqlx::query!(
-        r#"insert into subscription_tokens (subscription_token, subscriber_id)
-        values ($1, $2)"#,
+        r#"insert into subscription_tokens (subscription_token, subscriber_id, list_id, created_at, expires_at)
+        values ($1, $2, $3, now(), $4)"#,
         subscription_token,
-        subscriber_id
+        subscriber_id,
+        list_id,
+        expires_at
+    );
+
+    transaction.execute(query).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Revoke subscription tokens", skip(transaction))]
+async fn revoke_tokens(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    list_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"delete from subscription_tokens where subscriber_id = $1 and list_id = $2"#,
+        subscriber_id,
+        list_id,
     );
 
     transaction.execute(query).await?;
@@ -158,10 +618,35 @@ pub async fn store_token(
     Ok(())
 }
 
-#[tracing::instrument(name = "Mark subscriber as confirmed", skip(subscriber_id, pool))]
-pub async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
+#[tracing::instrument(
+    name = "Mark subscriber as confirmed",
+    skip(subscriber_id, transaction)
+)]
+pub async fn confirm_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    list_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"update list_memberships set status = 'confirmed'
+        where subscriber_id = $1 and list_id = $2"#,
+        subscriber_id,
+        list_id,
+    );
+
+    transaction.execute(query).await?;
+
+    refresh_subscriber_status(transaction, subscriber_id).await
+}
+
+/// Unsubscribe a subscriber from every list.
+#[tracing::instrument(name = "Mark subscriber as unsubscribed", skip(subscriber_id, pool))]
+pub async fn unsubscribe_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        r#"update subscriptions set status = 'confirmed' where id = $1"#,
+        r#"with memberships as (
+            update list_memberships set status = 'unsubscribed' where subscriber_id = $1
+        )
+        update subscriptions set status = 'unsubscribed' where id = $1"#,
         subscriber_id,
     )
     .execute(pool)
@@ -170,18 +655,71 @@ pub async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<()
     Ok(())
 }
 
-#[tracing::instrument(name = "Get subscriber_id from token", skip(subscription_token, pool))]
-pub async fn get_subscriber_id_from_token(
-    pool: &PgPool,
+struct StoredToken {
+    subscriber_id: Uuid,
+    subscriber_email: String,
+    subscriber_name: String,
+    list_id: Uuid,
+    list_name: String,
+    /// The status of the subscriber on the list the token confirms.
+    membership_status: String,
+    expires_at: DateTime<Utc>,
+    consumed_at: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(name = "Get subscription token", skip(subscription_token, transaction))]
+async fn get_token(
+    transaction: &mut Transaction<'_, Postgres>,
     subscription_token: &str,
-) -> Result<Option<Uuid>, sqlx::Error> {
-    let result = sqlx::query!(
-        "SELECT subscriber_id FROM subscription_tokens \
-      WHERE subscription_token = $1",
+) -> Result<Option<StoredToken>, sqlx::Error> {
+    sqlx::query_as!(
+        StoredToken,
+        r#"select
+            t.subscriber_id,
+            s.email as subscriber_email,
+            s.name as subscriber_name,
+            t.list_id,
+            l.name as list_name,
+            m.status as membership_status,
+            t.expires_at,
+            t.consumed_at
+        from subscription_tokens t
+        join subscriptions s on s.id = t.subscriber_id
+        join lists l on l.list_id = t.list_id
+        join list_memberships m on m.subscriber_id = t.subscriber_id and m.list_id = t.list_id
+        where t.subscription_token = $1
+        for update"#,
         subscription_token,
     )
-    .fetch_optional(pool)
-    .await?;
+    .fetch_optional(&mut **transaction)
+    .await
+}
+
+/// Mark the token as used and revoke the other tokens of the subscriber for the same
+/// list.
+#[tracing::instrument(
+    name = "Consume subscription token",
+    skip(token, subscription_token, transaction)
+)]
+async fn consume_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    token: &StoredToken,
+    subscription_token: &str,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"update subscription_tokens set consumed_at = now() where subscription_token = $1"#,
+        subscription_token,
+    );
+    transaction.execute(query).await?;
 
-    Ok(result.map(|r| r.subscriber_id))
+    let query = sqlx::query!(
+        r#"delete from subscription_tokens
+        where subscriber_id = $1 and list_id = $2 and subscription_token <> $3"#,
+        token.subscriber_id,
+        token.list_id,
+        subscription_token,
+    );
+    transaction.execute(query).await?;
+
+    Ok(())
 }
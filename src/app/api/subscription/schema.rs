@@ -1,19 +1,31 @@
 use serde::Deserialize;
+use uuid::Uuid;
 
-use crate::domain::subscriber::{email::Email, name::Name, NewSubscriber};
+use crate::{
+    app::error::FieldErrors,
+    domain::subscriber::{email::Email, name::Name, NewSubscriber},
+};
 
 #[derive(Deserialize)]
 pub struct SubscribeBody {
     pub email: String,
     pub name: String,
+    /// The slug of the list to join, the default list when missing.
+    #[serde(default)]
+    pub list: Option<String>,
 }
 
 impl TryFrom<SubscribeBody> for NewSubscriber {
-    type Error = String;
+    type Error = FieldErrors;
     fn try_from(value: SubscribeBody) -> Result<Self, Self::Error> {
-        let name = Name::try_from(value.name)?;
-        let email = Email::try_from(value.email)?;
-        Ok(Self { name, email })
+        let mut errors = FieldErrors::default();
+        let name = errors.check("name", Name::try_from(value.name));
+        let email = errors.check("email", Email::try_from(value.email));
+
+        match (name, email) {
+            (Some(name), Some(email)) => Ok(Self { name, email }),
+            _ => Err(errors),
+        }
     }
 }
 
@@ -21,3 +33,14 @@ impl TryFrom<SubscribeBody> for NewSubscriber {
 pub struct ConfirmParams {
     pub subscription_token: String,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct ResendConfirmationBody {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnsubscribeParams {
+    pub subscriber_id: Uuid,
+    pub token: String,
+}
@@ -0,0 +1,11 @@
+use crate::app::AppState;
+use axum::{routing::get, Router};
+
+pub mod route;
+pub mod schema;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/tracking/open", get(route::track_open))
+        .route("/tracking/click", get(route::track_click))
+}
@@ -0,0 +1,210 @@
+use anyhow::Context;
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use secrecy::Secret;
+use sqlx::{Executor, PgPool};
+use uuid::Uuid;
+
+use super::schema;
+use crate::{
+    app::AppState,
+    domain::subscriber::tracking_token::{TrackedEvent, TrackingToken},
+};
+
+/// A transparent 1x1 GIF.
+const PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+/// The tracking pixel of an issue, recording the first time the subscriber opened it.
+///
+/// The pixel is returned whatever happens, so that mail clients never show a broken
+/// image.
+#[tracing::instrument(name = "Track an open", skip(state, params), fields(newsletter_issue_id = %params.issue_id, subscriber_id = %params.subscriber_id))]
+pub async fn track_open(
+    State(state): State<AppState>,
+    Query(params): Query<schema::OpenParams>,
+) -> Response {
+    let event = TrackedEvent::Open;
+    if is_valid_tracking_link(
+        params.issue_id,
+        params.subscriber_id,
+        &params.token,
+        event,
+        &state.hmac_key,
+    ) {
+        if let Err(e) = record_open(&state.db, params.issue_id, params.subscriber_id).await {
+            tracing::error!(error.cause_chain = ?e, "Failed to record an open");
+        }
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, "image/gif"),
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        PIXEL,
+    )
+        .into_response()
+}
+
+/// Record that the subscriber followed a link of an issue, then redirect them to it.
+///
+/// Following a link also counts as opening the issue, for mail clients that block
+/// images. Links whose token does not match their target are rejected, so that they
+/// can't redirect anywhere else.
+#[tracing::instrument(name = "Track a click", skip(state, params), fields(newsletter_issue_id = %params.issue_id, subscriber_id = %params.subscriber_id))]
+pub async fn track_click(
+    State(state): State<AppState>,
+    Query(params): Query<schema::ClickParams>,
+) -> Response {
+    let event = TrackedEvent::Click { url: &params.url };
+    if !is_valid_tracking_link(
+        params.issue_id,
+        params.subscriber_id,
+        &params.token,
+        event,
+        &state.hmac_key,
+    ) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let Ok(location) = HeaderValue::try_from(params.url.as_str()) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    if let Err(e) = record_click(
+        &state.db,
+        params.issue_id,
+        params.subscriber_id,
+        &params.url,
+    )
+    .await
+    {
+        tracing::error!(error.cause_chain = ?e, "Failed to record a click");
+    }
+
+    (StatusCode::SEE_OTHER, [(header::LOCATION, location)]).into_response()
+}
+
+fn is_valid_tracking_link(
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    token: &str,
+    event: TrackedEvent<'_>,
+    hmac_key: &Secret<String>,
+) -> bool {
+    match TrackingToken::try_from(token.to_owned()) {
+        Ok(token) => token.is_valid_for(newsletter_issue_id, subscriber_id, event, hmac_key),
+        Err(_) => false,
+    }
+}
+
+/// Record the first open of a tracked issue, unless the subscriber opted out since it
+/// was sent.
+#[tracing::instrument(skip(pool))]
+async fn record_open(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        insert into issue_opens (newsletter_issue_id, subscriber_id)
+        select i.newsletter_issue_id, s.id
+        from newsletter_issues i, subscriptions s
+        where i.newsletter_issue_id = $1
+            and s.id = $2
+            and i.tracking
+            and not s.tracking_opt_out
+        on conflict do nothing
+        "#,
+        newsletter_issue_id,
+        subscriber_id,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the open of an issue.")?;
+
+    Ok(())
+}
+
+/// Count a click on a link of a tracked issue, unless the subscriber opted out since it
+/// was sent.
+#[tracing::instrument(skip(pool, url))]
+async fn record_click(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    url: &str,
+) -> Result<(), anyhow::Error> {
+    record_open(pool, newsletter_issue_id, subscriber_id).await?;
+
+    sqlx::query!(
+        r#"
+        insert into issue_clicks (newsletter_issue_id, subscriber_id, url)
+        select i.newsletter_issue_id, s.id, $3
+        from newsletter_issues i, subscriptions s
+        where i.newsletter_issue_id = $1
+            and s.id = $2
+            and i.tracking
+            and not s.tracking_opt_out
+        on conflict (newsletter_issue_id, subscriber_id, url) do update
+        set n_clicks = issue_clicks.n_clicks + 1
+        "#,
+        newsletter_issue_id,
+        subscriber_id,
+        url,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the click on a link of an issue.")?;
+
+    Ok(())
+}
+
+/// Stop tracking a subscriber, forgetting what was recorded about them so far.
+#[tracing::instrument(skip(pool))]
+pub async fn opt_out_of_tracking(pool: &PgPool, subscriber_id: Uuid) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let query = sqlx::query!(
+        r#"update subscriptions set tracking_opt_out = true where id = $1"#,
+        subscriber_id,
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to store the tracking opt-out of the subscriber.")?;
+    let query = sqlx::query!(
+        r#"delete from issue_opens where subscriber_id = $1"#,
+        subscriber_id,
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to delete the opens of the subscriber.")?;
+    let query = sqlx::query!(
+        r#"delete from issue_clicks where subscriber_id = $1"#,
+        subscriber_id,
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to delete the clicks of the subscriber.")?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to opt out of tracking.")?;
+
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// The query parameters of the tracking pixel of an issue.
+#[derive(Debug, Deserialize)]
+pub struct OpenParams {
+    pub issue_id: Uuid,
+    pub subscriber_id: Uuid,
+    pub token: String,
+}
+
+/// The query parameters of a tracked link.
+#[derive(Debug, Deserialize)]
+pub struct ClickParams {
+    pub issue_id: Uuid,
+    pub subscriber_id: Uuid,
+    /// Where the link points to.
+    pub url: String,
+    pub token: String,
+}
@@ -1,13 +1,17 @@
-use crate::app::AppState;
+use crate::app::{rate_limit::limit_logins, AppState};
 use axum::routing::{get, post};
-use axum::Router;
+use axum::{middleware, Router};
 
 pub mod route;
 pub mod schema;
 
-pub(crate) fn router() -> Router<AppState> {
+pub(crate) fn router(state: &AppState) -> Router<AppState> {
     Router::new()
         .route("/users", post(route::create_user))
-        .route("/users/login", post(route::login_user))
+        .route(
+            "/users/login",
+            post(route::login_user)
+                .layer(middleware::from_fn_with_state(state.clone(), limit_logins)),
+        )
         .route("/whoami", get(route::get_current_user))
 }
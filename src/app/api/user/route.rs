@@ -11,14 +11,17 @@ use super::AppState;
 
 use crate::app::authentication::{compute_password_hash, validate_credentials, Credentials};
 use crate::app::error::{AppError, AppResult};
+use crate::app::extractor::api_json::ApiJson;
 use crate::app::extractor::authorization_header::ApiToken;
 use crate::telemetry::spawn_blocking_with_tracing;
 
 #[tracing::instrument(name = "Create new user", skip(state, body))]
 pub async fn create_user(
     State(state): State<AppState>,
-    Json(body): Json<CreateUserRequestBody>,
+    ApiJson(body): ApiJson<CreateUserRequestBody>,
 ) -> AppResult<Json<CreateUserResponseBody>> {
+    body.validate()?;
+
     let password_hash =
         spawn_blocking_with_tracing(move || compute_password_hash(Secret::new(body.password)))
             .await
@@ -42,16 +45,16 @@ pub async fn create_user(
 #[tracing::instrument(skip(state, body), fields(username=tracing::field::Empty, user_id=tracing::field::Empty))]
 pub async fn login_user(
     State(state): State<AppState>,
-    Json(body): Json<LoginUserRequestBody>,
+    ApiJson(body): ApiJson<LoginUserRequestBody>,
 ) -> AppResult<Json<LoginUserResponseBody>> {
     let credentials = Credentials {
         username: body.username,
         password: Secret::new(body.password),
     };
-    tracing::Span::current().record("username", &tracing::field::display(&credentials.username));
+    tracing::Span::current().record("username", tracing::field::display(&credentials.username));
     match validate_credentials(credentials, &state.db).await {
         Ok(user_id) => {
-            tracing::Span::current().record("user_id", &tracing::field::display(&user_id));
+            tracing::Span::current().record("user_id", tracing::field::display(&user_id));
             Ok(Json(LoginUserResponseBody {
                 token: ApiToken { user_id }.to_jwt(&state),
             }))
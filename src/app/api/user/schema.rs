@@ -1,9 +1,70 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{app::error::FieldErrors, domain::validation::ValidationError};
+
+const MAX_USERNAME_LENGTH: usize = 64;
+const MIN_PASSWORD_LENGTH: usize = 12;
+const MAX_PASSWORD_LENGTH: usize = 128;
+
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct CreateUserRequestBody {
     pub username: String,
     pub password: String,
 }
 
+impl CreateUserRequestBody {
+    /// Check every field, reporting all the rejected ones at once.
+    pub fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::default();
+        errors.check("username", validate_username(&self.username));
+        errors.check("password", validate_password(&self.password));
+        errors.into_result()
+    }
+}
+
+fn validate_username(username: &str) -> Result<(), ValidationError> {
+    if username.trim().is_empty() {
+        return Err(ValidationError::new("empty", "username is empty"));
+    }
+
+    if username.graphemes(true).count() > MAX_USERNAME_LENGTH {
+        return Err(ValidationError::new(
+            "too_long",
+            format!(
+                "username must be at most {} characters",
+                MAX_USERNAME_LENGTH
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_password(password: &str) -> Result<(), ValidationError> {
+    let length = password.graphemes(true).count();
+    if length < MIN_PASSWORD_LENGTH {
+        return Err(ValidationError::new(
+            "too_short",
+            format!(
+                "password must be at least {} characters",
+                MIN_PASSWORD_LENGTH
+            ),
+        ));
+    }
+
+    if length > MAX_PASSWORD_LENGTH {
+        return Err(ValidationError::new(
+            "too_long",
+            format!(
+                "password must be at most {} characters",
+                MAX_PASSWORD_LENGTH
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct CreateUserResponseBody {
     pub token: String,
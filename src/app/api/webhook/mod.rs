@@ -0,0 +1,9 @@
+use crate::app::AppState;
+use axum::{routing::post, Router};
+
+pub mod route;
+pub mod schema;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/webhooks/postmark", post(route::postmark_webhook))
+}
@@ -0,0 +1,127 @@
+use anyhow::Context;
+use axum::{body::Bytes, extract::State, http::StatusCode, Json};
+use axum_extra::{
+    headers::{authorization::Basic, Authorization},
+    TypedHeader,
+};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::{Executor, PgPool};
+
+use super::schema::PostmarkEvent;
+use crate::{
+    app::{
+        error::{AppError, AppResult},
+        AppState,
+    },
+    domain::subscriber::email::Email,
+    email::suppression::{suppress, SuppressionReason},
+};
+
+/// Suppress the addresses Postmark reports as hard-bounced or complaining about spam,
+/// and unsubscribe them from every list.
+///
+/// Postmark is configured to send the shared secret as the password of HTTP Basic
+/// credentials, e.g. `https://postmark:<secret>@example.com/api/v1/webhooks/postmark`.
+/// Other events are acknowledged and ignored, so that Postmark does not retry them.
+///
+/// The body is only parsed once the secret is checked, so that anonymous callers learn
+/// nothing about the expected events.
+#[tracing::instrument(name = "Handle a Postmark webhook", skip_all)]
+pub async fn postmark_webhook(
+    State(state): State<AppState>,
+    authorization: Option<TypedHeader<Authorization<Basic>>>,
+    body: Bytes,
+) -> AppResult<StatusCode> {
+    let password = authorization.as_ref().map(|header| header.password());
+    if !is_valid_secret(password, &state.webhook_secret) {
+        return Err(AppError::Authentication(
+            "Invalid webhook credentials.".to_owned(),
+        ));
+    }
+
+    let Json(event) = Json::<PostmarkEvent>::from_bytes(&body)?;
+    let (email, reason) = match event {
+        PostmarkEvent::Bounce { kind, email } if kind == "HardBounce" => {
+            (email, SuppressionReason::HardBounce)
+        }
+        PostmarkEvent::SpamComplaint { email } => (email, SuppressionReason::SpamComplaint),
+        _ => return Ok(StatusCode::OK),
+    };
+    let Ok(email) = Email::try_from(email) else {
+        tracing::warn!("Ignoring a webhook about an invalid address");
+        return Ok(StatusCode::OK);
+    };
+
+    suppress_address(&state.db, &email, reason, &state.hmac_key).await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Compare the digests of the secrets, so that the time taken does not reveal how much
+/// of the secret was guessed.
+fn is_valid_secret(password: Option<&str>, secret: &Secret<String>) -> bool {
+    match password {
+        Some(password) => {
+            Sha256::digest(password.as_bytes()) == Sha256::digest(secret.expose_secret().as_bytes())
+        }
+        None => false,
+    }
+}
+
+/// Store the suppression of the address and move its subscriber, if any, out of every
+/// list.
+#[tracing::instrument(skip(pool, email, hmac_key))]
+async fn suppress_address(
+    pool: &PgPool,
+    email: &Email,
+    reason: SuppressionReason,
+    hmac_key: &Secret<String>,
+) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    suppress(&mut *transaction, email, reason, hmac_key).await?;
+
+    let subscriber_id = sqlx::query_scalar!(
+        r#"select id from subscriptions where email = $1 for update"#,
+        email.as_ref(),
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to look up the subscriber in the database.")?;
+
+    if let Some(subscriber_id) = subscriber_id {
+        let query = sqlx::query!(
+            r#"update list_memberships set status = 'unsubscribed' where subscriber_id = $1"#,
+            subscriber_id,
+        );
+        transaction
+            .execute(query)
+            .await
+            .context("Failed to unsubscribe the subscriber from their lists.")?;
+
+        let status = match reason {
+            SuppressionReason::SpamComplaint => "complained",
+            _ => "bounced",
+        };
+        let query = sqlx::query!(
+            r#"update subscriptions set status = $2 where id = $1"#,
+            subscriber_id,
+            status,
+        );
+        transaction
+            .execute(query)
+            .await
+            .context("Failed to update the status of the subscriber.")?;
+    }
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to suppress an address.")?;
+
+    Ok(())
+}
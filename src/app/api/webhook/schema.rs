@@ -0,0 +1,24 @@
+use serde::Deserialize;
+
+/// A bounce or spam complaint webhook sent by Postmark, only the fields we use.
+///
+/// See <https://postmarkapp.com/developer/webhooks/bounce-webhook> and
+/// <https://postmarkapp.com/developer/webhooks/spam-complaint-webhook>.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "RecordType")]
+pub enum PostmarkEvent {
+    Bounce {
+        /// `HardBounce`, `SoftBounce`, `Transient`...
+        #[serde(rename = "Type")]
+        kind: String,
+        #[serde(rename = "Email")]
+        email: String,
+    },
+    SpamComplaint {
+        #[serde(rename = "Email")]
+        email: String,
+    },
+    /// Deliveries, opens, clicks... which are not handled.
+    #[serde(other)]
+    Other,
+}
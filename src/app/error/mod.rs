@@ -1,41 +1,131 @@
-use std::result;
+use std::{result, time::Duration};
 
-use axum::http::StatusCode;
+use axum::extract::rejection::JsonRejection;
+use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 
+use crate::domain::validation::ValidationError;
+
 mod schema;
 
-///
+/// Challenge sent along with `401 Unauthorized` responses to API clients.
+const WWW_AUTHENTICATE_CHALLENGE: &str = r#"Bearer, Basic realm="zero2prod", charset="UTF-8""#;
+
+/// Stable error codes returned in the `code` field of JSON error bodies.
+pub mod code {
+    pub const UNEXPECTED: u16 = 0;
+    /// One or more fields were rejected, see `details`.
+    pub const INVALID_FIELDS: u16 = 1000;
+    /// The body is not valid JSON.
+    pub const MALFORMED_JSON: u16 = 1001;
+    /// The body is valid JSON, but does not match the expected structure.
+    pub const INVALID_JSON_DATA: u16 = 1002;
+    /// The request does not have a `Content-Type: application/json` header.
+    pub const UNSUPPORTED_CONTENT_TYPE: u16 = 1003;
+    /// The body could not be read.
+    pub const UNREADABLE_BODY: u16 = 1004;
+    /// Too many requests were sent, retry after the delay in the `Retry-After` header.
+    pub const RATE_LIMITED: u16 = 1005;
+}
+
+/// The result of handlers and the helpers they call, failing with an [`AppError`] by default.
 pub type AppResult<T, E = AppError> = result::Result<T, E>;
 
+/// A rejected input field.
+#[derive(Debug)]
+pub struct FieldError {
+    pub field: String,
+    pub error: ValidationError,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, error: ValidationError) -> Self {
+        Self {
+            field: field.into(),
+            error,
+        }
+    }
+}
+
+/// Collect the errors of several fields validated independently.
+#[derive(Debug, Default)]
+pub struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    /// Keep the value of a field, or record why it was rejected.
+    pub fn check<T>(&mut self, field: &str, outcome: Result<T, ValidationError>) -> Option<T> {
+        match outcome {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.0.push(FieldError::new(field, error));
+                None
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &FieldError> {
+        self.0.iter()
+    }
+
+    /// Fail if any field was rejected.
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
 /// A common error type that can be used throughout the API.
 ///
 /// Can be returned in a `Result` from an API handler function.
 ///
 /// For convenience, this represents both API errors as well as internal recoverable errors,
-/// and maps them to appropriate status codes along with at least a minimally useful error
-/// message in a plain text body, or a JSON body in the case of `UnprocessableEntity`.
+/// and maps them to appropriate status codes along with a JSON `Error` body for invalid
+/// requests and unexpected errors.
 #[derive(thiserror::Error, Debug)]
 pub enum AppError {
+    #[error("Invalid fields: {0:?}")]
+    Validation(Vec<FieldError>),
+    #[error(transparent)]
+    JsonRejection(#[from] JsonRejection),
     #[error("{0}")]
-    Validation(String),
+    Authentication(String),
     #[error("{0}")]
     Authorization(String),
+    /// Too many requests were sent, the client can retry after the duration.
+    #[error("Too many requests, retry in {0:?}")]
+    RateLimited(Duration),
     #[error(transparent)]
     Unexpected(#[from] anyhow::Error),
 }
 
 impl AppError {
+    /// Reject a single field.
+    pub fn invalid_field(field: &str, error: ValidationError) -> Self {
+        Self::Validation(vec![FieldError::new(field, error)])
+    }
+
     fn status_code(&self) -> StatusCode {
         match self {
             Self::Validation(_) => StatusCode::BAD_REQUEST,
+            Self::JsonRejection(rejection) => rejection.status(),
+            Self::Authentication(_) => StatusCode::UNAUTHORIZED,
             Self::Authorization(_) => StatusCode::UNAUTHORIZED,
+            Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
             Self::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
+impl From<FieldErrors> for AppError {
+    fn from(errors: FieldErrors) -> Self {
+        Self::Validation(errors.0)
+    }
+}
+
 /// Axum allows you to return `Result` from handler functions, but the error type
 /// also must be some sort of response type.
 ///
@@ -43,22 +133,83 @@ impl AppError {
 /// to the client.
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let status_code = self.status_code();
         match self {
             Self::Unexpected(ref e) => {
                 tracing::error!("{:?}", e);
                 (
-                    self.status_code(),
+                    status_code,
                     Json(schema::Error {
-                        code: 0,
+                        code: code::UNEXPECTED,
                         message: "Unexpected error".to_owned(),
                         details: None,
                     }),
                 )
                     .into_response()
             }
+            Self::Validation(errors) => {
+                tracing::info!(?errors, "Rejected invalid fields");
+                let details = errors
+                    .into_iter()
+                    .map(|e| schema::ErrorDetails {
+                        field: e.field,
+                        code: e.error.code.to_owned(),
+                        message: e.error.message,
+                    })
+                    .collect();
+                (
+                    status_code,
+                    Json(schema::Error {
+                        code: code::INVALID_FIELDS,
+                        message: "Some fields are invalid".to_owned(),
+                        details: Some(details),
+                    }),
+                )
+                    .into_response()
+            }
+            Self::JsonRejection(rejection) => {
+                tracing::info!("{}", rejection);
+                let code = match rejection {
+                    JsonRejection::JsonSyntaxError(_) => code::MALFORMED_JSON,
+                    JsonRejection::JsonDataError(_) => code::INVALID_JSON_DATA,
+                    JsonRejection::MissingJsonContentType(_) => code::UNSUPPORTED_CONTENT_TYPE,
+                    _ => code::UNREADABLE_BODY,
+                };
+                (
+                    status_code,
+                    Json(schema::Error {
+                        code,
+                        message: rejection.body_text(),
+                        details: None,
+                    }),
+                )
+                    .into_response()
+            }
+            Self::Authentication(ref e) => {
+                tracing::error!("{}", e);
+                (
+                    status_code,
+                    [(header::WWW_AUTHENTICATE, WWW_AUTHENTICATE_CHALLENGE)],
+                    (),
+                )
+                    .into_response()
+            }
+            Self::RateLimited(retry_after) => {
+                tracing::info!(?retry_after, "Rejected a rate limited request");
+                (
+                    status_code,
+                    [(header::RETRY_AFTER, retry_after.as_secs().to_string())],
+                    Json(schema::Error {
+                        code: code::RATE_LIMITED,
+                        message: "Too many requests, try again later".to_owned(),
+                        details: None,
+                    }),
+                )
+                    .into_response()
+            }
             ref e => {
                 tracing::error!("{}", e);
-                (self.status_code(), ()).into_response()
+                (status_code, ()).into_response()
             }
         }
     }
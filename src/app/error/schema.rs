@@ -8,5 +8,7 @@ pub struct Error {
 #[derive(serde::Serialize)]
 pub struct ErrorDetails {
     pub field: String,
+    /// Stable, machine-readable reason, e.g. `empty` or `too_long`.
+    pub code: String,
     pub message: String,
 }
@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use axum::{extract::FromRequestParts, http::request::Parts};
+use axum_extra::{
+    headers::{
+        authorization::{Basic, Bearer},
+        Authorization,
+    },
+    TypedHeader,
+};
+use secrecy::Secret;
+use uuid::Uuid;
+
+use super::authorization_header::ApiToken;
+use crate::app::authentication::{validate_credentials, AuthError, Credentials};
+use crate::app::error::AppError;
+use crate::app::AppState;
+
+/// Add this as a parameter to a handler function to require an authenticated API client.
+///
+/// Accepts either a JWT in the `Authorization: Bearer <token>` header or HTTP Basic
+/// credentials, which are checked against the `users` table.
+pub struct ApiUser {
+    pub user_id: Uuid,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for ApiUser
+where
+    AppState: Send + Sync,
+{
+    type Rejection = AppError;
+
+    #[tracing::instrument(name = "Authenticate API user", skip(parts, state), fields(user_id=tracing::field::Empty))]
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let user_id = if let Ok(TypedHeader(Authorization(bearer))) =
+            TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state).await
+        {
+            ApiToken::from_str(state, bearer.token())
+                .map_err(|_| AppError::Authentication("Invalid Authorization header.".to_owned()))?
+                .user_id
+        } else if let Ok(TypedHeader(Authorization(basic))) =
+            TypedHeader::<Authorization<Basic>>::from_request_parts(parts, state).await
+        {
+            let credentials = Credentials {
+                username: basic.username().to_owned(),
+                password: Secret::new(basic.password().to_owned()),
+            };
+            validate_credentials(credentials, &state.db)
+                .await
+                .map_err(|e| match e {
+                    AuthError::InvalidCredentials(_) => AppError::Authentication(e.to_string()),
+                    AuthError::Unexpected(_) => AppError::Unexpected(e.into()),
+                })?
+        } else {
+            return Err(AppError::Authentication(
+                "Missing Authorization header.".to_owned(),
+            ));
+        };
+
+        tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+
+        Ok(Self { user_id })
+    }
+}
@@ -91,9 +91,11 @@ where
         let auth_header: TypedHeader<Authorization<Bearer>> =
             TypedHeader::from_request_parts(parts, &state)
                 .await
-                .map_err(|_| AppError::Authorization("Missing Authorization header.".to_owned()))?;
+                .map_err(|_| {
+                    AppError::Authentication("Missing Authorization header.".to_owned())
+                })?;
 
         Self::from_str(&state, auth_header.token())
-            .map_err(|_| AppError::Authorization("Invalid Authorization header.".to_owned()))
+            .map_err(|_| AppError::Authentication("Invalid Authorization header.".to_owned()))
     }
 }
@@ -0,0 +1,74 @@
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+
+use crate::app::error::AppError;
+
+const FLASH_MESSAGES: &str = "flash_messages";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum FlashLevel {
+    Success,
+    Error,
+}
+
+/// A message kept in the session until the next page is rendered.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FlashMessage {
+    pub level: FlashLevel,
+    pub message: String,
+}
+
+impl FlashMessage {
+    pub fn is_error(&self) -> bool {
+        self.level == FlashLevel::Error
+    }
+}
+
+/// Flash messages of the current session.
+///
+/// Handlers push messages before redirecting, and the page they redirect to takes them
+/// out of the session so that each message is displayed only once.
+pub struct Flash(Session);
+
+impl Flash {
+    pub async fn success(&self, message: impl Into<String>) -> Result<(), anyhow::Error> {
+        self.push(FlashLevel::Success, message.into()).await
+    }
+
+    pub async fn error(&self, message: impl Into<String>) -> Result<(), anyhow::Error> {
+        self.push(FlashLevel::Error, message.into()).await
+    }
+
+    /// Remove and return the pending messages, oldest first.
+    pub async fn take(&self) -> Result<Vec<FlashMessage>, anyhow::Error> {
+        Ok(self
+            .0
+            .remove::<Vec<FlashMessage>>(FLASH_MESSAGES)
+            .await?
+            .unwrap_or_default())
+    }
+
+    async fn push(&self, level: FlashLevel, message: String) -> Result<(), anyhow::Error> {
+        let mut messages: Vec<FlashMessage> = self.0.get(FLASH_MESSAGES).await?.unwrap_or_default();
+        messages.push(FlashMessage { level, message });
+        self.0.insert(FLASH_MESSAGES, messages).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Flash
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let session = Session::from_request_parts(req, state)
+            .await
+            .map_err(|e| AppError::Unexpected(anyhow::anyhow!(e.1)))?;
+
+        Ok(Flash(session))
+    }
+}
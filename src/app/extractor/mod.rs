@@ -1,2 +1,5 @@
+pub mod api_json;
+pub mod api_user;
 pub mod authorization_header;
+pub mod flash;
 pub mod session_user;
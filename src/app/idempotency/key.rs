@@ -0,0 +1,96 @@
+use axum::http::HeaderMap;
+
+use crate::domain::validation::ValidationError;
+
+/// Name of the header clients use to make a request idempotent.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+const MAX_LENGTH: usize = 50;
+
+#[derive(Debug)]
+pub struct IdempotencyKey(String);
+
+impl IdempotencyKey {
+    /// Parse the optional `Idempotency-Key` header.
+    pub fn from_headers(headers: &HeaderMap) -> Result<Option<Self>, ValidationError> {
+        headers
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .map(|value| {
+                value
+                    .to_str()
+                    .map_err(|_| {
+                        ValidationError::new(
+                            "invalid_characters",
+                            "idempotency key contains invalid characters",
+                        )
+                    })
+                    .and_then(|value| Self::try_from(value.to_owned()))
+            })
+            .transpose()
+    }
+}
+
+impl TryFrom<String> for IdempotencyKey {
+    type Error = ValidationError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value.trim().is_empty() {
+            return Err(ValidationError::new("empty", "idempotency key is empty"));
+        }
+
+        if value.len() >= MAX_LENGTH {
+            return Err(ValidationError::new(
+                "too_long",
+                format!(
+                    "idempotency key must be shorter than {} characters",
+                    MAX_LENGTH
+                ),
+            ));
+        }
+
+        Ok(Self(value))
+    }
+}
+
+impl AsRef<str> for IdempotencyKey {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderMap, HeaderValue};
+
+    use super::{IdempotencyKey, IDEMPOTENCY_KEY_HEADER};
+
+    #[test]
+    fn empty_string_is_rejected() {
+        let key = "".to_string();
+        assert!(IdempotencyKey::try_from(key).is_err());
+    }
+
+    #[test]
+    fn a_key_of_50_characters_is_rejected() {
+        let key = "a".repeat(50);
+        assert!(IdempotencyKey::try_from(key).is_err());
+    }
+
+    #[test]
+    fn a_uuid_is_a_valid_key() {
+        let key = uuid::Uuid::new_v4().to_string();
+        assert!(IdempotencyKey::try_from(key).is_ok());
+    }
+
+    #[test]
+    fn a_missing_header_is_not_an_error() {
+        let headers = HeaderMap::new();
+        assert!(matches!(IdempotencyKey::from_headers(&headers), Ok(None)));
+    }
+
+    #[test]
+    fn a_blank_header_is_rejected() {
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_static(" "));
+        assert!(IdempotencyKey::from_headers(&headers).is_err());
+    }
+}
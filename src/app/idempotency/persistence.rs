@@ -0,0 +1,161 @@
+use anyhow::Context;
+use axum::{
+    body::{to_bytes, Body},
+    http::{Response, StatusCode},
+};
+use sqlx::{
+    postgres::{PgHasArrayType, PgTypeInfo},
+    Executor, PgPool, Postgres, Transaction,
+};
+use uuid::Uuid;
+
+use super::key::IdempotencyKey;
+
+#[derive(Debug, sqlx::Type)]
+#[sqlx(type_name = "header_pair")]
+struct HeaderPairRecord {
+    name: String,
+    value: Vec<u8>,
+}
+
+impl PgHasArrayType for HeaderPairRecord {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_header_pair")
+    }
+}
+
+/// What a handler should do after registering an idempotent request.
+pub enum NextAction {
+    /// The request is new: process it and store the response using the transaction.
+    StartProcessing(Box<Transaction<'static, Postgres>>),
+    /// The request was already processed: replay the stored response.
+    ReturnSavedResponse(Response<Body>),
+}
+
+/// Register the request as in flight, or fetch the response saved by an earlier attempt.
+///
+/// Concurrent duplicates block on the insert until the first request commits its
+/// transaction, at which point they find its saved response.
+#[tracing::instrument(
+    name = "Try processing idempotent request",
+    skip(pool, idempotency_key)
+)]
+pub async fn try_processing(
+    pool: &PgPool,
+    idempotency_key: &IdempotencyKey,
+    user_id: Uuid,
+) -> Result<NextAction, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let query = sqlx::query!(
+        r#"
+        insert into idempotency (user_id, idempotency_key, created_at)
+        values ($1, $2, now())
+        on conflict do nothing
+        "#,
+        user_id,
+        idempotency_key.as_ref()
+    );
+    let n_inserted_rows = transaction
+        .execute(query)
+        .await
+        .context("Failed to insert the idempotency key.")?
+        .rows_affected();
+
+    if n_inserted_rows > 0 {
+        Ok(NextAction::StartProcessing(Box::new(transaction)))
+    } else {
+        let saved_response = get_saved_response(pool, idempotency_key, user_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("We expected a saved response, we didn't find it"))?;
+        Ok(NextAction::ReturnSavedResponse(saved_response))
+    }
+}
+
+#[tracing::instrument(name = "Get saved response", skip(pool, idempotency_key))]
+pub async fn get_saved_response(
+    pool: &PgPool,
+    idempotency_key: &IdempotencyKey,
+    user_id: Uuid,
+) -> Result<Option<Response<Body>>, anyhow::Error> {
+    let saved_response = sqlx::query!(
+        r#"
+        select
+            response_status_code as "response_status_code!",
+            response_headers as "response_headers!: Vec<HeaderPairRecord>",
+            response_body as "response_body!"
+        from idempotency
+        where user_id = $1 and idempotency_key = $2
+        "#,
+        user_id,
+        idempotency_key.as_ref()
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the saved response.")?;
+
+    if let Some(r) = saved_response {
+        let status_code = StatusCode::from_u16(r.response_status_code.try_into()?)?;
+        let mut response = Response::builder().status(status_code);
+        for HeaderPairRecord { name, value } in r.response_headers {
+            response = response.header(name, value);
+        }
+        Ok(Some(response.body(Body::from(r.response_body))?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Store the response of an idempotent request and commit the transaction opened by
+/// [`try_processing`].
+#[tracing::instrument(name = "Save response", skip(transaction, idempotency_key, response))]
+pub async fn save_response(
+    mut transaction: Transaction<'static, Postgres>,
+    idempotency_key: &IdempotencyKey,
+    user_id: Uuid,
+    response: Response<Body>,
+) -> Result<Response<Body>, anyhow::Error> {
+    let (response_head, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX)
+        .await
+        .context("Failed to read the response body.")?;
+    let status_code = response_head.status.as_u16() as i16;
+    let headers = response_head
+        .headers
+        .iter()
+        .map(|(name, value)| HeaderPairRecord {
+            name: name.as_str().to_owned(),
+            value: value.as_bytes().to_owned(),
+        })
+        .collect::<Vec<_>>();
+
+    // `sqlx` cannot type-check the custom `header_pair[]` column at compile time.
+    let query = sqlx::query_unchecked!(
+        r#"
+        update idempotency
+        set
+            response_status_code = $3,
+            response_headers = $4,
+            response_body = $5
+        where user_id = $1 and idempotency_key = $2
+        "#,
+        user_id,
+        idempotency_key.as_ref(),
+        status_code,
+        headers,
+        body.as_ref()
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to save the response.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to save the response.")?;
+
+    Ok(Response::from_parts(response_head, Body::from(body)))
+}
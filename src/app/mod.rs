@@ -1,4 +1,7 @@
-use std::{io, net::IpAddr};
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+};
 
 use axum::{extract::FromRef, http::Request, Router};
 use axum_extra::extract::cookie::Key;
@@ -11,7 +14,10 @@ use tokio::net::TcpListener;
 use tower_http::trace::TraceLayer;
 use tower_sessions::{Expiry, SessionManagerLayer};
 
-use crate::{config::Settings, email::EmailClient};
+use crate::{
+    config::{RateLimitSettings, Settings, SubscriptionSettings},
+    email::{suppression::SuppressionList, EmailClient},
+};
 
 use self::{session_store::RedisStore, ui::not_found::not_found_page};
 
@@ -19,16 +25,22 @@ mod api;
 mod authentication;
 mod error;
 mod extractor;
+mod idempotency;
+mod rate_limit;
 mod session_store;
 mod ui;
 
 #[derive(Clone)]
 pub struct AppState {
     db: PgPool,
-    _cache: Pool<RedisConnectionManager>,
+    cache: Pool<RedisConnectionManager>,
     email_client: EmailClient,
     base_url: String,
     hmac_key: Secret<String>,
+    webhook_secret: Secret<String>,
+    subscriptions: SubscriptionSettings,
+    rate_limits: RateLimitSettings,
+    public_archive: bool,
 }
 
 impl FromRef<AppState> for Key {
@@ -37,13 +49,19 @@ impl FromRef<AppState> for Key {
     }
 }
 
-fn app_router() -> Router<AppState> {
-    ui::router().nest(
+fn app_router(state: &AppState) -> Router<AppState> {
+    ui::router(state).nest(
         "/api/v1",
         api::health::router()
-            .merge(api::subscription::router())
+            .merge(api::list::router())
+            .merge(api::subscriber::router())
+            .merge(api::delivery::router())
+            .merge(api::subscription::router(state))
             .merge(api::newsletter::router())
-            .merge(api::user::router()),
+            .merge(api::personal_data::router())
+            .merge(api::tracking::router())
+            .merge(api::webhook::router())
+            .merge(api::user::router(state)),
     )
 }
 
@@ -52,23 +70,19 @@ pub struct App {
     email_client: EmailClient,
     base_url: String,
     hmac_key: Secret<String>,
+    webhook_secret: Secret<String>,
+    subscriptions: SubscriptionSettings,
+    rate_limits: RateLimitSettings,
+    public_archive: bool,
 }
 
 impl App {
     pub async fn with(config: Settings) -> Self {
         // TODO do not take ownership of the config
-        let timeout = config.email_client.timeout();
-        let email_client = EmailClient::new(
-            config.email_client.base_url,
-            config
-                .email_client
-                .sender_email
-                .try_into()
-                .expect("the sender email should be valid"),
-            config.email_client.authorization_token,
-            timeout,
-        )
-        .expect("the email client should be available");
+        let email_client = config
+            .email_client
+            .client()
+            .expect("the email client should be available");
 
         let listener = tokio::net::TcpListener::bind(format!(
             "{}:{}",
@@ -82,6 +96,10 @@ impl App {
             email_client,
             base_url: config.application.base_url,
             hmac_key: config.application.hmac_key,
+            webhook_secret: config.email_client.webhook_secret,
+            public_archive: config.application.public_archive,
+            subscriptions: config.subscriptions,
+            rate_limits: config.rate_limits,
         }
     }
 
@@ -120,19 +138,30 @@ impl App {
             .with_secure(false)
             .with_expiry(Expiry::OnInactivity(Duration::minutes(10)));
 
-        let app = app_router()
-            .with_state(AppState {
-                db,
-                _cache: cache,
-                email_client: self.email_client,
-                base_url: self.base_url,
-                hmac_key: self.hmac_key.clone(),
-            })
+        let suppressions = SuppressionList::new(db.clone(), self.hmac_key.clone());
+        let state = AppState {
+            db,
+            cache,
+            email_client: self.email_client.with_suppressions(suppressions),
+            base_url: self.base_url,
+            hmac_key: self.hmac_key.clone(),
+            webhook_secret: self.webhook_secret,
+            subscriptions: self.subscriptions,
+            rate_limits: self.rate_limits,
+            public_archive: self.public_archive,
+        };
+        let app = app_router(&state)
+            .with_state(state)
             .layer(session_layer)
             .layer(trace_layer);
 
         let app = app.fallback(not_found_page);
 
-        axum::serve(self.listener, app.into_make_service()).await
+        // Rate limits need the address of the client.
+        axum::serve(
+            self.listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
     }
 }
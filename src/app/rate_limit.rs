@@ -0,0 +1,236 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use anyhow::Context;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, FromRef, Request, State},
+    http::{request::Parts, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use bb8::Pool;
+use bb8_redis::RedisConnectionManager;
+use sha2::{Digest, Sha256};
+
+use super::{error::AppError, AppState};
+use crate::{
+    config::{EndpointRateLimits, RateLimit, RateLimitSettings},
+    domain::subscriber::to_hex,
+};
+
+/// The largest body read to find out who a request is about, in bytes. Subscriptions
+/// and logins are far smaller.
+const MAX_BODY_SIZE: usize = 16 * 1024;
+
+/// The endpoints with a rate limit.
+#[derive(Clone, Copy, Debug)]
+enum Endpoint {
+    Subscribe,
+    Login,
+}
+
+impl Endpoint {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Subscribe => "subscribe",
+            Self::Login => "login",
+        }
+    }
+
+    /// The field of the JSON body naming who the request is about.
+    fn target_field(&self) -> &'static str {
+        match self {
+            Self::Subscribe => "email",
+            Self::Login => "username",
+        }
+    }
+}
+
+/// Counts requests in fixed windows stored in Redis, so that every instance of the
+/// application shares the same counters.
+#[derive(Clone)]
+pub struct RateLimiter {
+    cache: Pool<RedisConnectionManager>,
+    settings: RateLimitSettings,
+}
+
+impl FromRef<AppState> for RateLimiter {
+    fn from_ref(state: &AppState) -> Self {
+        Self {
+            cache: state.cache.clone(),
+            settings: state.rate_limits.clone(),
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Count the request per client address and per target, rejecting it with `429 Too
+    /// Many Requests` once either is over its limit.
+    ///
+    /// Requests are let through when Redis is unavailable, rather than locking everyone
+    /// out.
+    #[tracing::instrument(name = "Rate limit", skip_all, fields(endpoint = endpoint.name()))]
+    async fn limit(&self, endpoint: Endpoint, request: Request, next: Next) -> Response {
+        let (parts, body) = request.into_parts();
+        let Ok(body) = to_bytes(body, MAX_BODY_SIZE).await else {
+            return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+        };
+
+        let limits = self.limits(endpoint);
+        let mut counters = Vec::with_capacity(2);
+        if let Some(ip) = self.client_ip(&parts) {
+            counters.push((
+                self.key(endpoint, "client", &ip.to_string()),
+                limits.per_client,
+            ));
+        }
+        if let Some(target) = target(&body, endpoint.target_field()) {
+            counters.push((self.key(endpoint, "target", &target), limits.per_target));
+        }
+
+        for (key, limit) in counters {
+            match self.hit(&key, limit).await {
+                Ok(Some(retry_after)) => {
+                    return AppError::RateLimited(retry_after).into_response();
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::error!(error.cause_chain = ?e, "Failed to apply a rate limit");
+                }
+            }
+        }
+
+        next.run(Request::from_parts(parts, Body::from(body))).await
+    }
+
+    fn limits(&self, endpoint: Endpoint) -> EndpointRateLimits {
+        match endpoint {
+            Endpoint::Subscribe => self.settings.subscribe,
+            Endpoint::Login => self.settings.login,
+        }
+    }
+
+    fn key(&self, endpoint: Endpoint, kind: &str, id: &str) -> String {
+        format!(
+            "{}:{}:{}:{}",
+            self.settings.namespace,
+            endpoint.name(),
+            kind,
+            id
+        )
+    }
+
+    /// The address of the client, read from the header of the proxy when there is one.
+    ///
+    /// Proxies append the address they received the request from, so only the last one
+    /// can't be forged by the client.
+    fn client_ip(&self, parts: &Parts) -> Option<IpAddr> {
+        let forwarded = self.settings.client_ip_header.as_ref().and_then(|header| {
+            parts
+                .headers
+                .get(header.as_str())?
+                .to_str()
+                .ok()?
+                .rsplit(',')
+                .next()?
+                .trim()
+                .parse()
+                .ok()
+        });
+
+        forwarded.or_else(|| {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+        })
+    }
+
+    /// Count a request against `key`, returning how long to wait when it is over the
+    /// limit.
+    async fn hit(&self, key: &str, limit: RateLimit) -> Result<Option<Duration>, anyhow::Error> {
+        let mut connection = self
+            .cache
+            .get()
+            .await
+            .context("Failed to get a Redis connection from the pool.")?;
+
+        // The window starts with the first request, and is not extended by the next ones.
+        let (n_requests, ttl): (u32, i64) = redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(key)
+            .arg(0)
+            .arg("EX")
+            .arg(limit.window_seconds)
+            .arg("NX")
+            .ignore()
+            .incr(key, 1)
+            .ttl(key)
+            .query_async(&mut *connection)
+            .await
+            .context("Failed to count a request in Redis.")?;
+
+        if n_requests > limit.max_requests {
+            tracing::warn!(?limit, "Too many requests");
+            Ok(Some(Duration::from_secs(ttl.max(1) as u64)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// The hash of who the request is about, so that email addresses and usernames are not
+/// stored in Redis.
+fn target(body: &[u8], field: &str) -> Option<String> {
+    let body: serde_json::Value = serde_json::from_slice(body).ok()?;
+    let target = body.get(field)?.as_str()?.trim().to_lowercase();
+    if target.is_empty() {
+        return None;
+    }
+
+    Some(to_hex(&Sha256::digest(target.as_bytes())))
+}
+
+/// Limit the requests sending confirmation emails, per client and per email address.
+pub async fn limit_subscriptions(
+    State(limiter): State<RateLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    limiter.limit(Endpoint::Subscribe, request, next).await
+}
+
+/// Limit the login attempts, per client and per username.
+pub async fn limit_logins(
+    State(limiter): State<RateLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    limiter.limit(Endpoint::Login, request, next).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::target;
+
+    #[test]
+    fn targets_ignore_case_and_surrounding_whitespace() {
+        let a = target(br#"{"email": "Bulbasaur@Example.com"}"#, "email");
+        let b = target(br#"{"email": " bulbasaur@example.com "}"#, "email");
+
+        assert!(a.is_some());
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn requests_without_a_target_are_only_limited_per_client() {
+        assert_eq!(target(b"not json", "email"), None);
+        assert_eq!(target(br#"{"email": 42}"#, "email"), None);
+        assert_eq!(target(br#"{"email": "  "}"#, "email"), None);
+        assert_eq!(target(br#"{"name": "bulbasaur"}"#, "email"), None);
+    }
+}
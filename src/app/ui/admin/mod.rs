@@ -11,4 +11,5 @@ pub fn router() -> Router<AppState> {
     Router::new()
         .route("/app", get(route::admin_dashboard))
         .route("/change-password", post(route::change_password))
+        .route("/change-email", post(route::change_email))
 }
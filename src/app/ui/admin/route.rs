@@ -11,13 +11,16 @@ use secrecy::ExposeSecret;
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::app::{
-    authentication::{self, validate_credentials, Credentials},
-    extractor::session_user::SessionUser,
-    AppState,
+use crate::{
+    app::{
+        authentication::{self, validate_credentials, Credentials},
+        extractor::session_user::SessionUser,
+        AppState,
+    },
+    domain::subscriber::email::Email,
 };
 
-use super::schema::ChangePasswordRequestBody;
+use super::schema::{ChangeEmailRequestBody, ChangePasswordRequestBody};
 
 #[derive(Template)]
 #[template(path = "incorrect_username_or_password.html")]
@@ -29,10 +32,17 @@ struct Success {
     message: String,
 }
 
+#[derive(Template)]
+#[template(path = "error.html")]
+struct Error {
+    message: String,
+}
+
 #[derive(Template)]
 #[template(path = "admin_dashboard.html")]
 struct AdminDashboardTemplate {
     user: String,
+    email: String,
 }
 
 #[tracing::instrument(name = "Admin dashboard", skip(state, session))]
@@ -46,6 +56,10 @@ pub async fn admin_dashboard(
             .body(Body::from(
                 AdminDashboardTemplate {
                     user: get_username(user.id, &state.db).await.unwrap(),
+                    email: get_user_email(user.id, &state.db)
+                        .await
+                        .unwrap()
+                        .unwrap_or_default(),
                 }
                 .render()
                 .unwrap(),
@@ -97,6 +111,39 @@ pub async fn change_password(
         .unwrap()
 }
 
+/// Change the address test newsletters are sent to.
+#[tracing::instrument(name = "Change email", skip(user, state, body))]
+pub async fn change_email(
+    user: SessionUser,
+    state: State<AppState>,
+    Json(body): Json<ChangeEmailRequestBody>,
+) -> impl IntoResponse {
+    let fragment = match Email::try_from(body.email) {
+        Err(e) => Error {
+            message: format!("Invalid email: {}.", e),
+        }
+        .render(),
+        Ok(email) => match set_user_email(user.id, &email, &state.db).await {
+            Ok(()) => Success {
+                message: "Email successfully changed.".to_owned(),
+            }
+            .render(),
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to change the email");
+                Error {
+                    message: "Something went wrong, please try again later.".to_owned(),
+                }
+                .render()
+            }
+        },
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(fragment.unwrap()))
+        .unwrap()
+}
+
 #[tracing::instrument(name = "Get username", skip(pool))]
 pub async fn get_username(user_id: Uuid, pool: &PgPool) -> Result<String, anyhow::Error> {
     let row = sqlx::query!(
@@ -112,3 +159,36 @@ pub async fn get_username(user_id: Uuid, pool: &PgPool) -> Result<String, anyhow
     .context("Failed to perform a query to retrieve a username.")?;
     Ok(row.username)
 }
+
+#[tracing::instrument(name = "Get user email", skip(pool))]
+pub async fn get_user_email(user_id: Uuid, pool: &PgPool) -> Result<Option<String>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        select email
+        from users
+        where user_id = $1
+        "#,
+        user_id,
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to perform a query to retrieve a user email.")?;
+    Ok(row.email)
+}
+
+#[tracing::instrument(name = "Set user email", skip(email, pool))]
+async fn set_user_email(user_id: Uuid, email: &Email, pool: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        update users
+        set email = $1
+        where user_id = $2
+        "#,
+        email.as_ref(),
+        user_id,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to update the user email.")?;
+    Ok(())
+}
@@ -7,3 +7,8 @@ pub struct ChangePasswordRequestBody {
     pub new_password: Secret<String>,
     pub new_password_check: Secret<String>,
 }
+
+#[derive(serde::Deserialize)]
+pub struct ChangeEmailRequestBody {
+    pub email: String,
+}
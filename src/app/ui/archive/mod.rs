@@ -0,0 +1,10 @@
+use super::AppState;
+use axum::{routing::get, Router};
+
+pub mod route;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/archive", get(route::archive))
+        .route("/archive/:newsletter_issue_id", get(route::archived_issue))
+}
@@ -0,0 +1,64 @@
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use uuid::Uuid;
+
+use crate::app::{
+    api::newsletter::{
+        route::{get_issue, list_issues},
+        schema::{IssueDetails, IssueSummary},
+    },
+    error::AppResult,
+    ui::not_found::not_found_page,
+    AppState,
+};
+
+#[derive(Template)]
+#[template(path = "archive.html")]
+struct ArchiveTemplate {
+    issues: Vec<IssueSummary>,
+}
+
+#[derive(Template)]
+#[template(path = "archived_issue.html")]
+struct ArchivedIssueTemplate {
+    issue: IssueDetails,
+}
+
+/// The public list of published issues, when `public_archive` is enabled.
+#[tracing::instrument(name = "Archive page", skip(state))]
+pub async fn archive(State(state): State<AppState>) -> AppResult<Response> {
+    if !state.public_archive {
+        return Ok(not_found().await);
+    }
+
+    let mut issues = list_issues(&state.db).await?;
+    issues.retain(|issue| issue.published_at.is_some());
+
+    Ok(ArchiveTemplate { issues }.into_response())
+}
+
+/// A published issue, at a URL that stays valid as long as the issue exists.
+#[tracing::instrument(name = "Archived issue page", skip(state))]
+pub async fn archived_issue(
+    State(state): State<AppState>,
+    Path(newsletter_issue_id): Path<Uuid>,
+) -> AppResult<Response> {
+    if !state.public_archive {
+        return Ok(not_found().await);
+    }
+
+    match get_issue(&state.db, newsletter_issue_id).await? {
+        Some(issue) if issue.published_at.is_some() => {
+            Ok(ArchivedIssueTemplate { issue }.into_response())
+        }
+        _ => Ok(not_found().await),
+    }
+}
+
+async fn not_found() -> Response {
+    (StatusCode::NOT_FOUND, not_found_page().await).into_response()
+}
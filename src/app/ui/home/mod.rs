@@ -1,8 +1,20 @@
 use super::AppState;
-use axum::{routing::get, Router};
+use crate::app::rate_limit::limit_subscriptions;
+use axum::{
+    middleware,
+    routing::{get, post},
+    Router,
+};
 
 pub mod route;
+pub mod schema;
 
-pub fn router() -> Router<AppState> {
-    Router::new().route("/", get(route::home_page))
+pub fn router(state: &AppState) -> Router<AppState> {
+    Router::new().route("/", get(route::home_page)).route(
+        "/subscribe",
+        post(route::subscribe).layer(middleware::from_fn_with_state(
+            state.clone(),
+            limit_subscriptions,
+        )),
+    )
 }
@@ -1,12 +1,113 @@
 use askama::Template;
-use axum::response::IntoResponse;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Response, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use tower_sessions::Session;
 
+use super::schema::SubscribeRequestBody;
+use crate::{
+    app::{
+        api::{
+            list::route::{get_list, DEFAULT_LIST},
+            subscription::route::register_subscriber,
+        },
+        AppState,
+    },
+    domain::subscriber::{email::Email, name::Name, NewSubscriber},
+};
+
+/// The values and validation errors of the subscription form.
+#[derive(Default)]
+struct SubscribeForm {
+    name: String,
+    email: String,
+    name_error: Option<String>,
+    email_error: Option<String>,
+    error: Option<String>,
+}
+
 #[derive(Template)]
 #[template(path = "index.html")]
-struct HomeTemplate;
+struct HomeTemplate {
+    form: SubscribeForm,
+}
+
+#[derive(Template)]
+#[template(path = "subscribe_form.html")]
+struct SubscribeFormTemplate {
+    form: SubscribeForm,
+}
+
+#[derive(Template)]
+#[template(path = "subscribe_pending.html")]
+struct SubscribePendingTemplate {
+    email: String,
+}
 
 #[tracing::instrument(name = "Home page")]
 pub async fn home_page(session: Session) -> impl IntoResponse {
-    HomeTemplate
+    HomeTemplate {
+        form: SubscribeForm::default(),
+    }
+}
+
+/// Subscribe from the form of the home page.
+///
+/// Re-renders the form with inline errors when a field is invalid, otherwise asks the
+/// visitor to check their inbox.
+#[tracing::instrument(name = "Subscribe from the home page", skip(state, body))]
+pub async fn subscribe(
+    State(state): State<AppState>,
+    Json(body): Json<SubscribeRequestBody>,
+) -> impl IntoResponse {
+    let name = Name::try_from(body.name.clone());
+    let email = Email::try_from(body.email.clone());
+
+    let new_subscriber = match (name, email) {
+        (Ok(name), Ok(email)) => NewSubscriber { name, email },
+        (name, email) => {
+            return render_form(SubscribeForm {
+                name: body.name,
+                email: body.email,
+                name_error: name.err().map(|e| e.to_string()),
+                email_error: email.err().map(|e| e.to_string()),
+                error: None,
+            })
+        }
+    };
+
+    let registration = match get_list(&state.db, DEFAULT_LIST).await {
+        Ok(Some(list)) => register_subscriber(&state, &new_subscriber, &list).await,
+        Ok(None) => Err(anyhow::anyhow!("The default list does not exist.")),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = registration {
+        tracing::error!(error.cause_chain = ?e, "Failed to register a subscriber");
+        return render_form(SubscribeForm {
+            name: body.name,
+            email: body.email,
+            error: Some("Something went wrong, please try again later.".to_owned()),
+            ..SubscribeForm::default()
+        });
+    }
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(
+            SubscribePendingTemplate { email: body.email }
+                .render()
+                .unwrap(),
+        ))
+        .unwrap()
+}
+
+fn render_form(form: SubscribeForm) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(SubscribeFormTemplate { form }.render().unwrap()))
+        .unwrap()
 }
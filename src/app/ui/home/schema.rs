@@ -0,0 +1,9 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct SubscribeRequestBody {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub email: String,
+}
@@ -0,0 +1,17 @@
+use super::AppState;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+pub mod route;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/app/issues", get(route::issue_list))
+        .route("/app/issues/:newsletter_issue_id", get(route::issue_detail))
+        .route(
+            "/app/issues/:newsletter_issue_id/cancel",
+            post(route::cancel),
+        )
+}
@@ -0,0 +1,99 @@
+use askama::Template;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+};
+use uuid::Uuid;
+
+use crate::app::{
+    api::newsletter::{
+        route::{cancel_issue, get_issue, get_link_clicks, list_issues},
+        schema::{IssueDetails, IssueSummary, LinkClicks},
+    },
+    error::AppResult,
+    extractor::{
+        flash::{Flash, FlashMessage},
+        session_user::SessionUser,
+    },
+    ui::not_found::not_found_page,
+    AppState,
+};
+
+#[derive(Template)]
+#[template(path = "issue_list.html")]
+struct IssueListTemplate {
+    flashes: Vec<FlashMessage>,
+    issues: Vec<IssueSummary>,
+}
+
+#[derive(Template)]
+#[template(path = "issue_detail.html")]
+struct IssueDetailTemplate {
+    issue: IssueDetails,
+    /// The followed links of tracked issues.
+    links: Vec<LinkClicks>,
+}
+
+/// Every issue with its status, and the delivery counts of published ones.
+#[tracing::instrument(name = "Issue list page", skip(state, session, flash))]
+pub async fn issue_list(
+    State(state): State<AppState>,
+    session: Option<SessionUser>,
+    flash: Flash,
+) -> AppResult<Response> {
+    if session.is_none() {
+        return Ok(Redirect::temporary("/login").into_response());
+    }
+
+    let issues = list_issues(&state.db).await?;
+    let flashes = flash.take().await?;
+
+    Ok(IssueListTemplate { flashes, issues }.into_response())
+}
+
+/// A past issue, as it was sent, with its opens and clicks when tracked.
+#[tracing::instrument(name = "Issue detail page", skip(state, session))]
+pub async fn issue_detail(
+    State(state): State<AppState>,
+    session: Option<SessionUser>,
+    Path(newsletter_issue_id): Path<Uuid>,
+) -> AppResult<Response> {
+    if session.is_none() {
+        return Ok(Redirect::temporary("/login").into_response());
+    }
+
+    match get_issue(&state.db, newsletter_issue_id).await? {
+        Some(issue) => {
+            let links = get_link_clicks(&state.db, newsletter_issue_id).await?;
+            Ok(IssueDetailTemplate { issue, links }.into_response())
+        }
+        None => Ok((StatusCode::NOT_FOUND, not_found_page().await).into_response()),
+    }
+}
+
+/// Cancel a draft or scheduled issue, then reload the list with a flash message.
+#[tracing::instrument(name = "Cancel newsletter issue", skip(user, state, flash), fields(user_id = %user.id))]
+pub async fn cancel(
+    user: SessionUser,
+    State(state): State<AppState>,
+    flash: Flash,
+    Path(newsletter_issue_id): Path<Uuid>,
+) -> AppResult<Response> {
+    if cancel_issue(&state.db, newsletter_issue_id).await? {
+        flash
+            .success("The newsletter issue has been cancelled.")
+            .await?;
+    } else {
+        flash
+            .error("This issue can no longer be cancelled, its delivery has started.")
+            .await?;
+    }
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("HX-Redirect", "/app/issues")
+        .body(Body::empty())
+        .unwrap())
+}
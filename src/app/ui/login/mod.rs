@@ -1,5 +1,7 @@
 use super::AppState;
+use crate::app::rate_limit::limit_logins;
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
@@ -7,9 +9,12 @@ use axum::{
 pub mod route;
 pub mod schema;
 
-pub fn router() -> Router<AppState> {
+pub fn router(state: &AppState) -> Router<AppState> {
     Router::new()
         .route("/login", get(route::login_form))
-        .route("/login", post(route::login))
+        .route(
+            "/login",
+            post(route::login).layer(middleware::from_fn_with_state(state.clone(), limit_logins)),
+        )
         .route("/logout", get(route::logout))
 }
@@ -27,7 +27,7 @@ struct IncorrectUsernameOrPasswordTemplate;
 #[tracing::instrument(name = "Login form")]
 pub async fn login_form(session: Option<SessionUser>) -> impl IntoResponse {
     if let Some(user) = session {
-        tracing::Span::current().record("user_id", &tracing::field::display(&user.id));
+        tracing::Span::current().record("user_id", tracing::field::display(&user.id));
         return Redirect::temporary("/app").into_response();
     }
 
@@ -51,7 +51,7 @@ pub async fn login(
         username: body.username,
         password: Secret::new(body.password),
     };
-    tracing::Span::current().record("username", &tracing::field::display(&credentials.username));
+    tracing::Span::current().record("username", tracing::field::display(&credentials.username));
 
     match validate_credentials(credentials, &state.db).await {
         Ok(user_id) => {
@@ -59,7 +59,7 @@ pub async fn login(
             session.cycle_id().await.unwrap();
             session.save().await.unwrap();
 
-            tracing::Span::current().record("user_id", &tracing::field::display(&user_id));
+            tracing::Span::current().record("user_id", tracing::field::display(&user_id));
 
             Response::builder()
                 .status(StatusCode::OK)
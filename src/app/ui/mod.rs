@@ -2,15 +2,31 @@ use axum::Router;
 
 use super::AppState;
 
-mod admin;
+pub mod admin;
+mod archive;
 mod asset;
 mod home;
+mod issue;
 mod login;
+mod newsletter;
 pub mod not_found;
+mod personal_data;
+mod subscriber;
+mod subscription;
+mod tracking;
+mod unsubscribe;
 
-pub fn router() -> Router<AppState> {
-    home::router()
+pub fn router(state: &AppState) -> Router<AppState> {
+    home::router(state)
         .merge(admin::router())
-        .merge(login::router())
+        .merge(login::router(state))
+        .merge(issue::router())
+        .merge(archive::router())
+        .merge(newsletter::router())
         .merge(asset::router())
+        .merge(personal_data::router())
+        .merge(subscriber::router())
+        .merge(subscription::router(state))
+        .merge(tracking::router())
+        .merge(unsubscribe::router())
 }
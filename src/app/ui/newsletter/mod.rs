@@ -0,0 +1,22 @@
+use super::AppState;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+pub mod route;
+pub mod schema;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/app/newsletters", get(route::compose_page))
+        .route("/app/newsletters", post(route::publish))
+        .route("/app/newsletters/draft", post(route::save_draft))
+        .route("/app/newsletters/schedule", post(route::schedule))
+        .route(
+            "/app/newsletters/:newsletter_issue_id",
+            get(route::edit_page),
+        )
+        .route("/app/newsletters/preview", post(route::preview))
+        .route("/app/newsletters/test", post(route::send_test_email))
+}
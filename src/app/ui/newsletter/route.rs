@@ -0,0 +1,484 @@
+use anyhow::Context;
+use askama::Template;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{Response, StatusCode},
+    response::{IntoResponse, Redirect},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::schema::{ComposeRequestBody, SCHEDULE_FORMAT};
+use crate::{
+    app::{
+        api::{
+            list::{
+                route::{get_lists, resolve_lists, DEFAULT_LIST},
+                schema::MailingList,
+            },
+            newsletter::{
+                route::{get_issue, insert_issue, update_issue, IssueDraft},
+                schema::PublishNewsletterRequestBody,
+            },
+        },
+        error::{AppError, FieldError},
+        extractor::{
+            flash::{Flash, FlashMessage},
+            session_user::SessionUser,
+        },
+        idempotency::{
+            key::IdempotencyKey,
+            persistence::{save_response, try_processing, NextAction},
+        },
+        ui::admin::route::get_user_email,
+        AppState,
+    },
+    domain::subscriber::email::Email,
+    email::template::{NewsletterEmail, Recipient},
+    issue_scheduling_worker::start_delivery,
+};
+
+const COMPOSE_PAGE: &str = "/app/newsletters";
+const ISSUES_PAGE: &str = "/app/issues";
+
+/// The name used in place of `{{ name }}` in test emails.
+const TEST_RECIPIENT_NAME: &str = "Test subscriber";
+
+/// The values of the compose form, empty for a new issue.
+#[derive(Default)]
+struct ComposeForm {
+    newsletter_issue_id: Option<Uuid>,
+    title: String,
+    html_content: String,
+    text_content: String,
+    markdown_content: String,
+    /// Whether the markdown editor is shown rather than the HTML and text ones.
+    use_markdown: bool,
+    scheduled_for: String,
+    /// The slugs of the lists the issue is sent to.
+    lists: Vec<String>,
+    tracking: bool,
+}
+
+impl ComposeForm {
+    fn is_selected(&self, slug: &str) -> bool {
+        self.lists.iter().any(|selected| selected == slug)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "newsletter_compose.html")]
+struct ComposeTemplate {
+    flashes: Vec<FlashMessage>,
+    idempotency_key: String,
+    lists: Vec<MailingList>,
+    form: ComposeForm,
+}
+
+#[derive(Template)]
+#[template(path = "newsletter_preview.html")]
+struct PreviewTemplate {
+    title: String,
+    html_content: String,
+    text_content: String,
+}
+
+#[derive(Template)]
+#[template(path = "success.html")]
+struct Success {
+    message: String,
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+struct Error {
+    message: String,
+}
+
+/// The page to compose, preview, test and publish a newsletter issue.
+///
+/// Each rendering carries a new idempotency key, so that submitting the same form twice
+/// publishes the issue only once.
+#[tracing::instrument(name = "Compose newsletter page", skip(state, session, flash))]
+pub async fn compose_page(
+    State(state): State<AppState>,
+    session: Option<SessionUser>,
+    flash: Flash,
+) -> impl IntoResponse {
+    if session.is_none() {
+        return Redirect::temporary("/login").into_response();
+    }
+
+    let form = ComposeForm {
+        use_markdown: true,
+        lists: vec![DEFAULT_LIST.to_owned()],
+        ..ComposeForm::default()
+    };
+    render_compose_page(&state, &flash, form).await
+}
+
+/// The compose page, filled with a draft or scheduled issue.
+#[tracing::instrument(name = "Edit newsletter page", skip(state, session, flash))]
+pub async fn edit_page(
+    State(state): State<AppState>,
+    session: Option<SessionUser>,
+    flash: Flash,
+    Path(newsletter_issue_id): Path<Uuid>,
+) -> impl IntoResponse {
+    if session.is_none() {
+        return Redirect::temporary("/login").into_response();
+    }
+
+    let issue = match get_issue(&state.db, newsletter_issue_id).await {
+        Ok(issue) => issue,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to get the newsletter issue");
+            None
+        }
+    };
+    match issue {
+        Some(issue) if matches!(issue.status.as_str(), "draft" | "scheduled") => {
+            let form = ComposeForm {
+                newsletter_issue_id: Some(issue.newsletter_issue_id),
+                title: issue.title,
+                html_content: issue.html_content,
+                text_content: issue.text_content,
+                use_markdown: issue.markdown_content.is_some(),
+                markdown_content: issue.markdown_content.unwrap_or_default(),
+                scheduled_for: issue
+                    .scheduled_for
+                    .map(|scheduled_for| scheduled_for.format(SCHEDULE_FORMAT).to_string())
+                    .unwrap_or_default(),
+                lists: issue.lists,
+                tracking: issue.tracking,
+            };
+            render_compose_page(&state, &flash, form).await
+        }
+        _ => {
+            push_flash_error(&flash, "This issue can no longer be edited.").await;
+            Redirect::to(ISSUES_PAGE).into_response()
+        }
+    }
+}
+
+async fn render_compose_page(state: &AppState, flash: &Flash, form: ComposeForm) -> Response<Body> {
+    let flashes = flash.take().await.unwrap_or_else(|e| {
+        tracing::error!(error = ?e, "Failed to read flash messages");
+        vec![]
+    });
+    let lists = get_lists(&state.db).await.unwrap_or_else(|e| {
+        tracing::error!(error = ?e, "Failed to get the mailing lists");
+        vec![]
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(
+            ComposeTemplate {
+                flashes,
+                idempotency_key: Uuid::new_v4().to_string(),
+                lists,
+                form,
+            }
+            .render()
+            .unwrap(),
+        ))
+        .unwrap()
+}
+
+/// Render both versions of the issue being composed.
+#[tracing::instrument(name = "Preview newsletter", skip(_user, body))]
+pub async fn preview(
+    _user: SessionUser,
+    Json(body): Json<ComposeRequestBody>,
+) -> impl IntoResponse {
+    let mut issue = body.to_issue();
+    issue.render_markdown();
+    PreviewTemplate {
+        title: issue.title,
+        html_content: issue.content.html,
+        text_content: issue.content.text,
+    }
+}
+
+/// Send the issue being composed to the logged-in admin only, in the newsletter layout
+/// and with sample values for the personalisation variables.
+#[tracing::instrument(name = "Send test newsletter", skip(user, state, body), fields(user_id = %user.id))]
+pub async fn send_test_email(
+    user: SessionUser,
+    State(state): State<AppState>,
+    Json(body): Json<ComposeRequestBody>,
+) -> impl IntoResponse {
+    let mut issue = body.to_issue();
+    if let Err(errors) = issue.validate() {
+        return error_fragment(describe(errors.iter()));
+    }
+    issue.render_markdown();
+
+    let recipient = match get_user_email(user.id, &state.db).await {
+        Ok(Some(email)) => Email::try_from(email).ok(),
+        Ok(None) => None,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to get the admin email");
+            return error_fragment("Something went wrong, please try again later.");
+        }
+    };
+    let Some(recipient) = recipient else {
+        return error_fragment("Set your email address on the dashboard to receive test emails.");
+    };
+
+    let unsubscribe_url = format!("{}/unsubscribe", state.base_url);
+    let email = NewsletterEmail {
+        title: &issue.title,
+        html_content: &issue.content.html,
+        text_content: &issue.content.text,
+        recipient: &Recipient {
+            name: TEST_RECIPIENT_NAME,
+            email: recipient.as_ref(),
+            unsubscribe_url: &unsubscribe_url,
+        },
+        // Test emails are not tracked, whatever the setting of the issue.
+        tracking: None,
+    }
+    .render();
+    let email = match email {
+        Ok(email) => email,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to render the test email");
+            return error_fragment("Something went wrong, please try again later.");
+        }
+    };
+
+    let subject = format!("[Test] {}", email.subject);
+    match state
+        .email_client
+        .send_email(&recipient, &subject, &email.html_body, &email.text_body)
+        .await
+    {
+        Ok(()) => success_fragment(format!("A test email was sent to {}.", recipient)),
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to send the test email");
+            error_fragment("The test email could not be sent, please try again later.")
+        }
+    }
+}
+
+/// Publish the issue to the confirmed subscribers of its lists, then reload the page with a flash
+/// message.
+#[tracing::instrument(name = "Publish newsletter from the admin page", skip(user, state, flash, body), fields(user_id = %user.id))]
+pub async fn publish(
+    user: SessionUser,
+    State(state): State<AppState>,
+    flash: Flash,
+    Json(body): Json<ComposeRequestBody>,
+) -> impl IntoResponse {
+    submit(user, &state, &flash, body, Submission::Publish).await
+}
+
+/// Save the issue without sending it, then keep editing it.
+#[tracing::instrument(name = "Save newsletter draft", skip(user, state, flash, body), fields(user_id = %user.id))]
+pub async fn save_draft(
+    user: SessionUser,
+    State(state): State<AppState>,
+    flash: Flash,
+    Json(body): Json<ComposeRequestBody>,
+) -> impl IntoResponse {
+    submit(user, &state, &flash, body, Submission::Draft).await
+}
+
+/// Save the issue for the scheduler to publish at the picked time.
+#[tracing::instrument(name = "Schedule newsletter", skip(user, state, flash, body), fields(user_id = %user.id))]
+pub async fn schedule(
+    user: SessionUser,
+    State(state): State<AppState>,
+    flash: Flash,
+    Json(body): Json<ComposeRequestBody>,
+) -> impl IntoResponse {
+    let Some(scheduled_for) = body.scheduled_for() else {
+        return error_fragment("Please pick a publication date.");
+    };
+    if scheduled_for <= Utc::now() {
+        return error_fragment("Please pick a publication date in the future.");
+    }
+
+    submit(
+        user,
+        &state,
+        &flash,
+        body,
+        Submission::Schedule(scheduled_for),
+    )
+    .await
+}
+
+/// What to do with a submitted compose form.
+#[derive(Clone, Copy)]
+enum Submission {
+    Publish,
+    Draft,
+    Schedule(DateTime<Utc>),
+}
+
+impl Submission {
+    fn scheduled_for(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Schedule(scheduled_for) => Some(*scheduled_for),
+            _ => None,
+        }
+    }
+}
+
+async fn submit(
+    user: SessionUser,
+    state: &AppState,
+    flash: &Flash,
+    body: ComposeRequestBody,
+    submission: Submission,
+) -> Response<Body> {
+    let mut issue = body.to_issue();
+    let validation = match submission {
+        Submission::Draft => issue.validate_draft(),
+        _ => issue.validate(),
+    };
+    if let Err(errors) = validation {
+        return error_fragment(describe(errors.iter()));
+    }
+    issue.render_markdown();
+
+    let list_ids = match resolve_lists(&state.db, &issue.lists).await {
+        Ok(list_ids) => list_ids,
+        Err(AppError::Validation(errors)) => return error_fragment(describe(&errors)),
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to look up the mailing lists");
+            return error_fragment("Something went wrong, please try again later.");
+        }
+    };
+
+    let Ok(idempotency_key) = IdempotencyKey::try_from(body.idempotency_key) else {
+        return error_fragment("This form has expired, please reload the page.");
+    };
+
+    let outcome = save_issue(
+        state,
+        user.id,
+        &idempotency_key,
+        body.newsletter_issue_id,
+        &issue,
+        &list_ids,
+        submission,
+    )
+    .await;
+    match outcome {
+        Ok(Some(response)) => {
+            let message = match submission {
+                Submission::Publish => "The newsletter issue has been published.",
+                Submission::Draft => "The draft has been saved.",
+                Submission::Schedule(_) => "The newsletter issue has been scheduled.",
+            };
+            if let Err(e) = flash.success(message).await {
+                tracing::error!(error = ?e, "Failed to store the flash message");
+            }
+            response
+        }
+        Ok(None) => error_fragment("This issue can no longer be edited."),
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to save the newsletter issue");
+            error_fragment("The newsletter issue could not be saved, please try again.")
+        }
+    }
+}
+
+/// Store the issue and start its delivery when publishing it, at most once per
+/// idempotency key.
+///
+/// Returns `None` when the edited issue was cancelled or sent in the meantime.
+async fn save_issue(
+    state: &AppState,
+    user_id: Uuid,
+    idempotency_key: &IdempotencyKey,
+    newsletter_issue_id: Option<Uuid>,
+    issue: &PublishNewsletterRequestBody,
+    list_ids: &[Uuid],
+    submission: Submission,
+) -> Result<Option<Response<Body>>, anyhow::Error> {
+    let mut transaction = match try_processing(&state.db, idempotency_key, user_id).await? {
+        NextAction::StartProcessing(transaction) => *transaction,
+        NextAction::ReturnSavedResponse(saved_response) => return Ok(Some(saved_response)),
+    };
+
+    let draft = IssueDraft::new(issue, list_ids, submission.scheduled_for());
+    let newsletter_issue_id = match newsletter_issue_id {
+        Some(newsletter_issue_id) => {
+            if !update_issue(&mut transaction, newsletter_issue_id, &draft).await? {
+                return Ok(None);
+            }
+            newsletter_issue_id
+        }
+        None => insert_issue(&mut transaction, user_id, &draft).await?,
+    };
+
+    let location = match submission {
+        Submission::Publish => {
+            start_delivery(&mut transaction, newsletter_issue_id).await?;
+            COMPOSE_PAGE.to_owned()
+        }
+        Submission::Draft => format!("{}/{}", COMPOSE_PAGE, newsletter_issue_id),
+        Submission::Schedule(_) => ISSUES_PAGE.to_owned(),
+    };
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header("HX-Redirect", location)
+        .body(Body::empty())
+        .context("Failed to build the redirection.")?;
+    save_response(transaction, idempotency_key, user_id, response)
+        .await
+        .map(Some)
+}
+
+async fn push_flash_error(flash: &Flash, message: &str) {
+    if let Err(e) = flash.error(message).await {
+        tracing::error!(error = ?e, "Failed to store the flash message");
+    }
+}
+
+/// A sentence listing the rejected fields of the form.
+fn describe<'a>(errors: impl IntoIterator<Item = &'a FieldError>) -> String {
+    let reasons: Vec<String> = errors
+        .into_iter()
+        .map(|e| {
+            let label = match e.field.as_str() {
+                "title" => "Title",
+                "content.html" => "HTML body",
+                "content.text" => "Plain-text body",
+                "content.markdown" => "Markdown body",
+                "content" => "Content",
+                "lists" => "Lists",
+                other => other,
+            };
+            format!("{}: {}", label, e.error)
+        })
+        .collect();
+    format!("Please fix the form. {}.", reasons.join(", "))
+}
+
+fn success_fragment(message: impl Into<String>) -> Response<Body> {
+    fragment(Success {
+        message: message.into(),
+    })
+}
+
+fn error_fragment(message: impl Into<String>) -> Response<Body> {
+    fragment(Error {
+        message: message.into(),
+    })
+}
+
+fn fragment(template: impl Template) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(template.render().unwrap()))
+        .unwrap()
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer};
+use uuid::Uuid;
+
+use crate::app::api::newsletter::schema::{NewsletterContent, PublishNewsletterRequestBody};
+
+/// The format of `datetime-local` inputs.
+pub const SCHEDULE_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ComposeRequestBody {
+    /// Set when editing an existing draft or scheduled issue.
+    #[serde(default)]
+    pub newsletter_issue_id: Option<Uuid>,
+    pub idempotency_key: String,
+    pub title: String,
+    /// Only the fields of the chosen editor are submitted, markdown or HTML and text.
+    #[serde(default)]
+    pub html_content: String,
+    #[serde(default)]
+    pub text_content: String,
+    #[serde(default)]
+    pub markdown_content: Option<String>,
+    #[serde(default)]
+    pub scheduled_for: String,
+    /// The slugs of the checked lists.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub lists: Vec<String>,
+    /// Only submitted when the box is checked.
+    #[serde(default)]
+    pub tracking: Option<String>,
+}
+
+impl ComposeRequestBody {
+    /// The same issue, as it would be submitted to the API.
+    pub fn to_issue(&self) -> PublishNewsletterRequestBody {
+        PublishNewsletterRequestBody {
+            title: self.title.clone(),
+            content: NewsletterContent {
+                html: self.html_content.clone(),
+                text: self.text_content.clone(),
+                markdown: self.markdown_content.clone(),
+            },
+            lists: self.lists.clone(),
+            tracking: self.tracking.is_some(),
+        }
+    }
+
+    /// The publication time picked in the form, read as UTC.
+    pub fn scheduled_for(&self) -> Option<DateTime<Utc>> {
+        NaiveDateTime::parse_from_str(self.scheduled_for.trim(), SCHEDULE_FORMAT)
+            .ok()
+            .map(|scheduled_for| scheduled_for.and_utc())
+    }
+}
+
+/// Checkboxes sharing a name are submitted as a string when a single one is checked,
+/// and as an array otherwise.
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
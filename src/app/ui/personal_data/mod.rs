@@ -0,0 +1,14 @@
+use super::AppState;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+pub mod route;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/my-data", get(route::personal_data_page))
+        .route("/my-data/request", post(route::request_data_link))
+        .route("/my-data/erase", post(route::erase))
+}
@@ -0,0 +1,131 @@
+use askama::Template;
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{Response, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+
+use crate::{
+    app::{
+        api::personal_data::{
+            route::{erase_subscriber, is_valid_data_request, send_data_link},
+            schema::{DataLinkRequestBody, DataParams},
+        },
+        AppState,
+    },
+    domain::subscriber::email::Email,
+};
+
+/// The state of the page, depending on the link it was opened with.
+enum Access {
+    /// No link: the visitor can ask for one.
+    Request,
+    Invalid,
+    Granted {
+        subscriber_id: String,
+        token: String,
+    },
+}
+
+#[derive(Template)]
+#[template(path = "personal_data.html")]
+struct PersonalDataTemplate {
+    access: Access,
+}
+
+#[derive(Template)]
+#[template(path = "success.html")]
+struct Success {
+    message: String,
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+struct Error {
+    message: String,
+}
+
+/// The page behind the link emailed to subscribers who ask for their data, or the form
+/// to ask for it.
+#[tracing::instrument(name = "Personal data page", skip(state, params))]
+pub async fn personal_data_page(
+    State(state): State<AppState>,
+    params: Option<Query<DataParams>>,
+) -> impl IntoResponse {
+    let (status, access) = match params {
+        None => (StatusCode::OK, Access::Request),
+        Some(Query(params)) if is_valid_data_request(&params, &state.hmac_key) => (
+            StatusCode::OK,
+            Access::Granted {
+                subscriber_id: params.subscriber_id.to_string(),
+                token: params.token,
+            },
+        ),
+        Some(_) => (StatusCode::UNAUTHORIZED, Access::Invalid),
+    };
+
+    Response::builder()
+        .status(status)
+        .body(Body::from(
+            PersonalDataTemplate { access }.render().unwrap(),
+        ))
+        .unwrap()
+}
+
+#[tracing::instrument(name = "Request a personal data link from the page", skip(state, body))]
+pub async fn request_data_link(
+    State(state): State<AppState>,
+    Json(body): Json<DataLinkRequestBody>,
+) -> impl IntoResponse {
+    let Ok(email) = Email::try_from(body.email) else {
+        return fragment(Error {
+            message: "Please enter a valid email address.".to_owned(),
+        });
+    };
+
+    match send_data_link(&state, &email).await {
+        Ok(()) => fragment(Success {
+            message: "If this address is subscribed, a link to your data is on its way.".to_owned(),
+        }),
+        Err(e) => {
+            tracing::error!(error.cause_chain = ?e, "Failed to send the personal data link");
+            fragment(Error {
+                message: "Something went wrong, please try again later.".to_owned(),
+            })
+        }
+    }
+}
+
+#[tracing::instrument(name = "Erase personal data from the page", skip(state, params), fields(subscriber_id = %params.subscriber_id))]
+pub async fn erase(
+    State(state): State<AppState>,
+    Query(params): Query<DataParams>,
+) -> impl IntoResponse {
+    if !is_valid_data_request(&params, &state.hmac_key) {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    match erase_subscriber(&state.db, params.subscriber_id, &state.hmac_key).await {
+        Ok(()) => fragment(Success {
+            message: "Your data has been deleted, you will not hear from us again.".to_owned(),
+        }),
+        Err(e) => {
+            tracing::error!(error.cause_chain = ?e, "Failed to erase the subscriber");
+            fragment(Error {
+                message: "Something went wrong, please try again later.".to_owned(),
+            })
+        }
+    }
+}
+
+fn fragment(template: impl Template) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(template.render().unwrap()))
+        .unwrap()
+}
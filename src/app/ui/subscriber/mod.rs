@@ -0,0 +1,22 @@
+use super::AppState;
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{get, post},
+    Router,
+};
+
+use crate::app::api::subscriber::route::MAX_IMPORT_SIZE;
+
+pub mod route;
+pub mod schema;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/app/subscribers", get(route::subscribers_page))
+        .route(
+            "/app/subscribers/import",
+            // The file is sent inside a JSON body, which takes a bit more room.
+            post(route::import).layer(DefaultBodyLimit::max(2 * MAX_IMPORT_SIZE)),
+        )
+        .route("/app/subscribers/export", get(route::export))
+}
@@ -0,0 +1,114 @@
+use askama::Template;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Response, StatusCode},
+    response::{IntoResponse, Redirect},
+    Json,
+};
+
+use super::schema::ImportRequestBody;
+use crate::app::{
+    api::{
+        list::{
+            route::{get_lists, resolve_list, DEFAULT_LIST},
+            schema::MailingList,
+        },
+        subscriber::{
+            route::{export_response, run_import},
+            schema::ImportReport,
+        },
+    },
+    error::AppError,
+    extractor::session_user::SessionUser,
+    AppState,
+};
+
+#[derive(Template)]
+#[template(path = "subscribers.html")]
+struct SubscribersTemplate {
+    lists: Vec<MailingList>,
+    default_list: &'static str,
+}
+
+#[derive(Template)]
+#[template(path = "import_report.html")]
+struct ImportReportTemplate {
+    report: ImportReport,
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+struct Error {
+    message: String,
+}
+
+/// The page to import subscribers from a CSV file and to export them.
+#[tracing::instrument(name = "Subscribers page", skip(state, session))]
+pub async fn subscribers_page(
+    State(state): State<AppState>,
+    session: Option<SessionUser>,
+) -> impl IntoResponse {
+    if session.is_none() {
+        return Redirect::temporary("/login").into_response();
+    }
+
+    let lists = get_lists(&state.db).await.unwrap_or_else(|e| {
+        tracing::error!(error = ?e, "Failed to get the mailing lists");
+        vec![]
+    });
+    SubscribersTemplate {
+        lists,
+        default_list: DEFAULT_LIST,
+    }
+    .into_response()
+}
+
+/// Import the picked file, or only check it in a dry run, and show what happened to
+/// each row.
+#[tracing::instrument(name = "Import subscribers from the admin page", skip(user, state, body), fields(user_id = %user.id))]
+pub async fn import(
+    user: SessionUser,
+    State(state): State<AppState>,
+    Json(body): Json<ImportRequestBody>,
+) -> impl IntoResponse {
+    let dry_run = body.dry_run.is_some();
+    let report = match resolve_list(&state.db, Some(&body.list)).await {
+        Ok(list) => run_import(&state.db, &state.hmac_key, &body.csv, &list, dry_run).await,
+        Err(e) => Err(e),
+    };
+
+    match report {
+        Ok(report) => ImportReportTemplate { report }.into_response(),
+        Err(AppError::Validation(errors)) => {
+            let reasons: Vec<String> = errors.iter().map(|e| e.error.to_string()).collect();
+            error_fragment(format!(
+                "The file could not be imported: {}.",
+                reasons.join(", ")
+            ))
+        }
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to import subscribers");
+            error_fragment("Something went wrong, please try again later.")
+        }
+    }
+}
+
+/// Download every subscriber as a CSV file.
+#[tracing::instrument(name = "Export subscribers from the admin page", skip(user, state), fields(user_id = %user.id))]
+pub async fn export(user: SessionUser, State(state): State<AppState>) -> impl IntoResponse {
+    export_response(state.db.clone())
+}
+
+fn error_fragment(message: impl Into<String>) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(
+            Error {
+                message: message.into(),
+            }
+            .render()
+            .unwrap(),
+        ))
+        .unwrap()
+}
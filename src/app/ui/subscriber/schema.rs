@@ -0,0 +1,12 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ImportRequestBody {
+    /// The content of the picked file.
+    pub csv: String,
+    pub list: String,
+    /// Set when the dry-run box is checked.
+    #[serde(default)]
+    pub dry_run: Option<String>,
+}
@@ -0,0 +1,22 @@
+use super::AppState;
+use crate::app::rate_limit::limit_subscriptions;
+use axum::{
+    middleware,
+    routing::{get, post},
+    Router,
+};
+
+pub mod route;
+pub mod schema;
+
+pub fn router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/subscriptions/confirm", get(route::confirmation_page))
+        .route(
+            "/subscriptions/confirm/resend",
+            post(route::resend_confirmation).layer(middleware::from_fn_with_state(
+                state.clone(),
+                limit_subscriptions,
+            )),
+        )
+}
@@ -0,0 +1,101 @@
+use askama::Template;
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{Response, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+
+use super::schema::ResendConfirmationRequestBody;
+use crate::{
+    app::{
+        api::subscription::{
+            route::{confirm_subscription, request_new_confirmation, Confirmation},
+            schema::ConfirmParams,
+        },
+        AppState,
+    },
+    domain::subscriber::email::Email,
+};
+
+#[derive(Template)]
+#[template(path = "subscription_confirmation.html")]
+struct ConfirmationTemplate {
+    confirmation: Confirmation,
+}
+
+#[derive(Template)]
+#[template(path = "success.html")]
+struct Success {
+    message: String,
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+struct Error {
+    message: String,
+}
+
+/// The landing page of the link sent in confirmation emails.
+#[tracing::instrument(name = "Subscription confirmation page", skip(state, params))]
+pub async fn confirmation_page(
+    State(state): State<AppState>,
+    Query(params): Query<ConfirmParams>,
+) -> impl IntoResponse {
+    let confirmation = match confirm_subscription(&state, &params.subscription_token).await {
+        Ok(confirmation) => confirmation,
+        Err(e) => {
+            tracing::error!(error.cause_chain = ?e, "Failed to confirm a subscription");
+            return Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::empty())
+                .unwrap();
+        }
+    };
+
+    let status = match confirmation {
+        Confirmation::Confirmed | Confirmation::AlreadyConfirmed => StatusCode::OK,
+        Confirmation::InvalidToken => StatusCode::UNAUTHORIZED,
+        Confirmation::ExpiredToken => StatusCode::GONE,
+    };
+
+    Response::builder()
+        .status(status)
+        .body(Body::from(
+            ConfirmationTemplate { confirmation }.render().unwrap(),
+        ))
+        .unwrap()
+}
+
+#[tracing::instrument(name = "Resend confirmation", skip(state, body))]
+pub async fn resend_confirmation(
+    State(state): State<AppState>,
+    Json(body): Json<ResendConfirmationRequestBody>,
+) -> impl IntoResponse {
+    let fragment = match Email::try_from(body.email) {
+        Err(_) => Error {
+            message: "Please enter a valid email address.".to_owned(),
+        }
+        .render(),
+        Ok(email) => match request_new_confirmation(&state, &email).await {
+            Ok(()) => Success {
+                message: "If a subscription is waiting for confirmation, a new link is on its way."
+                    .to_owned(),
+            }
+            .render(),
+            Err(e) => {
+                tracing::error!(error.cause_chain = ?e, "Failed to resend a confirmation");
+                Error {
+                    message: "Something went wrong, please try again later.".to_owned(),
+                }
+                .render()
+            }
+        },
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(fragment.unwrap()))
+        .unwrap()
+}
@@ -0,0 +1,6 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ResendConfirmationRequestBody {
+    pub email: String,
+}
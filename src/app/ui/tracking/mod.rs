@@ -0,0 +1,8 @@
+use super::AppState;
+use axum::{routing::get, Router};
+
+pub mod route;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/tracking", get(route::tracking_page).post(route::opt_out))
+}
@@ -0,0 +1,90 @@
+use anyhow::Context;
+use askama::Template;
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{Response, StatusCode},
+    response::IntoResponse,
+};
+
+use crate::app::{
+    api::{
+        subscription::{route::is_valid_unsubscribe_request, schema::UnsubscribeParams},
+        tracking::route::opt_out_of_tracking,
+    },
+    error::AppResult,
+    AppState,
+};
+
+#[derive(Template)]
+#[template(path = "tracking.html")]
+struct TrackingTemplate {
+    subscriber_id: String,
+    token: String,
+    valid: bool,
+}
+
+#[derive(Template)]
+#[template(path = "success.html")]
+struct Success {
+    message: String,
+}
+
+/// The page behind the "Stop tracking" link in the footer of tracked newsletters.
+///
+/// Like unsubscribing, opting out takes an explicit click.
+#[tracing::instrument(name = "Tracking page", skip(state, params))]
+pub async fn tracking_page(
+    State(state): State<AppState>,
+    Query(params): Query<UnsubscribeParams>,
+) -> impl IntoResponse {
+    let valid = is_valid_unsubscribe_request(&params, &state.hmac_key);
+    let status = if valid {
+        StatusCode::OK
+    } else {
+        StatusCode::UNAUTHORIZED
+    };
+
+    Response::builder()
+        .status(status)
+        .body(Body::from(
+            TrackingTemplate {
+                subscriber_id: params.subscriber_id.to_string(),
+                token: params.token,
+                valid,
+            }
+            .render()
+            .unwrap(),
+        ))
+        .unwrap()
+}
+
+#[tracing::instrument(name = "Opt out of tracking", skip(state, params), fields(subscriber_id = %params.subscriber_id))]
+pub async fn opt_out(
+    State(state): State<AppState>,
+    Query(params): Query<UnsubscribeParams>,
+) -> AppResult<Response<Body>> {
+    if !is_valid_unsubscribe_request(&params, &state.hmac_key) {
+        return Ok(Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body(Body::empty())
+            .unwrap());
+    }
+
+    opt_out_of_tracking(&state.db, params.subscriber_id)
+        .await
+        .context("Failed to opt the subscriber out of tracking.")?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(
+            Success {
+                message:
+                    "We will no longer record when you open our newsletters or follow their links."
+                        .to_owned(),
+            }
+            .render()
+            .unwrap(),
+        ))
+        .unwrap())
+}
@@ -0,0 +1,11 @@
+use super::AppState;
+use axum::{routing::get, Router};
+
+pub mod route;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route(
+        "/unsubscribe",
+        get(route::unsubscribe_page).post(route::unsubscribe),
+    )
+}
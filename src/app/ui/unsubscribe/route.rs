@@ -0,0 +1,90 @@
+use anyhow::Context;
+use askama::Template;
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{Response, StatusCode},
+    response::IntoResponse,
+};
+
+use crate::app::{
+    api::subscription::{
+        route::{is_valid_unsubscribe_request, unsubscribe_subscriber},
+        schema::UnsubscribeParams,
+    },
+    error::AppResult,
+    AppState,
+};
+
+#[derive(Template)]
+#[template(path = "unsubscribe.html")]
+struct UnsubscribeTemplate {
+    subscriber_id: String,
+    token: String,
+    valid: bool,
+}
+
+#[derive(Template)]
+#[template(path = "success.html")]
+struct Success {
+    message: String,
+}
+
+/// The page behind the link in the footer of every newsletter.
+///
+/// Unsubscribing takes an explicit click, so that link scanners following the link do
+/// not unsubscribe anyone.
+#[tracing::instrument(name = "Unsubscribe page", skip(state, params))]
+pub async fn unsubscribe_page(
+    State(state): State<AppState>,
+    Query(params): Query<UnsubscribeParams>,
+) -> impl IntoResponse {
+    let valid = is_valid_unsubscribe_request(&params, &state.hmac_key);
+    let status = if valid {
+        StatusCode::OK
+    } else {
+        StatusCode::UNAUTHORIZED
+    };
+
+    Response::builder()
+        .status(status)
+        .body(Body::from(
+            UnsubscribeTemplate {
+                subscriber_id: params.subscriber_id.to_string(),
+                token: params.token,
+                valid,
+            }
+            .render()
+            .unwrap(),
+        ))
+        .unwrap()
+}
+
+#[tracing::instrument(name = "Unsubscribe", skip(state, params), fields(subscriber_id = %params.subscriber_id))]
+pub async fn unsubscribe(
+    State(state): State<AppState>,
+    Query(params): Query<UnsubscribeParams>,
+) -> AppResult<Response<Body>> {
+    if !is_valid_unsubscribe_request(&params, &state.hmac_key) {
+        return Ok(Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body(Body::empty())
+            .unwrap());
+    }
+
+    unsubscribe_subscriber(&state.db, params.subscriber_id)
+        .await
+        .context("Failed to update subscriber status.")?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(
+            Success {
+                message: "You have been unsubscribed, you will not receive any more newsletters."
+                    .to_owned(),
+            }
+            .render()
+            .unwrap(),
+        ))
+        .unwrap())
+}
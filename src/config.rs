@@ -1,14 +1,27 @@
 use std::{env, fmt, str::FromStr, time};
 
+use anyhow::Context;
 use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 
+use crate::{
+    domain::subscriber::email::Email,
+    email::{
+        file::FileTransport,
+        postmark::PostmarkTransport,
+        smtp::{SmtpAuthMechanism, SmtpTls, SmtpTransport},
+        EmailClient,
+    },
+};
+
 #[derive(Deserialize)]
 pub struct Settings {
     pub application: ApplicationSettings,
     pub database: DatabaseSettings,
     pub email_client: EmailClientSettings,
+    pub subscriptions: SubscriptionSettings,
+    pub rate_limits: RateLimitSettings,
     pub redis_uri: Secret<String>,
 }
 
@@ -19,20 +32,182 @@ pub struct ApplicationSettings {
     pub base_url: String,
     pub log_level: String,
     pub hmac_key: Secret<String>,
+    /// Serve published issues to everyone under `/archive`.
+    #[serde(default)]
+    pub public_archive: bool,
 }
 
 #[derive(Deserialize)]
 pub struct EmailClientSettings {
+    #[serde(default)]
+    pub transport: EmailTransportKind,
     pub base_url: String,
     pub sender_email: String,
     pub authorization_token: Secret<String>,
+    /// The password the provider sends along its bounce and spam complaint webhooks.
+    pub webhook_secret: Secret<String>,
     pub timeout_milliseconds: u64,
+    pub retry: RetrySettings,
+    pub smtp: Option<SmtpSettings>,
+    pub file_sink: Option<FileSinkSettings>,
 }
 
 impl EmailClientSettings {
     pub fn timeout(&self) -> time::Duration {
         time::Duration::from_millis(self.timeout_milliseconds)
     }
+
+    pub fn client(&self) -> Result<EmailClient, anyhow::Error> {
+        let sender_email = Email::try_from(self.sender_email.clone())
+            .map_err(|e| anyhow::anyhow!(e))
+            .context("The sender email should be valid.")?;
+
+        let email_client = match self.transport {
+            EmailTransportKind::Postmark => EmailClient::new(
+                sender_email,
+                PostmarkTransport::new(
+                    self.base_url.clone(),
+                    self.authorization_token.clone(),
+                    self.timeout(),
+                )?,
+            ),
+            EmailTransportKind::Smtp => {
+                let smtp = self
+                    .smtp
+                    .as_ref()
+                    .context("The `smtp` settings are required by the SMTP transport.")?;
+                let credentials = match (&smtp.username, &smtp.password) {
+                    (Some(username), Some(password)) => Some((username.clone(), password.clone())),
+                    (None, None) => None,
+                    _ => anyhow::bail!("SMTP credentials need both a username and a password."),
+                };
+                EmailClient::new(
+                    sender_email,
+                    SmtpTransport::new(
+                        &smtp.host,
+                        smtp.port,
+                        smtp.tls,
+                        credentials,
+                        smtp.auth_mechanism,
+                        self.timeout(),
+                    )?,
+                )
+            }
+            EmailTransportKind::File => {
+                let file_sink = self
+                    .file_sink
+                    .as_ref()
+                    .context("The `file_sink` settings are required by the file transport.")?;
+                EmailClient::new(sender_email, FileTransport::new(&file_sink.directory))
+            }
+        };
+
+        Ok(email_client)
+    }
+}
+
+/// Which backend `EmailClient` hands emails over to.
+#[derive(Deserialize, Default, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailTransportKind {
+    #[default]
+    Postmark,
+    Smtp,
+    File,
+}
+
+#[derive(Deserialize)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    #[serde(default = "default_smtp_auth_mechanism")]
+    pub auth_mechanism: SmtpAuthMechanism,
+}
+
+fn default_smtp_auth_mechanism() -> SmtpAuthMechanism {
+    SmtpAuthMechanism::Plain
+}
+
+#[derive(Deserialize)]
+pub struct FileSinkSettings {
+    pub directory: String,
+}
+
+/// How failed newsletter deliveries are retried before being dead-lettered.
+#[derive(Deserialize, Clone)]
+pub struct RetrySettings {
+    pub max_attempts: u32,
+    pub base_delay_milliseconds: u64,
+    pub jitter_milliseconds: u64,
+    /// Upper bound of the exponential part of the delay.
+    pub max_delay_milliseconds: u64,
+}
+
+impl RetrySettings {
+    pub fn base_delay(&self) -> time::Duration {
+        time::Duration::from_millis(self.base_delay_milliseconds)
+    }
+
+    pub fn jitter(&self) -> time::Duration {
+        time::Duration::from_millis(self.jitter_milliseconds)
+    }
+
+    pub fn max_delay(&self) -> time::Duration {
+        time::Duration::from_millis(self.max_delay_milliseconds)
+    }
+}
+
+/// Lifetime of confirmation links and of subscriptions that were never confirmed.
+#[derive(Deserialize, Clone)]
+pub struct SubscriptionSettings {
+    pub confirmation_token_ttl_hours: u64,
+    pub unconfirmed_retention_days: u64,
+    pub purge_interval_minutes: u64,
+}
+
+impl SubscriptionSettings {
+    pub fn confirmation_token_ttl(&self) -> time::Duration {
+        time::Duration::from_secs(self.confirmation_token_ttl_hours * 60 * 60)
+    }
+
+    pub fn unconfirmed_retention(&self) -> time::Duration {
+        time::Duration::from_secs(self.unconfirmed_retention_days * 24 * 60 * 60)
+    }
+
+    pub fn purge_interval(&self) -> time::Duration {
+        time::Duration::from_secs(self.purge_interval_minutes * 60)
+    }
+}
+
+/// How many requests clients may send to the endpoints that email people or check
+/// passwords.
+#[derive(Deserialize, Clone)]
+pub struct RateLimitSettings {
+    /// The prefix of the Redis keys holding the counters.
+    pub namespace: String,
+    /// The header a reverse proxy passes the client address in, e.g. `X-Forwarded-For`.
+    /// The address of the connection is used when missing.
+    pub client_ip_header: Option<String>,
+    pub subscribe: EndpointRateLimits,
+    pub login: EndpointRateLimits,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct EndpointRateLimits {
+    /// The requests sent from a single address.
+    pub per_client: RateLimit,
+    /// The requests about a single email address or username, whoever sends them.
+    pub per_target: RateLimit,
+}
+
+/// At most `max_requests` requests every `window_seconds`.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct RateLimit {
+    pub max_requests: u32,
+    pub window_seconds: u64,
 }
 
 #[derive(Deserialize)]
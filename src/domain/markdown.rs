@@ -0,0 +1,317 @@
+use pulldown_cmark::{html, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+/// The width plain-text paragraphs are reflowed to.
+const TEXT_WIDTH: usize = 72;
+
+/// Below this width, deeply nested paragraphs overflow rather than wrap every word.
+const MIN_TEXT_WIDTH: usize = 20;
+
+/// The two parts of an email, generated from the same markdown source.
+pub struct RenderedMarkdown {
+    pub html: String,
+    pub text: String,
+}
+
+pub fn render(markdown: &str) -> RenderedMarkdown {
+    RenderedMarkdown {
+        html: to_html(markdown),
+        text: to_text(markdown),
+    }
+}
+
+fn parser(markdown: &str) -> Parser<'_> {
+    Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH)
+}
+
+/// Render markdown to HTML, dropping scripts, styles, event handlers and any other
+/// markup that has no place in an email.
+fn to_html(markdown: &str) -> String {
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, parser(markdown));
+    ammonia::clean(&unsafe_html)
+}
+
+/// Render markdown to plain text: formatting is dropped, link targets are written out
+/// and paragraphs are reflowed to `TEXT_WIDTH` columns.
+fn to_text(markdown: &str) -> String {
+    let mut writer = TextWriter::default();
+    for event in parser(markdown) {
+        match event {
+            Event::End(TagEnd::Paragraph) => writer.flush_paragraph(),
+            Event::End(TagEnd::Heading(level)) => writer.flush_heading(level),
+            Event::Start(Tag::BlockQuote) => {
+                writer.flush_paragraph();
+                writer.containers.push(Container::Quote);
+            }
+            Event::End(TagEnd::BlockQuote) => {
+                writer.flush_paragraph();
+                writer.containers.pop();
+            }
+            Event::Start(Tag::List(start)) => {
+                writer.flush_paragraph();
+                if writer.lists.is_empty() {
+                    writer.n_lists += 1;
+                }
+                writer.lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                writer.lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                writer.flush_paragraph();
+                let marker = writer.next_marker();
+                writer.containers.push(Container::Item {
+                    marker,
+                    pending: true,
+                });
+            }
+            Event::End(TagEnd::Item) => {
+                writer.flush_paragraph();
+                writer.containers.pop();
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                writer.flush_paragraph();
+                writer.code = Some(String::new());
+            }
+            Event::End(TagEnd::CodeBlock) => writer.flush_code(),
+            Event::Start(Tag::Link { dest_url, .. })
+            | Event::Start(Tag::Image { dest_url, .. }) => writer.links.push(dest_url.to_string()),
+            Event::End(TagEnd::Link) | Event::End(TagEnd::Image) => writer.close_link(),
+            Event::Text(text) => match writer.code.as_mut() {
+                Some(code) => code.push_str(&text),
+                None => writer.inline.push_str(&text),
+            },
+            Event::Code(code) => writer.inline.push_str(&code),
+            Event::SoftBreak => writer.inline.push(' '),
+            Event::HardBreak => writer.inline.push('\n'),
+            Event::Rule => {
+                writer.flush_paragraph();
+                let (first, _) = writer.prefixes();
+                writer.push_block(format!("{}{}", first, "-".repeat(TEXT_WIDTH)));
+            }
+            _ => {}
+        }
+    }
+    writer.flush_paragraph();
+
+    writer.finish()
+}
+
+/// A block that indents the lines it contains.
+enum Container {
+    Quote,
+    /// `pending` until the marker has been written on the first line of the item.
+    Item {
+        marker: String,
+        pending: bool,
+    },
+}
+
+#[derive(Default)]
+struct TextWriter {
+    /// Finished blocks, and the top-level list they belong to.
+    blocks: Vec<(String, Option<usize>)>,
+    containers: Vec<Container>,
+    /// The next number of each open list, `None` for bullet lists.
+    lists: Vec<Option<u64>>,
+    /// How many top-level lists were opened so far.
+    n_lists: usize,
+    /// The text of the paragraph being read.
+    inline: String,
+    /// The targets of the links being read.
+    links: Vec<String>,
+    /// The content of the code block being read.
+    code: Option<String>,
+}
+
+impl TextWriter {
+    fn next_marker(&mut self) -> String {
+        match self.lists.last_mut() {
+            Some(Some(number)) => {
+                let marker = format!("{}. ", number);
+                *number += 1;
+                marker
+            }
+            _ => "- ".to_owned(),
+        }
+    }
+
+    /// The prefix of the first and following lines of a new block.
+    fn prefixes(&mut self) -> (String, String) {
+        let mut first = String::new();
+        let mut rest = String::new();
+        for container in &mut self.containers {
+            match container {
+                Container::Quote => {
+                    first.push_str("> ");
+                    rest.push_str("> ");
+                }
+                Container::Item { marker, pending } => {
+                    let indent = " ".repeat(marker.chars().count());
+                    if *pending {
+                        first.push_str(marker);
+                        *pending = false;
+                    } else {
+                        first.push_str(&indent);
+                    }
+                    rest.push_str(&indent);
+                }
+            }
+        }
+        (first, rest)
+    }
+
+    fn push_block(&mut self, block: String) {
+        let in_list = self
+            .containers
+            .iter()
+            .any(|container| matches!(container, Container::Item { .. }));
+        self.blocks.push((block, in_list.then_some(self.n_lists)));
+    }
+
+    fn flush_paragraph(&mut self) {
+        let paragraph = std::mem::take(&mut self.inline);
+        if paragraph.trim().is_empty() {
+            return;
+        }
+        let (first, rest) = self.prefixes();
+        self.push_block(wrap(&paragraph, &first, &rest));
+    }
+
+    fn flush_heading(&mut self, level: HeadingLevel) {
+        let heading = std::mem::take(&mut self.inline);
+        let heading = heading.trim();
+        let (first, rest) = self.prefixes();
+        let underline = match level {
+            HeadingLevel::H1 => "=",
+            _ => "-",
+        };
+        self.push_block(format!(
+            "{}{}\n{}{}",
+            first,
+            heading,
+            rest,
+            underline.repeat(heading.chars().count())
+        ));
+    }
+
+    fn flush_code(&mut self) {
+        let Some(code) = self.code.take() else {
+            return;
+        };
+        let (first, rest) = self.prefixes();
+        let block = code
+            .trim_end_matches('\n')
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                let prefix = if i == 0 { &first } else { &rest };
+                format!("{}    {}", prefix, line).trim_end().to_owned()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.push_block(block);
+    }
+
+    /// Write the target of a link after its text, unless the text is the target itself.
+    fn close_link(&mut self) {
+        if let Some(url) = self.links.pop() {
+            if !self.inline.ends_with(&url) {
+                self.inline.push_str(&format!(" ({})", url));
+            }
+        }
+    }
+
+    /// Separate blocks with a blank line, except between the items of a list.
+    fn finish(self) -> String {
+        let mut text = String::new();
+        let mut previous = None;
+        for (block, list) in self.blocks {
+            match previous {
+                Some(Some(previous_list)) if list == Some(previous_list) => text.push('\n'),
+                Some(_) => text.push_str("\n\n"),
+                None => {}
+            }
+            text.push_str(&block);
+            previous = Some(list);
+        }
+        text
+    }
+}
+
+/// Greedily wrap words to `TEXT_WIDTH`, keeping hard line breaks.
+fn wrap(text: &str, first: &str, rest: &str) -> String {
+    let mut lines = Vec::new();
+    let mut prefix = first;
+    for segment in text.trim().split('\n') {
+        let mut line = String::new();
+        for word in segment.split_whitespace() {
+            let width = TEXT_WIDTH
+                .saturating_sub(prefix.chars().count())
+                .max(MIN_TEXT_WIDTH);
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(format!("{}{}", prefix, line));
+                prefix = rest;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(format!("{}{}", prefix, line).trim_end().to_owned());
+        prefix = rest;
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, TEXT_WIDTH};
+
+    #[test]
+    fn html_is_sanitized() {
+        let rendered = render("Hello **world**!\n\n<script>alert(1)</script>");
+
+        assert!(rendered
+            .html
+            .contains("<p>Hello <strong>world</strong>!</p>"));
+        assert!(!rendered.html.contains("script"));
+    }
+
+    #[test]
+    fn text_drops_formatting_and_keeps_link_targets() {
+        let rendered = render("# Title\n\nRead *the* [docs](https://example.com).");
+
+        assert_eq!(
+            rendered.text,
+            "Title\n=====\n\nRead the docs (https://example.com)."
+        );
+    }
+
+    #[test]
+    fn text_paragraphs_are_reflowed() {
+        let paragraph = "word ".repeat(50);
+
+        let rendered = render(&paragraph);
+
+        let lines = rendered.text.lines().collect::<Vec<_>>();
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|line| line.len() <= TEXT_WIDTH));
+        assert_eq!(rendered.text.split_whitespace().count(), 50);
+    }
+
+    #[test]
+    fn text_lists_keep_their_markers() {
+        let rendered = render("- one\n- two\n\n1. first\n2. second");
+
+        assert_eq!(rendered.text, "- one\n- two\n\n1. first\n2. second");
+    }
+
+    #[test]
+    fn text_quotes_and_code_are_indented() {
+        let rendered = render("> quoted\n\n```\nlet x = 1;\n```");
+
+        assert_eq!(rendered.text, "> quoted\n\n    let x = 1;");
+    }
+}
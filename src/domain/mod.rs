@@ -1 +1,3 @@
+pub mod markdown;
 pub mod subscriber;
+pub mod validation;
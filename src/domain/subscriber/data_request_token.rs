@@ -0,0 +1,141 @@
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
+use uuid::Uuid;
+
+use super::{is_well_formed, sign, verify};
+
+const DOMAIN: &[u8] = b"data-request:";
+
+/// A token proving that a link to the personal data of a subscriber was emailed to
+/// them.
+///
+/// It is written `<expiry>.<tag>`, the expiry as a Unix timestamp and the tag as the
+/// hex-encoded HMAC-SHA256 of the subscriber id and the expiry. Unlike unsubscribe
+/// links, these links give access to the data and stop working after a while.
+#[derive(Debug)]
+pub struct DataRequestToken {
+    expires_at: i64,
+    tag: String,
+}
+
+impl DataRequestToken {
+    pub fn generate(
+        subscriber_id: Uuid,
+        expires_at: DateTime<Utc>,
+        hmac_key: &Secret<String>,
+    ) -> Self {
+        let expires_at = expires_at.timestamp();
+        Self {
+            expires_at,
+            tag: sign(
+                DOMAIN,
+                &[subscriber_id.as_bytes(), &expires_at.to_be_bytes()],
+                hmac_key,
+            ),
+        }
+    }
+
+    /// Check that the token was issued for the subscriber and has not expired, comparing
+    /// the tags in constant time.
+    pub fn is_valid_for(
+        &self,
+        subscriber_id: Uuid,
+        hmac_key: &Secret<String>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        if self.expires_at <= now.timestamp() {
+            return false;
+        }
+
+        verify(
+            &self.tag,
+            DOMAIN,
+            &[subscriber_id.as_bytes(), &self.expires_at.to_be_bytes()],
+            hmac_key,
+        )
+    }
+}
+
+impl TryFrom<String> for DataRequestToken {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let malformed = || "data request token is malformed".to_owned();
+        let (expires_at, tag) = value.split_once('.').ok_or_else(malformed)?;
+        let expires_at = expires_at.parse().map_err(|_| malformed())?;
+        if !is_well_formed(tag) {
+            return Err(malformed());
+        }
+
+        Ok(Self {
+            expires_at,
+            tag: tag.to_owned(),
+        })
+    }
+}
+
+impl std::fmt::Display for DataRequestToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.expires_at, self.tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    use super::DataRequestToken;
+
+    fn hmac_key() -> Secret<String> {
+        Secret::new(Uuid::new_v4().to_string())
+    }
+
+    #[test]
+    fn a_generated_token_is_valid_for_its_subscriber_until_it_expires() {
+        let (subscriber_id, hmac_key, now) = (Uuid::new_v4(), hmac_key(), Utc::now());
+        let token = DataRequestToken::generate(subscriber_id, now + Duration::hours(1), &hmac_key);
+        assert!(token.is_valid_for(subscriber_id, &hmac_key, now));
+        assert!(!token.is_valid_for(subscriber_id, &hmac_key, now + Duration::hours(2)));
+    }
+
+    #[test]
+    fn a_token_is_not_valid_for_another_subscriber() {
+        let (hmac_key, now) = (hmac_key(), Utc::now());
+        let token = DataRequestToken::generate(Uuid::new_v4(), now + Duration::hours(1), &hmac_key);
+        assert!(!token.is_valid_for(Uuid::new_v4(), &hmac_key, now));
+    }
+
+    #[test]
+    fn the_expiry_can_not_be_extended() {
+        let (subscriber_id, hmac_key, now) = (Uuid::new_v4(), hmac_key(), Utc::now());
+        let token = DataRequestToken::generate(subscriber_id, now + Duration::hours(1), &hmac_key);
+        let token = token.to_string();
+        let (_, tag) = token.split_once('.').unwrap();
+        let forged = format!("{}.{}", (now + Duration::days(365)).timestamp(), tag);
+        let forged = DataRequestToken::try_from(forged).unwrap();
+        assert!(!forged.is_valid_for(subscriber_id, &hmac_key, now));
+    }
+
+    #[test]
+    fn a_generated_token_round_trips_through_its_string_form() {
+        let (subscriber_id, hmac_key, now) = (Uuid::new_v4(), hmac_key(), Utc::now());
+        let token = DataRequestToken::generate(subscriber_id, now + Duration::hours(1), &hmac_key);
+        let parsed = DataRequestToken::try_from(token.to_string())
+            .expect("a generated token should be well formed");
+        assert!(parsed.is_valid_for(subscriber_id, &hmac_key, now));
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        let tag = "a".repeat(64);
+        for token in [
+            "",
+            &tag,
+            &format!("soon.{}", tag),
+            &format!("1.{}", "z".repeat(64)),
+        ] {
+            assert!(DataRequestToken::try_from(token.to_string()).is_err());
+        }
+    }
+}
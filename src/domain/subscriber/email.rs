@@ -1,21 +1,39 @@
 use derive_more::Display;
+use secrecy::Secret;
 use validator::validate_email;
 
+use super::sign;
+use crate::domain::validation::ValidationError;
+
 #[derive(Display, Clone)]
 #[display(fmt = "{}", _0)]
 pub struct Email(String);
 
 impl TryFrom<String> for Email {
-    type Error = String;
+    type Error = ValidationError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        if validate_email(&value) {
+        if value.is_empty() {
+            Err(ValidationError::new("empty", "email is empty"))
+        } else if validate_email(&value) {
             Ok(Self(value))
         } else {
-            Err("invalid email".into())
+            Err(ValidationError::new("invalid_format", "invalid email"))
         }
     }
 }
 
+impl Email {
+    /// The hex-encoded HMAC-SHA256 of the lowercased address, to recognise it later
+    /// without storing it.
+    pub fn suppression_hash(&self, hmac_key: &Secret<String>) -> String {
+        sign(
+            b"suppression:",
+            &[self.0.to_lowercase().as_bytes()],
+            hmac_key,
+        )
+    }
+}
+
 impl AsRef<str> for Email {
     fn as_ref(&self) -> &str {
         &self.0
@@ -1,7 +1,95 @@
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+
+pub mod data_request_token;
 pub mod email;
 pub mod name;
+pub mod tracking_token;
+pub mod unsubscribe_token;
 
 pub struct NewSubscriber {
     pub name: name::Name,
     pub email: email::Email,
 }
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The hex-encoded HMAC-SHA256 of the parts, prefixed by a `domain` so that a tag made
+/// for one purpose is never valid for another.
+pub fn sign(domain: &[u8], parts: &[&[u8]], hmac_key: &Secret<String>) -> String {
+    to_hex(&mac(domain, parts, hmac_key).finalize().into_bytes())
+}
+
+/// Check a tag made by [`sign`], in constant time.
+pub fn verify(tag: &str, domain: &[u8], parts: &[&[u8]], hmac_key: &Secret<String>) -> bool {
+    match from_hex(tag) {
+        Some(tag) => mac(domain, parts, hmac_key).verify_slice(&tag).is_ok(),
+        None => false,
+    }
+}
+
+/// Whether the text can be a tag made by [`sign`].
+pub fn is_well_formed(tag: &str) -> bool {
+    tag.len() == 64 && tag.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !is_well_formed(text) {
+        return None;
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn mac(domain: &[u8], parts: &[&[u8]], hmac_key: &Secret<String>) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(hmac_key.expose_secret().as_bytes())
+        .expect("HMAC-SHA-256 should accept any key length");
+    mac.update(domain);
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    use super::{sign, verify};
+
+    fn hmac_key() -> Secret<String> {
+        Secret::new(Uuid::new_v4().to_string())
+    }
+
+    #[test]
+    fn a_tag_is_valid_for_what_it_signs() {
+        let hmac_key = hmac_key();
+        let tag = sign(b"test:", &[b"a", b"b"], &hmac_key);
+        assert!(verify(&tag, b"test:", &[b"a", b"b"], &hmac_key));
+        assert!(!verify(&tag, b"test:", &[b"a", b"c"], &hmac_key));
+    }
+
+    #[test]
+    fn a_tag_is_not_valid_for_another_domain() {
+        let hmac_key = hmac_key();
+        let tag = sign(b"open:", &[b"a"], &hmac_key);
+        assert!(!verify(&tag, b"click:", &[b"a"], &hmac_key));
+    }
+
+    #[test]
+    fn malformed_tags_are_not_valid() {
+        let hmac_key = hmac_key();
+        for tag in ["", "not-hex", &"é".repeat(32), &"a".repeat(63)] {
+            assert!(!verify(tag, b"test:", &[b"a"], &hmac_key));
+        }
+    }
+}
@@ -1,24 +1,29 @@
 use derive_more::Display;
 use unicode_segmentation::UnicodeSegmentation;
 
+use crate::domain::validation::ValidationError;
+
 #[derive(Display)]
 #[display(fmt = "{}", _0)]
 pub struct Name(String);
 
 impl TryFrom<String> for Name {
-    type Error = String;
+    type Error = ValidationError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         if value.trim().is_empty() {
-            return Err("name is empty".into());
+            return Err(ValidationError::new("empty", "name is empty"));
         }
 
         if value.graphemes(true).count() > 256 {
-            return Err("name is too long".into());
+            return Err(ValidationError::new("too_long", "name is too long"));
         }
 
         let forbidden_characters = ['/', '(', ')', '"', '<', '>', '\\', '{', '}'];
         if value.chars().any(|g| forbidden_characters.contains(&g)) {
-            return Err("name contains invalid characters".into());
+            return Err(ValidationError::new(
+                "invalid_characters",
+                "name contains invalid characters",
+            ));
         }
 
         Ok(Self(value))
@@ -67,6 +72,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn rejections_carry_a_stable_code() {
+        let code = |name: &str| Name::try_from(name.to_string()).err().map(|e| e.code);
+        assert_eq!(code(""), Some("empty"));
+        assert_eq!(code(&"a".repeat(257)), Some("too_long"));
+        assert_eq!(code("{bulbasaur}"), Some("invalid_characters"));
+    }
+
     #[test]
     fn a_valid_name_is_parsed_successfully() {
         let name = "Kurt Gödel".to_string();
@@ -0,0 +1,139 @@
+use derive_more::Display;
+use secrecy::Secret;
+use uuid::Uuid;
+
+use super::{is_well_formed, sign, verify};
+
+/// What following a tracking link records.
+#[derive(Clone, Copy)]
+pub enum TrackedEvent<'a> {
+    /// The subscriber opened the issue, reported by its tracking pixel.
+    Open,
+    /// The subscriber followed a link of the issue to `url`.
+    Click { url: &'a str },
+}
+
+/// A token proving that a tracking link was issued for a subscriber, an issue and, for
+/// clicks, the link target.
+///
+/// Like [`super::unsubscribe_token::UnsubscribeToken`], it is a hex-encoded
+/// HMAC-SHA256, so links need no storage. Signing the target of click links stops them
+/// from redirecting anywhere else.
+#[derive(Display)]
+#[display(fmt = "{}", _0)]
+pub struct TrackingToken(String);
+
+impl TrackingToken {
+    pub fn generate(
+        newsletter_issue_id: Uuid,
+        subscriber_id: Uuid,
+        event: TrackedEvent<'_>,
+        hmac_key: &Secret<String>,
+    ) -> Self {
+        let (domain, url) = signed(event);
+        Self(sign(
+            domain,
+            &[
+                newsletter_issue_id.as_bytes(),
+                subscriber_id.as_bytes(),
+                url,
+            ],
+            hmac_key,
+        ))
+    }
+
+    /// Check the token against what the link records, in constant time.
+    pub fn is_valid_for(
+        &self,
+        newsletter_issue_id: Uuid,
+        subscriber_id: Uuid,
+        event: TrackedEvent<'_>,
+        hmac_key: &Secret<String>,
+    ) -> bool {
+        let (domain, url) = signed(event);
+        verify(
+            &self.0,
+            domain,
+            &[
+                newsletter_issue_id.as_bytes(),
+                subscriber_id.as_bytes(),
+                url,
+            ],
+            hmac_key,
+        )
+    }
+}
+
+/// The domain of the token and the target it signs, empty for opens.
+fn signed<'a>(event: TrackedEvent<'a>) -> (&'static [u8], &'a [u8]) {
+    match event {
+        TrackedEvent::Open => (b"open:", b""),
+        TrackedEvent::Click { url } => (b"click:", url.as_bytes()),
+    }
+}
+
+impl TryFrom<String> for TrackingToken {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if !is_well_formed(&value) {
+            return Err("tracking token is malformed".into());
+        }
+
+        Ok(Self(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    use super::{TrackedEvent, TrackingToken};
+
+    fn hmac_key() -> Secret<String> {
+        Secret::new(Uuid::new_v4().to_string())
+    }
+
+    #[test]
+    fn a_generated_token_is_valid_for_its_event() {
+        let (issue_id, subscriber_id, hmac_key) = (Uuid::new_v4(), Uuid::new_v4(), hmac_key());
+        let click = TrackedEvent::Click {
+            url: "https://example.com",
+        };
+        for event in [TrackedEvent::Open, click] {
+            let token = TrackingToken::generate(issue_id, subscriber_id, event, &hmac_key);
+            assert!(token.is_valid_for(issue_id, subscriber_id, event, &hmac_key));
+        }
+    }
+
+    #[test]
+    fn a_click_token_is_not_valid_for_another_url() {
+        let (issue_id, subscriber_id, hmac_key) = (Uuid::new_v4(), Uuid::new_v4(), hmac_key());
+        let event = TrackedEvent::Click {
+            url: "https://example.com",
+        };
+        let token = TrackingToken::generate(issue_id, subscriber_id, event, &hmac_key);
+
+        let other = TrackedEvent::Click {
+            url: "https://example.org",
+        };
+        assert!(!token.is_valid_for(issue_id, subscriber_id, other, &hmac_key));
+        assert!(!token.is_valid_for(issue_id, subscriber_id, TrackedEvent::Open, &hmac_key));
+    }
+
+    #[test]
+    fn a_token_is_not_valid_for_another_issue_or_subscriber() {
+        let (issue_id, subscriber_id, hmac_key) = (Uuid::new_v4(), Uuid::new_v4(), hmac_key());
+        let token = TrackingToken::generate(issue_id, subscriber_id, TrackedEvent::Open, &hmac_key);
+
+        assert!(!token.is_valid_for(Uuid::new_v4(), subscriber_id, TrackedEvent::Open, &hmac_key));
+        assert!(!token.is_valid_for(issue_id, Uuid::new_v4(), TrackedEvent::Open, &hmac_key));
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        for token in ["", "not-hex", &"z".repeat(64), &"a".repeat(63)] {
+            assert!(TrackingToken::try_from(token.to_string()).is_err());
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use derive_more::Display;
+use secrecy::Secret;
+use uuid::Uuid;
+
+use super::{is_well_formed, sign, verify};
+
+const DOMAIN: &[u8] = b"unsubscribe:";
+
+/// A token proving that an unsubscribe link was issued for a given subscriber.
+///
+/// It is the hex-encoded HMAC-SHA256 of the subscriber id, so links never expire and
+/// need no storage.
+#[derive(Display)]
+#[display(fmt = "{}", _0)]
+pub struct UnsubscribeToken(String);
+
+impl UnsubscribeToken {
+    pub fn generate(subscriber_id: Uuid, hmac_key: &Secret<String>) -> Self {
+        Self(sign(DOMAIN, &[subscriber_id.as_bytes()], hmac_key))
+    }
+
+    /// Check the token against the subscriber id, in constant time.
+    pub fn is_valid_for(&self, subscriber_id: Uuid, hmac_key: &Secret<String>) -> bool {
+        verify(&self.0, DOMAIN, &[subscriber_id.as_bytes()], hmac_key)
+    }
+}
+
+impl TryFrom<String> for UnsubscribeToken {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if !is_well_formed(&value) {
+            return Err("unsubscribe token is malformed".into());
+        }
+
+        Ok(Self(value))
+    }
+}
+
+impl AsRef<str> for UnsubscribeToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    use super::UnsubscribeToken;
+
+    fn hmac_key() -> Secret<String> {
+        Secret::new(Uuid::new_v4().to_string())
+    }
+
+    #[test]
+    fn a_generated_token_is_valid_for_its_subscriber() {
+        let (subscriber_id, hmac_key) = (Uuid::new_v4(), hmac_key());
+        let token = UnsubscribeToken::generate(subscriber_id, &hmac_key);
+        assert!(token.is_valid_for(subscriber_id, &hmac_key));
+    }
+
+    #[test]
+    fn a_token_is_not_valid_for_another_subscriber() {
+        let hmac_key = hmac_key();
+        let token = UnsubscribeToken::generate(Uuid::new_v4(), &hmac_key);
+        assert!(!token.is_valid_for(Uuid::new_v4(), &hmac_key));
+    }
+
+    #[test]
+    fn a_token_is_not_valid_with_another_key() {
+        let subscriber_id = Uuid::new_v4();
+        let token = UnsubscribeToken::generate(subscriber_id, &hmac_key());
+        assert!(!token.is_valid_for(subscriber_id, &hmac_key()));
+    }
+
+    #[test]
+    fn a_generated_token_round_trips_through_its_string_form() {
+        let (subscriber_id, hmac_key) = (Uuid::new_v4(), hmac_key());
+        let token = UnsubscribeToken::generate(subscriber_id, &hmac_key);
+        let parsed = UnsubscribeToken::try_from(token.to_string())
+            .expect("a generated token should be well formed");
+        assert!(parsed.is_valid_for(subscriber_id, &hmac_key));
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        for token in ["", "not-hex", &"z".repeat(64), &"a".repeat(63)] {
+            assert!(UnsubscribeToken::try_from(token.to_string()).is_err());
+        }
+    }
+}
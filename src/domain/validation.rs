@@ -0,0 +1,22 @@
+use derive_more::Display;
+
+/// Why a value was rejected.
+///
+/// `code` is stable and meant for API clients, `message` for humans.
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+#[display(fmt = "{}", message)]
+pub struct ValidationError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
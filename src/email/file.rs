@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use super::{EmailError, EmailMessage, EmailTransport};
+use crate::domain::subscriber::email::Email;
+
+/// Writes every email as a JSON file in a local directory, for development.
+pub struct FileTransport {
+    directory: PathBuf,
+}
+
+impl FileTransport {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl EmailTransport for FileTransport {
+    async fn send(&self, from: &Email, message: &EmailMessage<'_>) -> Result<(), EmailError> {
+        let file = StoredEmail {
+            from: from.as_ref(),
+            to: message.to.as_ref(),
+            subject: message.subject,
+            html_body: message.html_body,
+            text_body: message.text_body,
+            list_unsubscribe: message.list_unsubscribe,
+        };
+        let contents = serde_json::to_vec_pretty(&file)
+            .context("Failed to serialize the email.")
+            .map_err(EmailError::Permanent)?;
+
+        tokio::fs::create_dir_all(&self.directory)
+            .await
+            .context("Failed to create the email sink directory.")
+            .map_err(EmailError::Transient)?;
+
+        let path = self.directory.join(format!(
+            "{}-{}.json",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            Uuid::new_v4()
+        ));
+        tokio::fs::write(&path, contents)
+            .await
+            .with_context(|| format!("Failed to write the email to {}.", path.display()))
+            .map_err(EmailError::Transient)?;
+
+        tracing::info!(path = %path.display(), "Email written to the file sink");
+
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize)]
+struct StoredEmail<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    list_unsubscribe: Option<&'a str>,
+}
+
+#[cfg(test)]
+mod tests {
+    use fake::faker::internet::en::SafeEmail;
+    use fake::Fake;
+    use uuid::Uuid;
+
+    use super::FileTransport;
+    use crate::domain::subscriber::email::Email;
+    use crate::email::EmailClient;
+
+    fn email() -> Email {
+        SafeEmail()
+            .fake::<String>()
+            .try_into()
+            .expect("fake email should be a valid email")
+    }
+
+    #[tokio::test]
+    async fn send_email_writes_a_json_file() {
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let email_client = EmailClient::new(email(), FileTransport::new(&directory));
+        let recipient = email();
+
+        email_client
+            .send_email(&recipient, "Subject", "<p>HTML</p>", "Text")
+            .await
+            .expect("the email should be written");
+
+        let mut entries = std::fs::read_dir(&directory)
+            .expect("the sink directory should exist")
+            .collect::<Result<Vec<_>, _>>()
+            .expect("the sink directory should be readable");
+        assert_eq!(entries.len(), 1);
+
+        let contents = std::fs::read(entries.pop().unwrap().path()).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&contents).unwrap();
+        assert_eq!(body["to"], recipient.as_ref());
+        assert_eq!(body["subject"], "Subject");
+        assert_eq!(body["html_body"], "<p>HTML</p>");
+        assert_eq!(body["text_body"], "Text");
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
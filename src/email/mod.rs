@@ -0,0 +1,202 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::domain::subscriber::email::Email;
+
+use self::suppression::SuppressionList;
+
+pub mod file;
+pub mod postmark;
+pub mod smtp;
+pub mod suppression;
+pub mod template;
+pub mod tracking;
+
+#[derive(thiserror::Error, Debug)]
+pub enum EmailError {
+    /// The provider could not accept the email right now (5xx, 429, timeouts, connection
+    /// failures): sending it again later might succeed.
+    #[error("The email could not be delivered, but the failure is transient.")]
+    Transient(#[source] anyhow::Error),
+    /// The provider refused the email (4xx): sending it again will fail the same way.
+    #[error("The email was rejected by the provider.")]
+    Permanent(#[source] anyhow::Error),
+    /// The recipient is on the suppression list, the email was not sent.
+    #[error("The recipient's address is suppressed.")]
+    Suppressed,
+}
+
+impl EmailError {
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Transient(_))
+    }
+}
+
+/// What a transport reports about an email it accepted.
+#[derive(Clone, Debug, Default)]
+pub struct Receipt {
+    /// The id the provider gave the message, to look it up in its logs.
+    pub message_id: Option<String>,
+}
+
+/// An email ready to be handed over to an [`EmailTransport`].
+#[derive(Clone, Copy)]
+pub struct EmailMessage<'a> {
+    pub to: &'a Email,
+    pub subject: &'a str,
+    pub html_body: &'a str,
+    pub text_body: &'a str,
+    /// The one-click unsubscribe URL advertised through the `List-Unsubscribe` header.
+    pub list_unsubscribe: Option<&'a str>,
+}
+
+impl EmailMessage<'_> {
+    /// The extra headers to send along the message.
+    ///
+    /// A `List-Unsubscribe` URL comes with `List-Unsubscribe-Post`, so that mail clients
+    /// unsubscribe with a single POST request, as described in RFC 8058.
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        match self.list_unsubscribe {
+            Some(url) => vec![
+                ("List-Unsubscribe", format!("<{}>", url)),
+                (
+                    "List-Unsubscribe-Post",
+                    "List-Unsubscribe=One-Click".to_owned(),
+                ),
+            ],
+            None => vec![],
+        }
+    }
+}
+
+/// A backend able to deliver emails: Postmark, an SMTP server or a local file sink.
+#[async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, from: &Email, message: &EmailMessage<'_>) -> Result<(), EmailError>;
+
+    /// Send several emails at once, reporting the outcome of each message in order.
+    ///
+    /// The outer error is returned when the whole batch failed. Transports without a
+    /// batch API send the messages one by one.
+    async fn send_batch(
+        &self,
+        from: &Email,
+        messages: &[EmailMessage<'_>],
+    ) -> Result<Vec<Result<Receipt, EmailError>>, EmailError> {
+        let mut outcomes = Vec::with_capacity(messages.len());
+        for message in messages {
+            outcomes.push(self.send(from, message).await.map(|()| Receipt::default()));
+        }
+        Ok(outcomes)
+    }
+}
+
+#[derive(Clone)]
+pub struct EmailClient {
+    sender: Email,
+    transport: Arc<dyn EmailTransport>,
+    suppressions: Option<SuppressionList>,
+}
+
+impl EmailClient {
+    pub fn new(sender: Email, transport: impl EmailTransport + 'static) -> Self {
+        Self {
+            sender,
+            transport: Arc::new(transport),
+            suppressions: None,
+        }
+    }
+
+    /// Skip the recipients on the suppression list from now on.
+    pub fn with_suppressions(self, suppressions: SuppressionList) -> Self {
+        Self {
+            suppressions: Some(suppressions),
+            ..self
+        }
+    }
+
+    /// Whether each of the recipients is suppressed, in order.
+    async fn check_suppressions(&self, recipients: &[&Email]) -> Result<Vec<bool>, EmailError> {
+        match &self.suppressions {
+            Some(suppressions) => suppressions
+                .check(recipients)
+                .await
+                .map_err(EmailError::Transient),
+            None => Ok(vec![false; recipients.len()]),
+        }
+    }
+
+    /// Send a single email.
+    ///
+    /// Nothing is sent to a suppressed recipient, but the email is reported as sent: the
+    /// caller has nothing to do differently, and must not reveal that the address
+    /// bounced.
+    pub async fn send_email(
+        &self,
+        recipient: &Email,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<(), EmailError> {
+        if self.check_suppressions(&[recipient]).await?[0] {
+            tracing::info!("Skipping an email to a suppressed address");
+            return Ok(());
+        }
+
+        self.transport
+            .send(
+                &self.sender,
+                &EmailMessage {
+                    to: recipient,
+                    subject,
+                    html_body: html_content,
+                    text_body: text_content,
+                    list_unsubscribe: None,
+                },
+            )
+            .await
+    }
+
+    /// Send a batch of emails, see [`EmailTransport::send_batch`].
+    ///
+    /// The messages to suppressed recipients are not sent, and fail with
+    /// [`EmailError::Suppressed`].
+    pub async fn send_batch(
+        &self,
+        messages: &[EmailMessage<'_>],
+    ) -> Result<Vec<Result<Receipt, EmailError>>, EmailError> {
+        let recipients = messages
+            .iter()
+            .map(|message| message.to)
+            .collect::<Vec<_>>();
+        let suppressed = self.check_suppressions(&recipients).await?;
+        let allowed = messages
+            .iter()
+            .zip(&suppressed)
+            .filter(|(_, suppressed)| !**suppressed)
+            .map(|(message, _)| *message)
+            .collect::<Vec<_>>();
+
+        let mut outcomes = if allowed.is_empty() {
+            Vec::new()
+        } else {
+            self.transport.send_batch(&self.sender, &allowed).await?
+        }
+        .into_iter();
+
+        Ok(suppressed
+            .into_iter()
+            .map(|suppressed| {
+                if suppressed {
+                    return Err(EmailError::Suppressed);
+                }
+                outcomes.next().unwrap_or_else(|| {
+                    Err(EmailError::Permanent(anyhow::anyhow!(
+                        "The transport returned fewer outcomes than messages."
+                    )))
+                })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,627 @@
+use std::time;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use secrecy::{ExposeSecret, Secret};
+
+use super::{EmailError, EmailMessage, EmailTransport, Receipt};
+use crate::domain::subscriber::email::Email;
+
+/// The maximum number of messages accepted by Postmark's batch endpoint.
+pub const MAX_BATCH_SIZE: usize = 500;
+
+/// Sends emails through Postmark's `/email` and `/email/batch` JSON APIs.
+pub struct PostmarkTransport {
+    http_client: Client,
+    base_url: String,
+    authorization_token: Secret<String>,
+}
+
+impl PostmarkTransport {
+    pub fn new(
+        base_url: String,
+        authorization_token: Secret<String>,
+        timeout: time::Duration,
+    ) -> Result<Self, anyhow::Error> {
+        let http_client = Client::builder().timeout(timeout).build().context("TLS backend cannot be initialized, or the resolver cannot load the system configuration.")?;
+
+        Ok(Self {
+            http_client,
+            base_url,
+            authorization_token,
+        })
+    }
+
+    /// Send at most [`MAX_BATCH_SIZE`] messages in a single request.
+    async fn send_chunk(
+        &self,
+        from: &Email,
+        chunk: &[EmailMessage<'_>],
+    ) -> Result<Vec<Result<Receipt, EmailError>>, EmailError> {
+        let url = format!("{}/email/batch", self.base_url);
+        let request_body = chunk
+            .iter()
+            .map(|message| SendEmailRequest::new(from, message))
+            .collect::<Vec<_>>();
+        let results: Vec<SendEmailResponse> = self
+            .http_client
+            .post(&url)
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            // Postmark accepted the batch: retrying it could deliver duplicates.
+            .map_err(|e| EmailError::Permanent(e.into()))?;
+
+        if results.len() != chunk.len() {
+            return Err(EmailError::Permanent(anyhow::anyhow!(
+                "Postmark returned {} results for a batch of {} messages.",
+                results.len(),
+                chunk.len()
+            )));
+        }
+
+        Ok(results
+            .into_iter()
+            .map(SendEmailResponse::into_outcome)
+            .collect())
+    }
+}
+
+#[async_trait]
+impl EmailTransport for PostmarkTransport {
+    async fn send(&self, from: &Email, message: &EmailMessage<'_>) -> Result<(), EmailError> {
+        let url = format!("{}/email", self.base_url);
+        let request_body = SendEmailRequest::new(from, message);
+        self.http_client
+            .post(&url)
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Send the messages through Postmark's `/email/batch` endpoint, splitting them in
+    /// chunks of at most [`MAX_BATCH_SIZE`] messages.
+    ///
+    /// A chunk that fails as a whole fails each of its messages, without affecting the
+    /// outcomes of the chunks Postmark accepted.
+    async fn send_batch(
+        &self,
+        from: &Email,
+        messages: &[EmailMessage<'_>],
+    ) -> Result<Vec<Result<Receipt, EmailError>>, EmailError> {
+        let mut outcomes = Vec::with_capacity(messages.len());
+
+        for chunk in messages.chunks(MAX_BATCH_SIZE) {
+            match self.send_chunk(from, chunk).await {
+                Ok(chunk_outcomes) => outcomes.extend(chunk_outcomes),
+                Err(e) => {
+                    let is_retryable = e.is_retryable();
+                    let cause = format!("{:#}", anyhow::Error::new(e));
+                    outcomes.extend(chunk.iter().map(|_| {
+                        let e = anyhow::anyhow!(cause.clone());
+                        Err(if is_retryable {
+                            EmailError::Transient(e)
+                        } else {
+                            EmailError::Permanent(e)
+                        })
+                    }));
+                }
+            }
+        }
+
+        Ok(outcomes)
+    }
+}
+
+impl From<reqwest::Error> for EmailError {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS => {
+                Self::Transient(e.into())
+            }
+            Some(_) => Self::Permanent(e.into()),
+            None if e.is_builder() => Self::Permanent(e.into()),
+            None => Self::Transient(e.into()),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<MessageHeader>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct MessageHeader {
+    name: &'static str,
+    value: String,
+}
+
+impl<'a> SendEmailRequest<'a> {
+    fn new(from: &'a Email, message: &'a EmailMessage<'a>) -> Self {
+        Self {
+            from: from.as_ref(),
+            to: message.to.as_ref(),
+            subject: message.subject,
+            html_body: message.html_body,
+            text_body: message.text_body,
+            headers: message
+                .headers()
+                .into_iter()
+                .map(|(name, value)| MessageHeader { name, value })
+                .collect(),
+        }
+    }
+}
+
+/// The outcome of a single message, as reported by Postmark.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailResponse {
+    error_code: i64,
+    message: String,
+    /// Missing when the message was rejected.
+    #[serde(rename = "MessageID")]
+    message_id: Option<String>,
+}
+
+impl SendEmailResponse {
+    fn into_outcome(self) -> Result<Receipt, EmailError> {
+        match self.error_code {
+            0 => Ok(Receipt {
+                message_id: self.message_id,
+            }),
+            error_code => Err(EmailError::Permanent(anyhow::anyhow!(
+                "Postmark rejected the message with error code {}: {}",
+                error_code,
+                self.message
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time;
+
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::{Fake, Faker};
+    use secrecy::Secret;
+    use wiremock::matchers::{any, header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    use super::{PostmarkTransport, MAX_BATCH_SIZE};
+    use crate::domain::subscriber::email::Email;
+    use crate::email::{EmailClient, EmailError, EmailMessage};
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                body.get("From").is_some()
+                    && body.get("To").is_some()
+                    && body.get("Subject").is_some()
+                    && body.get("HtmlBody").is_some()
+                    && body.get("TextBody").is_some()
+            } else {
+                false
+            }
+        }
+    }
+
+    /// Generate a random email subject
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    /// Generate a random email content
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    /// Generate a random subscriber email
+    fn email() -> Email {
+        SafeEmail()
+            .fake::<String>()
+            .try_into()
+            .expect("fake email should be a valid email")
+    }
+
+    /// Get a test instance of `EmailClient` backed by Postmark.
+    fn email_client(base_url: String) -> Result<EmailClient, anyhow::Error> {
+        let transport = PostmarkTransport::new(
+            base_url,
+            Secret::new(Faker.fake()),
+            time::Duration::from_millis(200),
+        )?;
+        Ok(EmailClient::new(email(), transport))
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/email"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email_client =
+            email_client(mock_server.uri()).expect("the email client should be available");
+        let _ = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+    }
+
+    #[tokio::test]
+    async fn send_email_succeeds_if_the_server_returns_200() {
+        let mock_server = MockServer::start().await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email_client =
+            email_client(mock_server.uri()).expect("the email client should be available");
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email_client =
+            email_client(mock_server.uri()).expect("the email client should be available");
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[tokio::test]
+    async fn server_errors_and_rate_limiting_are_retryable() {
+        for status in [500, 503, 429] {
+            let mock_server = MockServer::start().await;
+            Mock::given(any())
+                .respond_with(ResponseTemplate::new(status))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+
+            let email_client =
+                email_client(mock_server.uri()).expect("the email client should be available");
+            let outcome = email_client
+                .send_email(&email(), &subject(), &content(), &content())
+                .await;
+
+            assert!(
+                matches!(outcome, Err(EmailError::Transient(_))),
+                "a {} response should be retryable",
+                status
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_permanent() {
+        for status in [400, 401, 422] {
+            let mock_server = MockServer::start().await;
+            Mock::given(any())
+                .respond_with(ResponseTemplate::new(status))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+
+            let email_client =
+                email_client(mock_server.uri()).expect("the email client should be available");
+            let outcome = email_client
+                .send_email(&email(), &subject(), &content(), &content())
+                .await;
+
+            assert!(
+                matches!(outcome, Err(EmailError::Permanent(_))),
+                "a {} response should not be retryable",
+                status
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let response = ResponseTemplate::new(200).set_delay(time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email_client =
+            email_client(mock_server.uri()).expect("the email client should be available");
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        assert!(matches!(outcome, Err(EmailError::Transient(_))));
+    }
+
+    /// Build a Postmark batch response reporting the given error codes.
+    fn batch_response(error_codes: &[i64]) -> ResponseTemplate {
+        let results = error_codes
+            .iter()
+            .map(|error_code| {
+                let mut result = serde_json::json!({
+                    "ErrorCode": error_code,
+                    "Message": if *error_code == 0 { "OK" } else { "Invalid email request" },
+                });
+                if *error_code == 0 {
+                    result["MessageID"] = uuid::Uuid::new_v4().to_string().into();
+                }
+                result
+            })
+            .collect::<Vec<_>>();
+        ResponseTemplate::new(200).set_body_json(results)
+    }
+
+    #[tokio::test]
+    async fn send_batch_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/email/batch"))
+            .and(method("POST"))
+            .respond_with(batch_response(&[0, 0]))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email_client =
+            email_client(mock_server.uri()).expect("the email client should be available");
+        let (recipients, subject, content) = ([email(), email()], subject(), content());
+        let messages = recipients
+            .iter()
+            .map(|recipient| EmailMessage {
+                to: recipient,
+                subject: &subject,
+                html_body: &content,
+                text_body: &content,
+                list_unsubscribe: None,
+            })
+            .collect::<Vec<_>>();
+
+        let outcomes = email_client
+            .send_batch(&messages)
+            .await
+            .expect("the batch should be accepted");
+
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(Result::is_ok));
+
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        let body = body.as_array().expect("the batch should be a json array");
+        assert_eq!(body.len(), 2);
+        assert_eq!(body[0]["To"], recipients[0].as_ref());
+        assert_eq!(body[1]["To"], recipients[1].as_ref());
+    }
+
+    #[tokio::test]
+    async fn send_batch_reports_failures_per_message() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/email/batch"))
+            .respond_with(batch_response(&[0, 300]))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email_client =
+            email_client(mock_server.uri()).expect("the email client should be available");
+        let recipients = [email(), email()];
+        let messages = recipients
+            .iter()
+            .map(|recipient| EmailMessage {
+                to: recipient,
+                subject: "Subject",
+                html_body: "<p>HTML</p>",
+                text_body: "Text",
+                list_unsubscribe: None,
+            })
+            .collect::<Vec<_>>();
+
+        let outcomes = email_client
+            .send_batch(&messages)
+            .await
+            .expect("the batch should be accepted");
+
+        assert!(matches!(&outcomes[0], Ok(receipt) if receipt.message_id.is_some()));
+        assert!(matches!(outcomes[1], Err(EmailError::Permanent(_))));
+    }
+
+    #[tokio::test]
+    async fn send_batch_splits_large_batches() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/email/batch"))
+            .and(BatchSizeMatcher(MAX_BATCH_SIZE))
+            .respond_with(batch_response(&[0; MAX_BATCH_SIZE]))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/email/batch"))
+            .and(BatchSizeMatcher(1))
+            .respond_with(batch_response(&[0]))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email_client =
+            email_client(mock_server.uri()).expect("the email client should be available");
+        let recipient = email();
+        let messages = (0..MAX_BATCH_SIZE + 1)
+            .map(|_| EmailMessage {
+                to: &recipient,
+                subject: "Subject",
+                html_body: "<p>HTML</p>",
+                text_body: "Text",
+                list_unsubscribe: None,
+            })
+            .collect::<Vec<_>>();
+
+        let outcomes = email_client
+            .send_batch(&messages)
+            .await
+            .expect("the batch should be accepted");
+
+        assert_eq!(outcomes.len(), MAX_BATCH_SIZE + 1);
+    }
+
+    #[tokio::test]
+    async fn send_batch_fails_every_message_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email_client =
+            email_client(mock_server.uri()).expect("the email client should be available");
+        let recipient = email();
+        let outcome = email_client
+            .send_batch(&[EmailMessage {
+                to: &recipient,
+                subject: "Subject",
+                html_body: "<p>HTML</p>",
+                text_body: "Text",
+                list_unsubscribe: None,
+            }])
+            .await
+            .expect("the failure should be reported per message");
+
+        assert!(matches!(outcome[..], [Err(EmailError::Transient(_))]));
+    }
+
+    #[tokio::test]
+    async fn a_failed_chunk_does_not_fail_the_chunks_already_sent() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/email/batch"))
+            .and(BatchSizeMatcher(MAX_BATCH_SIZE))
+            .respond_with(batch_response(&[0; MAX_BATCH_SIZE]))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/email/batch"))
+            .and(BatchSizeMatcher(2))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email_client =
+            email_client(mock_server.uri()).expect("the email client should be available");
+        let recipient = email();
+        let messages = (0..MAX_BATCH_SIZE + 2)
+            .map(|_| EmailMessage {
+                to: &recipient,
+                subject: "Subject",
+                html_body: "<p>HTML</p>",
+                text_body: "Text",
+                list_unsubscribe: None,
+            })
+            .collect::<Vec<_>>();
+
+        let outcomes = email_client
+            .send_batch(&messages)
+            .await
+            .expect("the failure should be reported per message");
+
+        assert_eq!(outcomes.len(), MAX_BATCH_SIZE + 2);
+        assert!(outcomes[..MAX_BATCH_SIZE].iter().all(Result::is_ok));
+        assert!(outcomes[MAX_BATCH_SIZE..]
+            .iter()
+            .all(|outcome| matches!(outcome, Err(EmailError::Transient(_)))));
+    }
+
+    #[tokio::test]
+    async fn list_unsubscribe_headers_are_sent_along_the_message() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/email/batch"))
+            .respond_with(batch_response(&[0]))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email_client =
+            email_client(mock_server.uri()).expect("the email client should be available");
+        let recipient = email();
+        email_client
+            .send_batch(&[EmailMessage {
+                to: &recipient,
+                subject: "Subject",
+                html_body: "<p>HTML</p>",
+                text_body: "Text",
+                list_unsubscribe: Some("https://example.com/unsubscribe"),
+            }])
+            .await
+            .expect("the batch should be accepted");
+
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(
+            body[0]["Headers"],
+            serde_json::json!([
+                { "Name": "List-Unsubscribe", "Value": "<https://example.com/unsubscribe>" },
+                { "Name": "List-Unsubscribe-Post", "Value": "List-Unsubscribe=One-Click" },
+            ])
+        );
+    }
+
+    struct BatchSizeMatcher(usize);
+
+    impl wiremock::Match for BatchSizeMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            serde_json::from_slice::<Vec<serde_json::Value>>(&request.body)
+                .map(|body| body.len() == self.0)
+                .unwrap_or(false)
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::time;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use lettre::{
+    message::{
+        header::{HeaderName, HeaderValue},
+        Mailbox, MultiPart,
+    },
+    transport::smtp::authentication::{Credentials, Mechanism},
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use super::{EmailError, EmailMessage, EmailTransport};
+use crate::domain::subscriber::email::Email;
+
+/// How the connection to the SMTP server is secured.
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    /// Plain-text connection, only meant for local mail catchers.
+    None,
+    /// Upgrade a plain-text connection with `STARTTLS` (usually port 587).
+    Starttls,
+    /// TLS from the first byte (usually port 465).
+    Implicit,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpAuthMechanism {
+    Plain,
+    Login,
+}
+
+impl From<SmtpAuthMechanism> for Mechanism {
+    fn from(mechanism: SmtpAuthMechanism) -> Self {
+        match mechanism {
+            SmtpAuthMechanism::Plain => Mechanism::Plain,
+            SmtpAuthMechanism::Login => Mechanism::Login,
+        }
+    }
+}
+
+/// Sends emails to an SMTP relay.
+pub struct SmtpTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpTransport {
+    pub fn new(
+        host: &str,
+        port: u16,
+        tls: SmtpTls,
+        credentials: Option<(String, Secret<String>)>,
+        auth_mechanism: SmtpAuthMechanism,
+        timeout: time::Duration,
+    ) -> Result<Self, anyhow::Error> {
+        let mut builder = match tls {
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+            SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                .context("Failed to configure STARTTLS for the SMTP relay.")?,
+            SmtpTls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+                .context("Failed to configure TLS for the SMTP relay.")?,
+        }
+        .port(port)
+        .timeout(Some(timeout));
+
+        if let Some((username, password)) = credentials {
+            builder = builder
+                .credentials(Credentials::new(
+                    username,
+                    password.expose_secret().to_owned(),
+                ))
+                .authentication(vec![auth_mechanism.into()]);
+        }
+
+        Ok(Self {
+            mailer: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SmtpTransport {
+    async fn send(&self, from: &Email, message: &EmailMessage<'_>) -> Result<(), EmailError> {
+        let email = build_message(from, message).map_err(EmailError::Permanent)?;
+
+        self.mailer.send(email).await.map_err(|e| {
+            if e.is_permanent() {
+                EmailError::Permanent(e.into())
+            } else {
+                EmailError::Transient(e.into())
+            }
+        })?;
+
+        Ok(())
+    }
+}
+
+fn build_message(from: &Email, message: &EmailMessage<'_>) -> Result<Message, anyhow::Error> {
+    let from: Mailbox = from
+        .as_ref()
+        .parse()
+        .context("The sender is not a valid mailbox.")?;
+    let to: Mailbox = message
+        .to
+        .as_ref()
+        .parse()
+        .context("The recipient is not a valid mailbox.")?;
+
+    let builder = message.headers().into_iter().fold(
+        Message::builder()
+            .from(from)
+            .to(to)
+            .subject(message.subject),
+        |builder, (name, value)| {
+            builder.raw_header(HeaderValue::new(
+                HeaderName::new_from_ascii_str(name),
+                value,
+            ))
+        },
+    );
+
+    builder
+        .multipart(MultiPart::alternative_plain_html(
+            message.text_body.to_owned(),
+            message.html_body.to_owned(),
+        ))
+        .context("Failed to build the email message.")
+}
+
+#[cfg(test)]
+mod tests {
+    use fake::faker::internet::en::SafeEmail;
+    use fake::Fake;
+
+    use super::build_message;
+    use crate::domain::subscriber::email::Email;
+    use crate::email::EmailMessage;
+
+    fn email() -> Email {
+        SafeEmail()
+            .fake::<String>()
+            .try_into()
+            .expect("fake email should be a valid email")
+    }
+
+    #[test]
+    fn messages_carry_both_the_html_and_the_text_part() {
+        let (from, to) = (email(), email());
+        let message = build_message(
+            &from,
+            &EmailMessage {
+                to: &to,
+                subject: "Subject",
+                html_body: "<p>Newsletter body as HTML</p>",
+                text_body: "Newsletter body as plain text",
+                list_unsubscribe: None,
+            },
+        )
+        .expect("the message should be built");
+
+        let formatted =
+            String::from_utf8(message.formatted()).expect("the message should be utf-8");
+        assert!(formatted.contains("multipart/alternative"));
+        assert!(formatted.contains("Newsletter body as plain text"));
+        assert!(formatted.contains("<p>Newsletter body as HTML</p>"));
+    }
+
+    #[test]
+    fn messages_carry_the_list_unsubscribe_headers() {
+        let (from, to) = (email(), email());
+        let message = build_message(
+            &from,
+            &EmailMessage {
+                to: &to,
+                subject: "Subject",
+                html_body: "<p>Newsletter body as HTML</p>",
+                text_body: "Newsletter body as plain text",
+                list_unsubscribe: Some("https://example.com/unsubscribe?token=abc"),
+            },
+        )
+        .expect("the message should be built");
+
+        let formatted =
+            String::from_utf8(message.formatted()).expect("the message should be utf-8");
+        assert!(formatted.contains("List-Unsubscribe: <https://example.com/unsubscribe?token=abc>"));
+        assert!(formatted.contains("List-Unsubscribe-Post: List-Unsubscribe=One-Click"));
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
+use secrecy::Secret;
+use sqlx::{PgExecutor, PgPool};
+
+use crate::domain::subscriber::email::Email;
+
+/// Why an address must not receive emails anymore.
+#[derive(Clone, Copy, Debug)]
+pub enum SuppressionReason {
+    /// The subscriber asked for their personal data to be erased.
+    Erasure,
+    /// The receiving server reported that the address does not exist.
+    HardBounce,
+    /// The recipient marked one of our emails as spam.
+    SpamComplaint,
+}
+
+impl SuppressionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Erasure => "erasure",
+            Self::HardBounce => "hard_bounce",
+            Self::SpamComplaint => "spam_complaint",
+        }
+    }
+}
+
+/// Add an address to the suppressions, keeping the first reason it was suppressed for.
+pub async fn suppress(
+    executor: impl PgExecutor<'_>,
+    email: &Email,
+    reason: SuppressionReason,
+    hmac_key: &Secret<String>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        insert into suppressions (email_hash, reason)
+        values ($1, $2)
+        on conflict do nothing
+        "#,
+        email.suppression_hash(hmac_key),
+        reason.as_str(),
+    )
+    .execute(executor)
+    .await
+    .context("Failed to store the suppression of the address.")?;
+
+    Ok(())
+}
+
+/// The addresses `EmailClient` must not send anything to.
+#[derive(Clone)]
+pub struct SuppressionList {
+    pool: PgPool,
+    hmac_key: Secret<String>,
+}
+
+impl SuppressionList {
+    pub fn new(pool: PgPool, hmac_key: Secret<String>) -> Self {
+        Self { pool, hmac_key }
+    }
+
+    /// Whether each of the recipients must not be sent anything, in order.
+    ///
+    /// Erased subscribers are not kept out: they may opt in again, and the confirmation
+    /// email is how they do it.
+    pub async fn check(&self, recipients: &[&Email]) -> Result<Vec<bool>, anyhow::Error> {
+        self.check_reasons(
+            recipients,
+            &[
+                SuppressionReason::HardBounce,
+                SuppressionReason::SpamComplaint,
+            ],
+        )
+        .await
+    }
+
+    /// Whether each of the recipients is suppressed for any reason, erasure included, in
+    /// order.
+    pub async fn check_all(&self, recipients: &[&Email]) -> Result<Vec<bool>, anyhow::Error> {
+        self.check_reasons(
+            recipients,
+            &[
+                SuppressionReason::Erasure,
+                SuppressionReason::HardBounce,
+                SuppressionReason::SpamComplaint,
+            ],
+        )
+        .await
+    }
+
+    async fn check_reasons(
+        &self,
+        recipients: &[&Email],
+        reasons: &[SuppressionReason],
+    ) -> Result<Vec<bool>, anyhow::Error> {
+        let hashes = recipients
+            .iter()
+            .map(|recipient| recipient.suppression_hash(&self.hmac_key))
+            .collect::<Vec<_>>();
+        let reasons = reasons
+            .iter()
+            .map(|reason| reason.as_str().to_owned())
+            .collect::<Vec<_>>();
+
+        let suppressed = sqlx::query_scalar!(
+            r#"
+            select email_hash from suppressions
+            where email_hash = any($1) and reason = any($2)
+            "#,
+            &hashes,
+            &reasons,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to look up suppressed addresses.")?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+        Ok(hashes
+            .iter()
+            .map(|hash| suppressed.contains(hash))
+            .collect())
+    }
+}
@@ -0,0 +1,433 @@
+use askama::Template;
+
+use super::tracking::TrackingLinks;
+use crate::domain::validation::ValidationError;
+
+/// The variables newsletter issues can refer to as `{{ variable }}`.
+pub const VARIABLES: [&str; 3] = ["name", "email", "unsubscribe_url"];
+
+/// An email rendered from one of the layouts below, in both formats.
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+/// The email sent to new subscribers, with the link confirming they want to join a list.
+pub struct ConfirmationEmail<'a> {
+    pub name: &'a str,
+    pub list_name: &'a str,
+    pub confirmation_link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/confirmation.html")]
+struct ConfirmationHtml<'a> {
+    email: &'a ConfirmationEmail<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "email/confirmation.txt")]
+struct ConfirmationText<'a> {
+    email: &'a ConfirmationEmail<'a>,
+}
+
+impl ConfirmationEmail<'_> {
+    pub fn render(&self) -> Result<RenderedEmail, askama::Error> {
+        Ok(RenderedEmail {
+            subject: "Welcome!".to_owned(),
+            html_body: ConfirmationHtml { email: self }.render()?,
+            text_body: ConfirmationText { email: self }.render()?,
+        })
+    }
+}
+
+/// The email sent once a subscription to a list is confirmed.
+pub struct WelcomeEmail<'a> {
+    pub name: &'a str,
+    pub list_name: &'a str,
+    pub unsubscribe_url: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/welcome.html")]
+struct WelcomeHtml<'a> {
+    email: &'a WelcomeEmail<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "email/welcome.txt")]
+struct WelcomeText<'a> {
+    email: &'a WelcomeEmail<'a>,
+}
+
+impl WelcomeEmail<'_> {
+    pub fn render(&self) -> Result<RenderedEmail, askama::Error> {
+        Ok(RenderedEmail {
+            subject: "Your subscription is confirmed".to_owned(),
+            html_body: WelcomeHtml { email: self }.render()?,
+            text_body: WelcomeText { email: self }.render()?,
+        })
+    }
+}
+
+/// The email sent to a subscriber who asks for the data held about them, with the link
+/// to download or delete it.
+pub struct DataRequestEmail<'a> {
+    pub name: &'a str,
+    pub data_link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/data_request.html")]
+struct DataRequestHtml<'a> {
+    email: &'a DataRequestEmail<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "email/data_request.txt")]
+struct DataRequestText<'a> {
+    email: &'a DataRequestEmail<'a>,
+}
+
+impl DataRequestEmail<'_> {
+    pub fn render(&self) -> Result<RenderedEmail, askama::Error> {
+        Ok(RenderedEmail {
+            subject: "Your personal data".to_owned(),
+            html_body: DataRequestHtml { email: self }.render()?,
+            text_body: DataRequestText { email: self }.render()?,
+        })
+    }
+}
+
+/// A newsletter issue addressed to a single subscriber.
+pub struct NewsletterEmail<'a> {
+    pub title: &'a str,
+    pub html_content: &'a str,
+    pub text_content: &'a str,
+    pub recipient: &'a Recipient<'a>,
+    /// Set when the opens and clicks of the recipient are tracked.
+    pub tracking: Option<&'a TrackingLinks<'a>>,
+}
+
+#[derive(Template)]
+#[template(path = "email/newsletter.html")]
+struct NewsletterHtml<'a> {
+    /// The content of the issue, already personalised and escaped.
+    body: &'a str,
+    unsubscribe_url: &'a str,
+    open_url: Option<String>,
+    tracking_opt_out_url: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "email/newsletter.txt")]
+struct NewsletterText<'a> {
+    body: &'a str,
+    unsubscribe_url: &'a str,
+}
+
+impl NewsletterEmail<'_> {
+    /// Fill the variables of the issue with the details of the recipient, then wrap it
+    /// in the newsletter layout.
+    ///
+    /// When tracked, the links of the HTML version go through the click tracking route,
+    /// except the unsubscribe link, and a tracking pixel is added.
+    pub fn render(&self) -> Result<RenderedEmail, askama::Error> {
+        let mut body = personalise(self.html_content, self.recipient, Format::Html);
+        if let Some(tracking) = self.tracking {
+            body = track_links(&body, tracking, &[self.recipient.unsubscribe_url]);
+        }
+        let html_body = NewsletterHtml {
+            body: &body,
+            unsubscribe_url: self.recipient.unsubscribe_url,
+            open_url: self.tracking.map(TrackingLinks::open_url),
+            tracking_opt_out_url: self.tracking.map(TrackingLinks::opt_out_url),
+        }
+        .render()?;
+        let text_body = NewsletterText {
+            body: &personalise(self.text_content, self.recipient, Format::Text),
+            unsubscribe_url: self.recipient.unsubscribe_url,
+        }
+        .render()?;
+
+        Ok(RenderedEmail {
+            subject: personalise(self.title, self.recipient, Format::Text),
+            html_body,
+            text_body,
+        })
+    }
+}
+
+/// The values of the variables of a newsletter issue, for one subscriber.
+pub struct Recipient<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+    pub unsubscribe_url: &'a str,
+}
+
+impl Recipient<'_> {
+    fn get(&self, variable: &str) -> Option<&str> {
+        match variable {
+            "name" => Some(self.name),
+            "email" => Some(self.email),
+            "unsubscribe_url" => Some(self.unsubscribe_url),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Html,
+    Text,
+}
+
+/// Replace every `{{ variable }}` with its value for the recipient, escaped in HTML
+/// content. Unknown variables are left untouched.
+fn personalise(content: &str, recipient: &Recipient, format: Format) -> String {
+    let mut personalised = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some((before, variable, after)) = next_placeholder(rest) {
+        personalised.push_str(before);
+        match (recipient.get(variable.trim()), format) {
+            (Some(value), Format::Html) => personalised.push_str(&escape_html(value)),
+            (Some(value), Format::Text) => personalised.push_str(value),
+            (None, _) => personalised.push_str(&rest[before.len()..rest.len() - after.len()]),
+        }
+        rest = after;
+    }
+    personalised.push_str(rest);
+    personalised
+}
+
+/// Check that the content only refers to known variables.
+pub fn check_variables(content: &str) -> Result<(), ValidationError> {
+    let mut rest = content;
+    while let Some((_, variable, after)) = next_placeholder(rest) {
+        let variable = variable.trim();
+        if !VARIABLES.contains(&variable) {
+            return Err(ValidationError::new(
+                "unknown_variable",
+                format!(
+                    "{{{{ {} }}}} is not one of the variables: {}",
+                    variable,
+                    VARIABLES.join(", ")
+                ),
+            ));
+        }
+        rest = after;
+    }
+
+    Ok(())
+}
+
+/// Split the content around its first `{{ variable }}`.
+fn next_placeholder(content: &str) -> Option<(&str, &str, &str)> {
+    let start = content.find("{{")?;
+    let end = start + 2 + content[start + 2..].find("}}")?;
+    Some((
+        &content[..start],
+        &content[start + 2..end],
+        &content[end + 2..],
+    ))
+}
+
+/// Point the absolute links of HTML content to the click tracking route, except the
+/// `untracked` ones.
+///
+/// Only double-quoted `href` attributes are rewritten, as written by the markdown
+/// renderer and by most editors.
+fn track_links(html: &str, tracking: &TrackingLinks, untracked: &[&str]) -> String {
+    const ATTRIBUTE: &str = "href=\"";
+
+    let mut tracked = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(ATTRIBUTE) {
+        let value_start = start + ATTRIBUTE.len();
+        let Some(length) = rest[value_start..].find('"') else {
+            break;
+        };
+        let value = &rest[value_start..value_start + length];
+        tracked.push_str(&rest[..value_start]);
+
+        let url = unescape_html(value);
+        let is_absolute = url.starts_with("https://") || url.starts_with("http://");
+        if is_absolute && !untracked.contains(&url.as_str()) {
+            tracked.push_str(&escape_html(&tracking.click_url(&url)));
+        } else {
+            tracked.push_str(value);
+        }
+        rest = &rest[value_start + length..];
+    }
+    tracked.push_str(rest);
+    tracked
+}
+
+fn unescape_html(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    use super::{check_variables, ConfirmationEmail, NewsletterEmail, Recipient, TrackingLinks};
+
+    fn recipient() -> Recipient<'static> {
+        Recipient {
+            name: "Ursula <Le Guin>",
+            email: "ursula@example.com",
+            unsubscribe_url: "https://example.com/unsubscribe?subscriber_id=1&token=abc",
+        }
+    }
+
+    #[test]
+    fn newsletters_are_personalised() {
+        let recipient = recipient();
+        let email = NewsletterEmail {
+            title: "News for {{ name }}",
+            html_content: "<p>Hello {{name}}!</p>",
+            text_content: "Hello {{ name }}, this was sent to {{ email }}.",
+            recipient: &recipient,
+            tracking: None,
+        }
+        .render()
+        .unwrap();
+
+        assert_eq!(email.subject, "News for Ursula <Le Guin>");
+        assert!(email
+            .html_body
+            .contains("<p>Hello Ursula &lt;Le Guin&gt;!</p>"));
+        assert!(email
+            .text_body
+            .starts_with("Hello Ursula <Le Guin>, this was sent to ursula@example.com."));
+    }
+
+    #[test]
+    fn newsletters_end_with_an_unsubscribe_link() {
+        let recipient = recipient();
+        let email = NewsletterEmail {
+            title: "News",
+            html_content: "<p>Hello</p>",
+            text_content: "Hello",
+            recipient: &recipient,
+            tracking: None,
+        }
+        .render()
+        .unwrap();
+
+        assert!(email
+            .html_body
+            .contains("href=\"https://example.com/unsubscribe?subscriber_id=1&amp;token=abc\""));
+        assert!(email
+            .text_body
+            .ends_with("Unsubscribe: https://example.com/unsubscribe?subscriber_id=1&token=abc"));
+    }
+
+    #[test]
+    fn unknown_variables_are_left_untouched() {
+        let recipient = recipient();
+        let email = NewsletterEmail {
+            title: "News",
+            html_content: "<p>{{ unknown }}</p>",
+            text_content: "{{ unknown }} {{ name",
+            recipient: &recipient,
+            tracking: None,
+        }
+        .render()
+        .unwrap();
+
+        assert!(email.html_body.contains("<p>{{ unknown }}</p>"));
+        assert!(email.text_body.starts_with("{{ unknown }} {{ name"));
+    }
+
+    #[test]
+    fn tracked_newsletters_rewrite_their_links() {
+        let recipient = recipient();
+        let hmac_key = Secret::new(Uuid::new_v4().to_string());
+        let tracking = TrackingLinks {
+            base_url: "https://example.com",
+            newsletter_issue_id: Uuid::new_v4(),
+            subscriber_id: Uuid::new_v4(),
+            hmac_key: &hmac_key,
+        };
+        let email = NewsletterEmail {
+            title: "News",
+            html_content: concat!(
+                r#"<p><a href="https://example.org/?a=1&amp;b=2">Read</a> "#,
+                r#"<a href="mailto:ursula@example.com">Write</a> "#,
+                r#"<a href="{{ unsubscribe_url }}">Leave</a></p>"#,
+            ),
+            text_content: "Read https://example.org/",
+            recipient: &recipient,
+            tracking: Some(&tracking),
+        }
+        .render()
+        .unwrap();
+
+        assert!(email
+            .html_body
+            .contains("href=\"https://example.com/api/v1/tracking/click?issue_id="));
+        assert!(email
+            .html_body
+            .contains("&amp;url=https%3A%2F%2Fexample.org%2F%3Fa%3D1%26b%3D2&amp;token="));
+        assert!(email
+            .html_body
+            .contains(r#"href="mailto:ursula@example.com""#));
+        assert!(email.html_body.contains(
+            r#"<a href="https://example.com/unsubscribe?subscriber_id=1&amp;token=abc">Leave</a>"#
+        ));
+        assert!(email
+            .html_body
+            .contains("https://example.com/api/v1/tracking/open?issue_id="));
+        assert!(email
+            .html_body
+            .contains("https://example.com/tracking?subscriber_id="));
+        assert!(!email.text_body.contains("tracking"));
+    }
+
+    #[test]
+    fn only_known_variables_are_accepted() {
+        assert!(check_variables("Hello {{ name }}, {{email}} {{ unsubscribe_url }}").is_ok());
+        assert_eq!(
+            check_variables("Hello {{ first_name }}").unwrap_err().code,
+            "unknown_variable"
+        );
+    }
+
+    #[test]
+    fn confirmation_emails_escape_the_name() {
+        let email = ConfirmationEmail {
+            name: "<b>Ursula</b>",
+            list_name: "Newsletter",
+            confirmation_link: "https://example.com/subscriptions/confirm?subscription_token=abc",
+        }
+        .render()
+        .unwrap();
+
+        assert!(email.html_body.contains("&lt;b&gt;Ursula&lt;/b&gt;"));
+        assert!(email.text_body.contains("<b>Ursula</b>"));
+    }
+}
@@ -0,0 +1,100 @@
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::domain::subscriber::{
+    tracking_token::{TrackedEvent, TrackingToken},
+    unsubscribe_token::UnsubscribeToken,
+};
+
+/// The links recording when a subscriber opens an issue or follows one of its links.
+pub struct TrackingLinks<'a> {
+    pub base_url: &'a str,
+    pub newsletter_issue_id: Uuid,
+    pub subscriber_id: Uuid,
+    pub hmac_key: &'a Secret<String>,
+}
+
+impl TrackingLinks<'_> {
+    /// The URL of the invisible image reporting that the issue was opened.
+    pub fn open_url(&self) -> String {
+        let token = TrackingToken::generate(
+            self.newsletter_issue_id,
+            self.subscriber_id,
+            TrackedEvent::Open,
+            self.hmac_key,
+        );
+        self.link(
+            "/api/v1/tracking/open",
+            &[
+                ("issue_id", &self.newsletter_issue_id.to_string()),
+                ("subscriber_id", &self.subscriber_id.to_string()),
+                ("token", &token.to_string()),
+            ],
+        )
+    }
+
+    /// The URL recording the click before redirecting to `url`.
+    pub fn click_url(&self, url: &str) -> String {
+        let token = TrackingToken::generate(
+            self.newsletter_issue_id,
+            self.subscriber_id,
+            TrackedEvent::Click { url },
+            self.hmac_key,
+        );
+        self.link(
+            "/api/v1/tracking/click",
+            &[
+                ("issue_id", &self.newsletter_issue_id.to_string()),
+                ("subscriber_id", &self.subscriber_id.to_string()),
+                ("url", url),
+                ("token", &token.to_string()),
+            ],
+        )
+    }
+
+    /// The page where the subscriber stops the tracking of their opens and clicks.
+    ///
+    /// It is authorised by the same token as the unsubscribe page: both manage the
+    /// subscription from a link found in the newsletter.
+    pub fn opt_out_url(&self) -> String {
+        let token = UnsubscribeToken::generate(self.subscriber_id, self.hmac_key);
+        self.link(
+            "/tracking",
+            &[
+                ("subscriber_id", &self.subscriber_id.to_string()),
+                ("token", token.as_ref()),
+            ],
+        )
+    }
+
+    fn link(&self, path: &str, params: &[(&str, &str)]) -> String {
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params)
+            .finish();
+        format!("{}{}?{}", self.base_url, path, query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    use super::TrackingLinks;
+
+    #[test]
+    fn click_urls_encode_the_target() {
+        let hmac_key = Secret::new(Uuid::new_v4().to_string());
+        let links = TrackingLinks {
+            base_url: "https://example.com",
+            newsletter_issue_id: Uuid::new_v4(),
+            subscriber_id: Uuid::new_v4(),
+            hmac_key: &hmac_key,
+        };
+
+        let url = links.click_url("https://example.org/a?b=c&d=e");
+
+        assert!(url.starts_with("https://example.com/api/v1/tracking/click?issue_id="));
+        assert!(url.contains("&url=https%3A%2F%2Fexample.org%2Fa%3Fb%3Dc%26d%3De&"));
+    }
+}
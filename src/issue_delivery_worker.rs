@@ -0,0 +1,703 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Context;
+use rand::Rng;
+use secrecy::Secret;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use tracing::Span;
+use uuid::Uuid;
+
+use crate::{
+    config::{RetrySettings, Settings},
+    domain::subscriber::{email::Email, unsubscribe_token::UnsubscribeToken},
+    email::{
+        postmark::MAX_BATCH_SIZE,
+        suppression::SuppressionList,
+        template::{NewsletterEmail, Recipient},
+        tracking::TrackingLinks,
+        EmailClient, EmailError, EmailMessage, Receipt,
+    },
+};
+
+/// How long the worker sleeps when there is nothing to deliver.
+const EMPTY_QUEUE_BACKOFF: Duration = Duration::from_secs(10);
+
+/// How long the worker sleeps after an unexpected error.
+const ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// How many delivery tasks are sent to the email provider at once.
+const BATCH_SIZE: usize = MAX_BATCH_SIZE;
+
+pub enum ExecutionOutcome {
+    TaskCompleted,
+    EmptyQueue,
+}
+
+/// Delivers the newsletter issues queued in `issue_delivery_queue`.
+pub struct IssueDeliveryWorker {
+    pool: PgPool,
+    email_client: EmailClient,
+    retry_settings: RetrySettings,
+    base_url: String,
+    hmac_key: Secret<String>,
+}
+
+impl IssueDeliveryWorker {
+    pub fn with(pool: PgPool, config: &Settings) -> Result<Self, anyhow::Error> {
+        let suppressions = SuppressionList::new(pool.clone(), config.application.hmac_key.clone());
+        Ok(Self {
+            pool,
+            email_client: config
+                .email_client
+                .client()?
+                .with_suppressions(suppressions),
+            retry_settings: config.email_client.retry.clone(),
+            base_url: config.application.base_url.clone(),
+            hmac_key: config.application.hmac_key.clone(),
+        })
+    }
+
+    /// Deliver queued newsletter issues until the process is stopped.
+    ///
+    /// Tasks are dequeued with `FOR UPDATE SKIP LOCKED`, so any number of workers can
+    /// drain the same queue concurrently.
+    pub async fn run_until_stopped(self) -> Result<(), anyhow::Error> {
+        loop {
+            match self.try_execute_task().await {
+                Ok(ExecutionOutcome::EmptyQueue) => tokio::time::sleep(EMPTY_QUEUE_BACKOFF).await,
+                Ok(ExecutionOutcome::TaskCompleted) => {}
+                Err(_) => tokio::time::sleep(ERROR_BACKOFF).await,
+            }
+        }
+    }
+
+    /// Dequeue a batch of delivery tasks and attempt to deliver them with a single call
+    /// to the email provider.
+    ///
+    /// Every message is personalised with the recipient's details and unsubscribe links,
+    /// and wrapped in the newsletter layout. Tasks whose
+    /// subscriber has left in the meantime, or whose address is suppressed, are dropped
+    /// without sending anything.
+    ///
+    /// Transient failures are rescheduled with an exponential backoff until
+    /// `max_attempts` is reached; permanent failures and exhausted tasks are moved to
+    /// the dead-letter table. Every attempt is logged in the `deliveries` table.
+    #[tracing::instrument(skip_all, fields(n_tasks=tracing::field::Empty), err)]
+    pub async fn try_execute_task(&self) -> Result<ExecutionOutcome, anyhow::Error> {
+        let (mut transaction, tasks) = dequeue_tasks(&self.pool).await?;
+        if tasks.is_empty() {
+            return Ok(ExecutionOutcome::EmptyQueue);
+        }
+        Span::current().record("n_tasks", tasks.len());
+
+        let (tasks, dropped): (Vec<_>, Vec<_>) =
+            tasks.into_iter().partition(|task| task.is_subscribed);
+        let mut counts: HashMap<Uuid, DeliveryCounts> = HashMap::new();
+        for task in &dropped {
+            counts.entry(task.newsletter_issue_id).or_default();
+            tracing::info!(
+                newsletter_issue_id = %task.newsletter_issue_id,
+                subscriber_email = %task.subscriber_email,
+                "The subscriber is no longer on the lists of the issue. Dropping the delivery task.",
+            );
+            delete_task(&mut transaction, task).await?;
+        }
+
+        let issue_ids = tasks
+            .iter()
+            .map(|task| task.newsletter_issue_id)
+            .collect::<Vec<_>>();
+        let issues = get_issues(&self.pool, &issue_ids).await?;
+
+        let emails = tasks
+            .iter()
+            .map(|task| self.personalise(task, &issues))
+            .collect::<Vec<_>>();
+        let messages = emails
+            .iter()
+            .map(|email| match email {
+                Ok(email) => Ok(email.message()),
+                Err(failure) => Err(failure.clone()),
+            })
+            .collect::<Vec<Result<_, DeliveryFailure>>>();
+        let batch = messages
+            .iter()
+            .filter_map(|message| message.as_ref().ok().copied())
+            .collect::<Vec<_>>();
+
+        let mut outcomes = match self.email_client.send_batch(&batch).await {
+            Ok(outcomes) => outcomes
+                .into_iter()
+                .map(|outcome| outcome.map_err(DeliveryFailure::from))
+                .collect::<Vec<_>>(),
+            Err(e) => vec![Err(DeliveryFailure::from(e)); batch.len()],
+        }
+        .into_iter();
+
+        for (task, message) in tasks.iter().zip(messages) {
+            let outcome = match message {
+                Ok(_) => outcomes
+                    .next()
+                    .context("The email client returned fewer outcomes than messages.")?,
+                Err(failure) => Err(failure),
+            };
+            let completion =
+                complete_task(&mut transaction, task, outcome, &self.retry_settings).await?;
+            counts
+                .entry(task.newsletter_issue_id)
+                .or_default()
+                .record(completion);
+        }
+        for (issue_id, counts) in counts {
+            record_progress(&mut transaction, issue_id, counts).await?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction to complete delivery tasks.")?;
+
+        Ok(ExecutionOutcome::TaskCompleted)
+    }
+
+    /// Build the email sent to the subscriber of a task, with their name and unsubscribe
+    /// links, and tracking links unless the issue or the subscriber opted out.
+    fn personalise(
+        &self,
+        task: &DeliveryTask,
+        issues: &HashMap<Uuid, NewsletterIssue>,
+    ) -> Result<PersonalisedIssue, DeliveryFailure> {
+        let recipient = Email::try_from(task.subscriber_email.clone())
+            .map_err(|e| DeliveryFailure::permanent(format!("Invalid subscriber email: {}", e)))?;
+        let issue = issues.get(&task.newsletter_issue_id).ok_or_else(|| {
+            DeliveryFailure::permanent("The newsletter issue does not exist.".to_owned())
+        })?;
+        let subscriber_id = task.subscriber_id.ok_or_else(|| {
+            DeliveryFailure::permanent("The subscriber does not exist.".to_owned())
+        })?;
+
+        let token = UnsubscribeToken::generate(subscriber_id, &self.hmac_key);
+        let query = format!("subscriber_id={}&token={}", subscriber_id, token);
+        let unsubscribe_page = format!("{}/unsubscribe?{}", self.base_url, query);
+        let one_click_unsubscribe = format!(
+            "{}/api/v1/subscriptions/unsubscribe?{}",
+            self.base_url, query
+        );
+
+        let tracking = TrackingLinks {
+            base_url: &self.base_url,
+            newsletter_issue_id: issue.newsletter_issue_id,
+            subscriber_id,
+            hmac_key: &self.hmac_key,
+        };
+        let is_tracked = issue.tracking && !task.tracking_opt_out.unwrap_or_default();
+
+        let email = NewsletterEmail {
+            title: &issue.title,
+            html_content: &issue.html_content,
+            text_content: &issue.text_content,
+            recipient: &Recipient {
+                name: task.subscriber_name.as_deref().unwrap_or_default(),
+                email: recipient.as_ref(),
+                unsubscribe_url: &unsubscribe_page,
+            },
+            tracking: is_tracked.then_some(&tracking),
+        }
+        .render()
+        .map_err(|e| {
+            DeliveryFailure::permanent(format!("Failed to render the newsletter issue: {}", e))
+        })?;
+
+        Ok(PersonalisedIssue {
+            recipient,
+            subject: email.subject,
+            html_body: email.html_body,
+            text_body: email.text_body,
+            list_unsubscribe: one_click_unsubscribe,
+        })
+    }
+}
+
+/// A newsletter issue addressed to a single subscriber.
+struct PersonalisedIssue {
+    recipient: Email,
+    subject: String,
+    html_body: String,
+    text_body: String,
+    list_unsubscribe: String,
+}
+
+impl PersonalisedIssue {
+    fn message(&self) -> EmailMessage<'_> {
+        EmailMessage {
+            to: &self.recipient,
+            subject: &self.subject,
+            html_body: &self.html_body,
+            text_body: &self.text_body,
+            list_unsubscribe: Some(&self.list_unsubscribe),
+        }
+    }
+}
+
+/// What happened to a task once its delivery was attempted.
+#[derive(Clone, Copy)]
+enum Completion {
+    Delivered,
+    Rescheduled,
+    DeadLettered,
+    Dropped,
+}
+
+/// The deliveries of an issue settled by a batch.
+#[derive(Default)]
+struct DeliveryCounts {
+    delivered: i32,
+    failed: i32,
+}
+
+impl DeliveryCounts {
+    fn record(&mut self, completion: Completion) {
+        match completion {
+            Completion::Delivered => self.delivered += 1,
+            Completion::DeadLettered => self.failed += 1,
+            Completion::Rescheduled | Completion::Dropped => {}
+        }
+    }
+}
+
+/// Delete a delivered task, reschedule it or move it to the dead letters.
+#[tracing::instrument(
+    skip_all,
+    fields(
+        newsletter_issue_id = %task.newsletter_issue_id,
+        subscriber_email = %task.subscriber_email
+    )
+)]
+async fn complete_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    outcome: Result<Receipt, DeliveryFailure>,
+    retry_settings: &RetrySettings,
+) -> Result<Completion, anyhow::Error> {
+    let n_attempts = task.n_attempts + 1;
+    match outcome {
+        Ok(receipt) => {
+            let attempt = DeliveryAttempt::Sent {
+                message_id: receipt.message_id.as_deref(),
+            };
+            record_delivery(transaction, task, n_attempts, attempt).await?;
+            delete_task(transaction, task).await?;
+            Ok(Completion::Delivered)
+        }
+        Err(failure) if failure.is_suppressed => {
+            tracing::info!("The subscriber's address is suppressed. Dropping the delivery task.");
+            record_delivery(transaction, task, n_attempts, DeliveryAttempt::Suppressed).await?;
+            delete_task(transaction, task).await?;
+            Ok(Completion::Dropped)
+        }
+        Err(failure)
+            if failure.is_retryable && (n_attempts as u32) < retry_settings.max_attempts =>
+        {
+            tracing::warn!(
+                error.message = %failure.message,
+                n_attempts,
+                "Failed to deliver issue to a confirmed subscriber. Retrying later.",
+            );
+            let attempt = DeliveryAttempt::Deferred {
+                error: &failure.message,
+            };
+            record_delivery(transaction, task, n_attempts, attempt).await?;
+            let delay = retry_delay(retry_settings, n_attempts as u32);
+            reschedule_task(transaction, task, n_attempts, delay, &failure.message).await?;
+            Ok(Completion::Rescheduled)
+        }
+        Err(failure) => {
+            tracing::error!(
+                error.message = %failure.message,
+                n_attempts,
+                "Failed to deliver issue to a confirmed subscriber. Moving it to dead letters.",
+            );
+            let attempt = DeliveryAttempt::Failed {
+                error: &failure.message,
+            };
+            record_delivery(transaction, task, n_attempts, attempt).await?;
+            dead_letter_task(transaction, task, n_attempts, &failure.message).await?;
+            Ok(Completion::DeadLettered)
+        }
+    }
+}
+
+/// What happened to an attempt to deliver a task, as logged in `deliveries`.
+#[derive(Clone, Copy)]
+enum DeliveryAttempt<'a> {
+    /// The email provider accepted the email.
+    Sent { message_id: Option<&'a str> },
+    /// The attempt failed, the task was rescheduled.
+    Deferred { error: &'a str },
+    /// The attempt failed for good, the task was moved to the dead letters.
+    Failed { error: &'a str },
+    /// Nothing was sent because the address is suppressed.
+    Suppressed,
+}
+
+impl DeliveryAttempt<'_> {
+    fn status(&self) -> &'static str {
+        match self {
+            Self::Sent { .. } => "sent",
+            Self::Deferred { .. } => "deferred",
+            Self::Failed { .. } => "failed",
+            Self::Suppressed => "suppressed",
+        }
+    }
+}
+
+/// Exponential backoff with random jitter:
+/// `min(base_delay * 2^(n_attempts - 1), max_delay) + [0, jitter]`.
+fn retry_delay(retry_settings: &RetrySettings, n_attempts: u32) -> Duration {
+    let exponential = retry_settings
+        .base_delay()
+        .saturating_mul(2u32.saturating_pow(n_attempts.saturating_sub(1)))
+        .min(retry_settings.max_delay());
+    let jitter = retry_settings
+        .jitter()
+        .mul_f64(rand::thread_rng().gen_range(0.0..=1.0));
+
+    exponential.saturating_add(jitter)
+}
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+#[derive(Clone)]
+struct DeliveryFailure {
+    is_retryable: bool,
+    /// The email was not sent because the address is suppressed.
+    is_suppressed: bool,
+    message: String,
+}
+
+impl DeliveryFailure {
+    fn permanent(message: String) -> Self {
+        Self {
+            is_retryable: false,
+            is_suppressed: false,
+            message,
+        }
+    }
+}
+
+impl From<EmailError> for DeliveryFailure {
+    fn from(e: EmailError) -> Self {
+        Self {
+            is_retryable: e.is_retryable(),
+            is_suppressed: matches!(e, EmailError::Suppressed),
+            message: format!("{:?}", e),
+        }
+    }
+}
+
+struct DeliveryTask {
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+    n_attempts: i32,
+    subscriber_id: Option<Uuid>,
+    subscriber_name: Option<String>,
+    tracking_opt_out: Option<bool>,
+    /// Whether the subscriber is still confirmed on one of the lists of the issue.
+    is_subscribed: bool,
+}
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_tasks(pool: &PgPool) -> Result<(PgTransaction, Vec<DeliveryTask>), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let tasks = sqlx::query_as!(
+        DeliveryTask,
+        r#"
+        select
+            q.newsletter_issue_id,
+            q.subscriber_email,
+            q.n_attempts,
+            s.id as "subscriber_id?",
+            s.name as "subscriber_name?",
+            s.tracking_opt_out as "tracking_opt_out?",
+            exists(
+                select 1
+                from list_memberships m
+                join newsletter_issue_lists il on il.list_id = m.list_id
+                where m.subscriber_id = s.id
+                    and il.newsletter_issue_id = q.newsletter_issue_id
+                    and m.status = 'confirmed'
+            ) as "is_subscribed!"
+        from issue_delivery_queue q
+        left join subscriptions s on s.email = q.subscriber_email
+        where q.execute_after <= now()
+        for update of q
+        skip locked
+        limit $1
+        "#,
+        BATCH_SIZE as i64
+    )
+    .fetch_all(&mut *transaction)
+    .await
+    .context("Failed to dequeue delivery tasks.")?;
+
+    Ok((transaction, tasks))
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        delete from issue_delivery_queue
+        where
+            newsletter_issue_id = $1 and
+            subscriber_email = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email
+    );
+
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to delete a delivery task.")?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn reschedule_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    n_attempts: i32,
+    delay: Duration,
+    error: &str,
+) -> Result<(), anyhow::Error> {
+    let execute_after = chrono::Utc::now()
+        + chrono::Duration::from_std(delay).context("The retry delay is out of range.")?;
+    let query = sqlx::query!(
+        r#"
+        update issue_delivery_queue
+        set
+            n_attempts = $3,
+            execute_after = $4,
+            last_error = $5
+        where
+            newsletter_issue_id = $1 and
+            subscriber_email = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        n_attempts,
+        execute_after,
+        error
+    );
+
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to reschedule a delivery task.")?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn dead_letter_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    n_attempts: i32,
+    error: &str,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        insert into issue_delivery_dead_letters (
+            newsletter_issue_id,
+            subscriber_email,
+            n_attempts,
+            last_error,
+            failed_at
+        )
+        values ($1, $2, $3, $4, now())
+        on conflict (newsletter_issue_id, subscriber_email) do update
+        set
+            n_attempts = excluded.n_attempts,
+            last_error = excluded.last_error,
+            failed_at = excluded.failed_at
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        n_attempts,
+        error
+    );
+
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to store a dead-lettered delivery task.")?;
+
+    delete_task(transaction, task).await
+}
+
+#[tracing::instrument(skip_all, fields(status = attempt.status()))]
+async fn record_delivery(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    n_attempts: i32,
+    attempt: DeliveryAttempt<'_>,
+) -> Result<(), anyhow::Error> {
+    let (message_id, error) = match attempt {
+        DeliveryAttempt::Sent { message_id } => (message_id, None),
+        DeliveryAttempt::Deferred { error } | DeliveryAttempt::Failed { error } => {
+            (None, Some(error))
+        }
+        DeliveryAttempt::Suppressed => (None, None),
+    };
+    let query = sqlx::query!(
+        r#"
+        insert into deliveries (
+            delivery_id,
+            newsletter_issue_id,
+            subscriber_id,
+            subscriber_email,
+            n_attempts,
+            status,
+            provider_message_id,
+            error
+        )
+        values ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+        Uuid::new_v4(),
+        task.newsletter_issue_id,
+        task.subscriber_id,
+        task.subscriber_email,
+        n_attempts,
+        attempt.status(),
+        message_id,
+        error
+    );
+
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to log a delivery attempt.")?;
+
+    Ok(())
+}
+
+/// Add the deliveries settled by a batch to the counts of an issue, and mark it as
+/// `sent` once none of its tasks is left in the queue.
+#[tracing::instrument(skip(transaction, counts))]
+async fn record_progress(
+    transaction: &mut PgTransaction,
+    newsletter_issue_id: Uuid,
+    counts: DeliveryCounts,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        update newsletter_issues
+        set
+            delivered_count = delivered_count + $2,
+            failed_count = failed_count + $3,
+            status = case
+                when status = 'sending' and not exists (
+                    select 1 from issue_delivery_queue q
+                    where q.newsletter_issue_id = $1
+                ) then 'sent'
+                else status
+            end
+        where newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+        counts.delivered,
+        counts.failed
+    );
+
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to record the delivery progress of an issue.")?;
+
+    Ok(())
+}
+
+struct NewsletterIssue {
+    newsletter_issue_id: Uuid,
+    title: String,
+    text_content: String,
+    html_content: String,
+    tracking: bool,
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_issues(
+    pool: &PgPool,
+    issue_ids: &[Uuid],
+) -> Result<HashMap<Uuid, NewsletterIssue>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        NewsletterIssue,
+        r#"
+        select newsletter_issue_id, title, text_content, html_content, tracking
+        from newsletter_issues
+        where newsletter_issue_id = any($1)
+        "#,
+        issue_ids
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the newsletter issues.")?
+    .into_iter()
+    .map(|issue| (issue.newsletter_issue_id, issue))
+    .collect();
+
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::retry_delay;
+    use crate::config::RetrySettings;
+
+    fn retry_settings(jitter_milliseconds: u64) -> RetrySettings {
+        RetrySettings {
+            max_attempts: 5,
+            base_delay_milliseconds: 1000,
+            jitter_milliseconds,
+            max_delay_milliseconds: 60 * 60 * 1000,
+        }
+    }
+
+    #[test]
+    fn retry_delay_doubles_at_each_attempt() {
+        let settings = retry_settings(0);
+        assert_eq!(retry_delay(&settings, 1), Duration::from_secs(1));
+        assert_eq!(retry_delay(&settings, 2), Duration::from_secs(2));
+        assert_eq!(retry_delay(&settings, 3), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn retry_delay_jitter_is_bounded() {
+        let settings = retry_settings(500);
+        for _ in 0..100 {
+            let delay = retry_delay(&settings, 2);
+            assert!(delay >= Duration::from_millis(2000));
+            assert!(delay <= Duration::from_millis(2500));
+        }
+    }
+
+    #[test]
+    fn retry_delay_does_not_overflow() {
+        let settings = retry_settings(500);
+        let delay = retry_delay(&settings, u32::MAX);
+        assert!(delay >= Duration::from_secs(60 * 60));
+        assert!(delay <= Duration::from_millis(60 * 60 * 1000 + 500));
+        assert!(chrono::Duration::from_std(delay).is_ok());
+    }
+}
@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// How often the worker looks for scheduled issues that are due.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Starts the delivery of scheduled newsletter issues once their time has come.
+pub struct IssueSchedulingWorker {
+    pool: PgPool,
+}
+
+impl IssueSchedulingWorker {
+    pub fn with(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Publish due issues every `POLL_INTERVAL` until the process is stopped.
+    pub async fn run_until_stopped(self) -> Result<(), anyhow::Error> {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            // Errors are already recorded by `publish_due_issues`, the next tick tries again.
+            let _ = self.publish_due_issues().await;
+        }
+    }
+
+    /// Queue the delivery of every scheduled issue whose `scheduled_for` has passed, and
+    /// return how many were published.
+    ///
+    /// Due issues are locked with `FOR UPDATE SKIP LOCKED`, so an issue is never
+    /// published twice by concurrent workers, nor cancelled while it is being published.
+    #[tracing::instrument(skip_all, err)]
+    pub async fn publish_due_issues(&self) -> Result<usize, anyhow::Error> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to acquire a Postgres connection from the pool")?;
+
+        let issue_ids = sqlx::query_scalar!(
+            r#"
+            select newsletter_issue_id
+            from newsletter_issues
+            where status = 'scheduled' and scheduled_for <= now()
+            for update
+            skip locked
+            "#,
+        )
+        .fetch_all(&mut *transaction)
+        .await
+        .context("Failed to retrieve the due newsletter issues.")?;
+
+        for issue_id in &issue_ids {
+            tracing::info!(newsletter_issue_id = %issue_id, "Publishing a scheduled issue.");
+            start_delivery(&mut transaction, *issue_id).await?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction to publish scheduled issues.")?;
+
+        Ok(issue_ids.len())
+    }
+}
+
+/// Queue an issue for every subscriber confirmed on one of its lists, once, and mark it
+/// as published.
+///
+/// An issue without recipients is `sent` right away, otherwise it stays `sending`
+/// until the delivery worker drains its tasks.
+#[tracing::instrument(skip(transaction))]
+pub async fn start_delivery(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        insert into issue_delivery_queue (
+            newsletter_issue_id,
+            subscriber_email
+        )
+        select distinct $1::uuid, s.email
+        from subscriptions s
+        join list_memberships m on m.subscriber_id = s.id
+        join newsletter_issue_lists il on il.list_id = m.list_id
+        where il.newsletter_issue_id = $1 and m.status = 'confirmed'
+        "#,
+        newsletter_issue_id,
+    );
+    let recipient_count = transaction
+        .execute(query)
+        .await
+        .context("Failed to enqueue delivery tasks.")?
+        .rows_affected();
+    let recipient_count =
+        i32::try_from(recipient_count).context("The recipient count is out of range.")?;
+
+    let query = sqlx::query!(
+        r#"
+        update newsletter_issues
+        set
+            status = case when $2 = 0 then 'sent' else 'sending' end,
+            recipient_count = $2,
+            published_at = now()
+        where newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+        recipient_count,
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to mark the newsletter issue as published.")?;
+
+    Ok(())
+}
@@ -2,4 +2,7 @@ pub mod app;
 pub mod config;
 pub mod domain;
 pub mod email;
+pub mod issue_delivery_worker;
+pub mod issue_scheduling_worker;
+pub mod subscription_cleanup_worker;
 pub mod telemetry;
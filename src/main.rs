@@ -1,11 +1,17 @@
+use std::fmt::{Debug, Display};
 use std::io;
 
 use bb8_redis::bb8;
 use bb8_redis::RedisConnectionManager;
 use secrecy::ExposeSecret;
 use sqlx::postgres::PgPoolOptions;
+use tokio::task::JoinError;
 use tracing_subscriber::util::SubscriberInitExt;
-use zero2prod::{app::App, config::get_configuration, telemetry::get_subscriber};
+use zero2prod::{
+    app::App, config::get_configuration, issue_delivery_worker::IssueDeliveryWorker,
+    issue_scheduling_worker::IssueSchedulingWorker,
+    subscription_cleanup_worker::SubscriptionCleanupWorker, telemetry::get_subscriber,
+};
 
 #[tokio::main]
 async fn main() {
@@ -25,13 +31,50 @@ async fn main() {
         .await
         .expect("redis connection pool should be created");
 
+    let worker = IssueDeliveryWorker::with(db.clone(), &config)
+        .expect("the issue delivery worker should be available");
+    let worker = tokio::spawn(worker.run_until_stopped());
+    let cleanup_worker =
+        tokio::spawn(SubscriptionCleanupWorker::with(db.clone(), &config).run_until_stopped());
+    let scheduling_worker =
+        tokio::spawn(IssueSchedulingWorker::with(db.clone()).run_until_stopped());
+
     let app = App::with(config).await;
     tracing::info!(
         host = app.host().to_string(),
         port = app.port(),
         "starting server"
     );
-    app.serve(db, cache)
-        .await
-        .expect("the server should be running");
+    let server = tokio::spawn(app.serve(db, cache));
+
+    tokio::select! {
+        outcome = server => report_exit("API", outcome),
+        outcome = worker => report_exit("Background worker", outcome),
+        outcome = cleanup_worker => report_exit("Subscription cleanup worker", outcome),
+        outcome = scheduling_worker => report_exit("Issue scheduling worker", outcome),
+    };
+}
+
+fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
+    match outcome {
+        Ok(Ok(())) => {
+            tracing::info!("{} has exited", task_name)
+        }
+        Ok(Err(e)) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "{} failed",
+                task_name
+            )
+        }
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "{} task failed to complete",
+                task_name
+            )
+        }
+    }
 }
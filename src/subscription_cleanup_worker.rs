@@ -0,0 +1,92 @@
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::config::{Settings, SubscriptionSettings};
+
+/// What a single purge removed.
+#[derive(Debug)]
+pub struct PurgeOutcome {
+    pub tokens: u64,
+    pub subscribers: u64,
+}
+
+/// Periodically deletes expired confirmation tokens and subscriptions that were never
+/// confirmed within `unconfirmed_retention_days`.
+pub struct SubscriptionCleanupWorker {
+    pool: PgPool,
+    settings: SubscriptionSettings,
+}
+
+impl SubscriptionCleanupWorker {
+    pub fn with(pool: PgPool, config: &Settings) -> Self {
+        Self {
+            pool,
+            settings: config.subscriptions.clone(),
+        }
+    }
+
+    /// Purge expired subscriptions every `purge_interval_minutes` until the process is
+    /// stopped.
+    pub async fn run_until_stopped(self) -> Result<(), anyhow::Error> {
+        let mut interval = tokio::time::interval(self.settings.purge_interval());
+        loop {
+            interval.tick().await;
+            // Errors are already recorded by `purge`, the next tick tries again.
+            let _ = self.purge().await;
+        }
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    pub async fn purge(&self) -> Result<PurgeOutcome, anyhow::Error> {
+        let cutoff = chrono::Utc::now()
+            - chrono::Duration::from_std(self.settings.unconfirmed_retention())
+                .context("The unconfirmed subscription retention is out of range.")?;
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to acquire a Postgres connection from the pool")?;
+
+        let tokens = sqlx::query!(
+            r#"
+            delete from subscription_tokens
+            where
+                expires_at < now() or
+                subscriber_id in (
+                    select id from subscriptions
+                    where status = 'pending_confirmation' and subscribed_at < $1
+                )
+            "#,
+            cutoff
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to delete expired subscription tokens.")?
+        .rows_affected();
+
+        let subscribers = sqlx::query!(
+            r#"
+            delete from subscriptions
+            where status = 'pending_confirmation' and subscribed_at < $1
+            "#,
+            cutoff
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to delete unconfirmed subscribers.")?
+        .rows_affected();
+
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction to purge expired subscriptions.")?;
+
+        tracing::info!(tokens, subscribers, "Purged expired subscriptions");
+
+        Ok(PurgeOutcome {
+            tokens,
+            subscribers,
+        })
+    }
+}
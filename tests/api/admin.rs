@@ -0,0 +1,221 @@
+use wiremock::{
+    matchers::{any, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helper::{create_confirmed_subscriber, spawn_app};
+
+fn compose_form() -> serde_json::Value {
+    serde_json::json!({
+        "idempotency-key": uuid::Uuid::new_v4().to_string(),
+        "title": "Newsletter title",
+        "html-content": "<p>Newsletter body as HTML</p>",
+        "text-content": "Newsletter body as plain text",
+    })
+}
+
+#[tokio::test]
+async fn the_compose_page_requires_a_login() {
+    let app = spawn_app().await;
+
+    let response = app.get_compose_page().await;
+
+    assert_eq!(response.url().path(), "/login");
+}
+
+#[tokio::test]
+async fn the_compose_actions_require_a_login() {
+    let app = spawn_app().await;
+
+    for action in ["", "/preview", "/test"] {
+        let response = app.post_compose_form(action, compose_form()).await;
+
+        assert_eq!(response.status().as_u16(), 401, "action {:?}", action);
+    }
+}
+
+#[tokio::test]
+async fn the_preview_shows_both_renderings() {
+    let app = spawn_app().await;
+    app.login_admin().await;
+
+    let response = app.post_compose_form("/preview", compose_form()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("&lt;p&gt;Newsletter body as HTML&lt;/p&gt;"));
+    assert!(html.contains("Newsletter body as plain text"));
+}
+
+#[tokio::test]
+async fn the_preview_renders_markdown() {
+    let app = spawn_app().await;
+    app.login_admin().await;
+
+    let response = app
+        .post_compose_form(
+            "/preview",
+            serde_json::json!({
+                "idempotency-key": uuid::Uuid::new_v4().to_string(),
+                "title": "Newsletter title",
+                "markdown-content": "Newsletter **body**",
+            }),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("&lt;p&gt;Newsletter &lt;strong&gt;body&lt;/strong&gt;&lt;/p&gt;"));
+    assert!(html.contains("Newsletter body"));
+}
+
+#[tokio::test]
+async fn a_test_email_is_sent_to_the_admin_only() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_admin().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_compose_form("/test", compose_form()).await;
+
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("A test email was sent to admin@example.com."));
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().unwrap().body).unwrap();
+    assert_eq!(body["To"], "admin@example.com");
+    assert_eq!(body["Subject"], "[Test] Newsletter title");
+    let issues = sqlx::query!("select count(*) as \"count!\" from newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issues.count, 0);
+}
+
+#[tokio::test]
+async fn a_test_email_requires_an_admin_email() {
+    let app = spawn_app().await;
+    app.login_admin().await;
+    sqlx::query!("update users set email = null")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_compose_form("/test", compose_form()).await;
+
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("Set your email address on the dashboard"));
+}
+
+#[tokio::test]
+async fn the_admin_can_change_their_email() {
+    let app = spawn_app().await;
+    app.login_admin().await;
+
+    let response = app.post_change_email("ash@example.com").await;
+
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("Email successfully changed."));
+    let user = sqlx::query!(
+        "select email from users where user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(user.email.as_deref(), Some("ash@example.com"));
+}
+
+#[tokio::test]
+async fn publishing_queues_the_issue_and_flashes_a_message_once() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_admin().await;
+
+    let response = app.post_compose_form("", compose_form()).await;
+
+    assert_eq!(
+        response.headers().get("HX-Redirect").unwrap(),
+        "/app/newsletters"
+    );
+    let queued = sqlx::query!("select count(*) as \"count!\" from issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(queued.count, 1);
+
+    let html = app.get_compose_page().await.text().await.unwrap();
+    assert!(html.contains("The newsletter issue has been published."));
+    let html = app.get_compose_page().await.text().await.unwrap();
+    assert!(!html.contains("The newsletter issue has been published."));
+}
+
+#[tokio::test]
+async fn publishing_the_same_form_twice_creates_a_single_issue() {
+    let app = spawn_app().await;
+    app.login_admin().await;
+    let form = compose_form();
+
+    app.post_compose_form("", form.clone()).await;
+    app.post_compose_form("", form).await;
+
+    let issues = sqlx::query!("select count(*) as \"count!\" from newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issues.count, 1);
+}
+
+#[tokio::test]
+async fn publishing_an_invalid_issue_shows_the_rejected_fields() {
+    let app = spawn_app().await;
+    app.login_admin().await;
+    let mut form = compose_form();
+    form["title"] = "".into();
+    form["text-content"] = "".into();
+
+    let response = app.post_compose_form("", form).await;
+
+    let html = response.text().await.unwrap();
+    assert!(html.contains("Title: title is empty"));
+    assert!(html.contains("Plain-text body: content is empty"));
+    let issues = sqlx::query!("select count(*) as \"count!\" from newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issues.count, 0);
+}
+
+#[tokio::test]
+async fn the_issue_history_requires_a_login() {
+    let app = spawn_app().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/app/issues", &app.addr))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.url().path(), "/login");
+}
@@ -0,0 +1,51 @@
+use uuid::Uuid;
+
+use crate::helper::spawn_app;
+
+#[tokio::test]
+async fn published_issues_are_listed_in_the_public_archive() {
+    let app = spawn_app().await;
+
+    app.post_newsletters(serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    }))
+    .await;
+
+    let html = reqwest::get(format!("{}/archive", &app.addr))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("Newsletter title"));
+
+    let issue_id = sqlx::query!("select newsletter_issue_id from newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+    let response = reqwest::get(format!("{}/archive/{}", &app.addr, issue_id))
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("<p>Newsletter body as HTML</p>"));
+}
+
+#[tokio::test]
+async fn unknown_archived_issues_are_not_found() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(format!("{}/archive/{}", &app.addr, Uuid::new_v4()))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -0,0 +1,137 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helper::{create_confirmed_subscriber, postmark_batch_response, spawn_app, TestApp};
+
+/// Publish an issue and attempt to deliver it once, with the email API answering with
+/// `response`.
+async fn publish_issue(app: &TestApp, response: ResponseTemplate) {
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(response)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "content": {"text": "Newsletter body", "html": "<p>Newsletter body</p>"},
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 202);
+    app.dispatch_all_pending_emails().await;
+}
+
+async fn deliveries(app: &TestApp, query: &[(&str, &str)]) -> serde_json::Value {
+    app.get_deliveries(query)
+        .await
+        .error_for_status()
+        .expect("deliveries should be listed")
+        .json()
+        .await
+        .expect("the response body should be valid json")
+}
+
+#[tokio::test]
+async fn sent_issues_are_logged_with_the_provider_message_id() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+
+    publish_issue(&app, postmark_batch_response(1)).await;
+
+    let body = deliveries(&app, &[("email", "Bulbasaur@example.com")]).await;
+    assert_eq!(body["total"], 1);
+    let delivery = &body["deliveries"][0];
+    assert_eq!(delivery["status"], "sent");
+    assert_eq!(delivery["subscriber_id"], subscriber_id.to_string());
+    assert_eq!(delivery["title"], "Newsletter title");
+    assert_eq!(delivery["n_attempts"], 1);
+    assert!(delivery["provider_message_id"].is_string());
+    assert!(delivery["error"].is_null());
+}
+
+#[tokio::test]
+async fn transient_failures_are_logged_as_deferred() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    publish_issue(&app, ResponseTemplate::new(500)).await;
+
+    let body = deliveries(&app, &[]).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["deliveries"][0]["status"], "deferred");
+    assert!(body["deliveries"][0]["error"].is_string());
+    assert!(body["deliveries"][0]["provider_message_id"].is_null());
+}
+
+#[tokio::test]
+async fn permanent_failures_are_logged_as_failed() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    publish_issue(&app, ResponseTemplate::new(422)).await;
+
+    let body = deliveries(&app, &[("status", "failed")]).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(
+        body["deliveries"][0]["subscriber_email"],
+        "bulbasaur@example.com"
+    );
+    assert!(body["deliveries"][0]["error"].is_string());
+}
+
+#[tokio::test]
+async fn deliveries_can_be_filtered_by_issue() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    publish_issue(&app, postmark_batch_response(1)).await;
+    let issues: serde_json::Value = app.get_newsletter_issues().await.json().await.unwrap();
+    let newsletter_issue_id = issues["issues"][0]["newsletter_issue_id"].as_str().unwrap();
+
+    let body = deliveries(&app, &[("newsletter_issue_id", newsletter_issue_id)]).await;
+    assert_eq!(body["total"], 1);
+
+    let other_issue_id = uuid::Uuid::new_v4().to_string();
+    let body = deliveries(&app, &[("newsletter_issue_id", &other_issue_id)]).await;
+    assert_eq!(body["total"], 0);
+    assert_eq!(body["deliveries"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn unknown_statuses_are_rejected() {
+    let app = spawn_app().await;
+
+    let response = app.get_deliveries(&[("status", "delivered")]).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn deliveries_require_authentication() {
+    let app = spawn_app().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/api/v1/deliveries", &app.addr))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn deleted_subscribers_are_removed_from_the_log() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+    publish_issue(&app, postmark_batch_response(1)).await;
+
+    app.delete_subscriber(subscriber_id)
+        .await
+        .error_for_status()
+        .expect("the subscriber should be deleted");
+
+    let body = deliveries(&app, &[]).await;
+    assert_eq!(body["total"], 0);
+}
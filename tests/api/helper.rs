@@ -1,18 +1,29 @@
 use std::{env, io};
 
+use argon2::{password_hash::SaltString, Algorithm, Argon2, Params, PasswordHasher, Version};
 use bb8_redis::RedisConnectionManager;
 use once_cell::sync::Lazy;
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
 use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
 use reqwest_tracing::TracingMiddleware;
-use secrecy::ExposeSecret;
+use secrecy::{ExposeSecret, Secret};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use tracing_subscriber::util::SubscriberInitExt;
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
 use zero2prod::{
     app::App,
-    config::{get_configuration, DatabaseSettings},
+    config::{get_configuration, DatabaseSettings, EmailTransportKind, RetrySettings, Settings},
+    domain::subscriber::{
+        tracking_token::{TrackedEvent, TrackingToken},
+        unsubscribe_token::UnsubscribeToken,
+    },
+    issue_delivery_worker::{ExecutionOutcome, IssueDeliveryWorker},
+    issue_scheduling_worker::IssueSchedulingWorker,
+    subscription_cleanup_worker::SubscriptionCleanupWorker,
     telemetry::get_subscriber,
 };
 
@@ -31,18 +42,76 @@ pub struct ConfirmationLinks {
     pub html: reqwest::Url,
     pub plain_text: reqwest::Url,
 }
+
+/// Unsubscribe links embedded in a newsletter sent through the batch email API.
+pub struct UnsubscribeLinks {
+    /// The link in the footer, leading to the unsubscribe page.
+    pub page: reqwest::Url,
+    /// The one-click link advertised by the `List-Unsubscribe` header.
+    pub one_click: reqwest::Url,
+}
+
+/// A user stored in the test database, used to authenticate against protected endpoints.
+pub struct TestUser {
+    pub user_id: Uuid,
+    pub username: String,
+    pub password: String,
+    pub email: String,
+}
+
+impl TestUser {
+    pub fn generate() -> Self {
+        Self {
+            user_id: Uuid::new_v4(),
+            username: Uuid::new_v4().to_string(),
+            password: Uuid::new_v4().to_string(),
+            email: "admin@example.com".to_owned(),
+        }
+    }
+
+    async fn store(&self, pool: &PgPool) {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let password_hash = Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(15000, 2, 1, None).expect("the provided Argon2 params should be valid"),
+        )
+        .hash_password(self.password.as_bytes(), &salt)
+        .expect("the password should be hashed")
+        .to_string();
+
+        sqlx::query!(
+            "insert into users (user_id, username, password_hash, email) values ($1, $2, $3, $4)",
+            self.user_id,
+            self.username,
+            password_hash,
+            self.email,
+        )
+        .execute(pool)
+        .await
+        .expect("the test user should be stored");
+    }
+}
+
 pub struct TestApp {
     pub addr: String,
     pub db_pool: PgPool,
     pub http_client: ClientWithMiddleware,
     pub email_server: MockServer,
     pub port: u16,
+    pub test_user: TestUser,
+    pub worker: IssueDeliveryWorker,
+    pub cleanup_worker: SubscriptionCleanupWorker,
+    pub scheduling_worker: IssueSchedulingWorker,
+    pub retry_settings: RetrySettings,
+    pub hmac_key: Secret<String>,
+    pub webhook_secret: Secret<String>,
 }
 
 impl TestApp {
     pub async fn health_check(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/api/v1/health_check", &self.addr))
+            .get(format!("{}/api/v1/health_check", &self.addr))
             .send()
             .await
             .expect("the request should succeed")
@@ -57,15 +126,475 @@ impl TestApp {
             .expect("the request should succeed")
     }
 
+    /// Submit the subscription form of the home page.
+    pub async fn post_subscribe_form(&self, body: serde_json::Value) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/subscribe", &self.addr))
+            .json(&body)
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn post_resend_confirmation(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!(
+                "{}/api/v1/subscriptions/confirm/resend",
+                &self.addr
+            ))
+            .json(&serde_json::json!({ "email": email }))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn post_postmark_webhook(
+        &self,
+        body: serde_json::Value,
+        secret: &str,
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/api/v1/webhooks/postmark", &self.addr))
+            .basic_auth("postmark", Some(secret))
+            .json(&body)
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn post_data_request(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/api/v1/subscriptions/data/request", &self.addr))
+            .json(&serde_json::json!({ "email": email }))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
     pub async fn post_newsletters(&self, body: serde_json::Value) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/api/v1/newsletters", &self.addr))
+            .post(format!("{}/api/v1/newsletters", &self.addr))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .json(&body)
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn post_newsletters_with_idempotency_key(
+        &self,
+        body: serde_json::Value,
+        idempotency_key: &str,
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/api/v1/newsletters", &self.addr))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .header("Idempotency-Key", idempotency_key)
+            .json(&body)
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn post_unsubscribe(&self, subscriber_id: Uuid, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/api/v1/subscriptions/unsubscribe", &self.addr))
+            .query(&[
+                ("subscriber_id", subscriber_id.to_string().as_str()),
+                ("token", token),
+            ])
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body("List-Unsubscribe=One-Click")
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn get_unsubscribe_page(
+        &self,
+        subscriber_id: Uuid,
+        token: &str,
+    ) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/unsubscribe", &self.addr))
+            .query(&[
+                ("subscriber_id", subscriber_id.to_string().as_str()),
+                ("token", token),
+            ])
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn post_unsubscribe_page(
+        &self,
+        subscriber_id: Uuid,
+        token: &str,
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/unsubscribe", &self.addr))
+            .query(&[
+                ("subscriber_id", subscriber_id.to_string().as_str()),
+                ("token", token),
+            ])
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    /// Generate the unsubscribe token the application signs for a subscriber.
+    pub fn unsubscribe_token(&self, subscriber_id: Uuid) -> String {
+        UnsubscribeToken::generate(subscriber_id, &self.hmac_key).to_string()
+    }
+
+    /// Drain the issue delivery queue.
+    pub async fn dispatch_all_pending_emails(&self) {
+        loop {
+            if let ExecutionOutcome::EmptyQueue = self
+                .worker
+                .try_execute_task()
+                .await
+                .expect("the delivery task should be executed")
+            {
+                break;
+            }
+        }
+    }
+
+    pub async fn get_newsletter_issues(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/api/v1/newsletters", &self.addr))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn get_newsletter_issue(&self, newsletter_issue_id: Uuid) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/api/v1/newsletters/{}",
+                &self.addr, newsletter_issue_id
+            ))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn get_newsletter_issue_links(&self, newsletter_issue_id: Uuid) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/api/v1/newsletters/{}/links",
+                &self.addr, newsletter_issue_id
+            ))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn get_tracking_pixel(
+        &self,
+        newsletter_issue_id: Uuid,
+        subscriber_id: Uuid,
+    ) -> reqwest::Response {
+        let token = TrackingToken::generate(
+            newsletter_issue_id,
+            subscriber_id,
+            TrackedEvent::Open,
+            &self.hmac_key,
+        );
+        self.http_client
+            .get(format!("{}/api/v1/tracking/open", &self.addr))
+            .query(&[
+                ("issue_id", newsletter_issue_id.to_string().as_str()),
+                ("subscriber_id", subscriber_id.to_string().as_str()),
+                ("token", token.to_string().as_str()),
+            ])
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    /// Follow a tracked link without following the redirection.
+    pub async fn get_tracked_link(
+        &self,
+        newsletter_issue_id: Uuid,
+        subscriber_id: Uuid,
+        url: &str,
+        token: &str,
+    ) -> reqwest::Response {
+        reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("the http client should be built")
+            .get(format!("{}/api/v1/tracking/click", &self.addr))
+            .query(&[
+                ("issue_id", newsletter_issue_id.to_string().as_str()),
+                ("subscriber_id", subscriber_id.to_string().as_str()),
+                ("url", url),
+                ("token", token),
+            ])
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    /// Generate the token the application signs for a tracked link.
+    pub fn click_token(&self, newsletter_issue_id: Uuid, subscriber_id: Uuid, url: &str) -> String {
+        TrackingToken::generate(
+            newsletter_issue_id,
+            subscriber_id,
+            TrackedEvent::Click { url },
+            &self.hmac_key,
+        )
+        .to_string()
+    }
+
+    pub async fn post_tracking_opt_out(
+        &self,
+        subscriber_id: Uuid,
+        token: &str,
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/tracking", &self.addr))
+            .query(&[
+                ("subscriber_id", subscriber_id.to_string().as_str()),
+                ("token", token),
+            ])
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn get_dead_letters(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/api/v1/newsletters/dead_letters", &self.addr))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn post_redrive_dead_letters(&self, body: serde_json::Value) -> reqwest::Response {
+        self.http_client
+            .post(format!(
+                "{}/api/v1/newsletters/dead_letters/redrive",
+                &self.addr
+            ))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .json(&body)
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn post_lists(&self, body: serde_json::Value) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/api/v1/lists", &self.addr))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .json(&body)
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn get_lists(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/api/v1/lists", &self.addr))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    /// List subscribers, with the filters and pagination given as query parameters.
+    pub async fn get_subscribers(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/api/v1/subscribers", &self.addr))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .query(query)
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn get_deliveries(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/api/v1/deliveries", &self.addr))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .query(query)
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn get_subscriber(&self, subscriber_id: Uuid) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/api/v1/subscribers/{}",
+                &self.addr, subscriber_id
+            ))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn patch_subscriber(
+        &self,
+        subscriber_id: Uuid,
+        body: serde_json::Value,
+    ) -> reqwest::Response {
+        self.http_client
+            .patch(format!(
+                "{}/api/v1/subscribers/{}",
+                &self.addr, subscriber_id
+            ))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .json(&body)
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    /// Call one of the actions on a subscriber, `confirm` or `unsubscribe`.
+    pub async fn post_subscriber_action(
+        &self,
+        subscriber_id: Uuid,
+        action: &str,
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!(
+                "{}/api/v1/subscribers/{}/{}",
+                &self.addr, subscriber_id, action
+            ))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn delete_subscriber(&self, subscriber_id: Uuid) -> reqwest::Response {
+        self.http_client
+            .delete(format!(
+                "{}/api/v1/subscribers/{}",
+                &self.addr, subscriber_id
+            ))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    /// Import subscribers from a CSV file, with the options given as query parameters.
+    pub async fn post_subscribers_import(
+        &self,
+        csv: &str,
+        query: &[(&str, &str)],
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/api/v1/subscribers/import", &self.addr))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .header("Content-Type", "text/csv")
+            .query(query)
+            .body(csv.to_owned())
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn get_subscribers_export(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/api/v1/subscribers/export", &self.addr))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    /// Log in through the login form, keeping the session cookie for later requests.
+    pub async fn login_admin(&self) {
+        self.http_client
+            .post(format!("{}/login", &self.addr))
+            .json(&serde_json::json!({
+                "username": &self.test_user.username,
+                "password": &self.test_user.password,
+            }))
+            .send()
+            .await
+            .expect("the request should succeed")
+            .error_for_status()
+            .expect("the test user should be able to log in");
+    }
+
+    pub async fn get_compose_page(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/app/newsletters", &self.addr))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    /// Submit the compose form to one of `""`, `"/preview"` or `"/test"`.
+    pub async fn post_compose_form(
+        &self,
+        action: &str,
+        body: serde_json::Value,
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/app/newsletters{}", &self.addr, action))
             .json(&body)
             .send()
             .await
             .expect("the request should succeed")
     }
 
+    pub async fn post_cancel_issue(&self, newsletter_issue_id: Uuid) -> reqwest::Response {
+        self.http_client
+            .post(format!(
+                "{}/app/issues/{}/cancel",
+                &self.addr, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    pub async fn post_change_email(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/change-email", &self.addr))
+            .json(&serde_json::json!({ "email": email }))
+            .send()
+            .await
+            .expect("the request should succeed")
+    }
+
+    /// Log in as the test user through the API and return the issued JWT.
+    pub async fn login_user(&self) -> String {
+        let body: serde_json::Value = self
+            .http_client
+            .post(format!("{}/api/v1/users/login", &self.addr))
+            .json(&serde_json::json!({
+                "username": &self.test_user.username,
+                "password": &self.test_user.password,
+            }))
+            .send()
+            .await
+            .expect("the request should succeed")
+            .error_for_status()
+            .expect("the test user should be able to log in")
+            .json()
+            .await
+            .expect("the response body should be valid json");
+
+        body["token"]
+            .as_str()
+            .expect("the token should be a string")
+            .to_owned()
+    }
+
     /// Extract the confirmation links embedded in the request to the email API.
     pub fn get_confirmation_links(&self, email_request: &wiremock::Request) -> ConfirmationLinks {
         let body: serde_json::Value =
@@ -107,33 +636,164 @@ impl TestApp {
         );
         ConfirmationLinks { html, plain_text }
     }
+
+    /// Extract the unsubscribe links of the first message of a newsletter batch.
+    pub fn get_unsubscribe_links(&self, batch_request: &wiremock::Request) -> UnsubscribeLinks {
+        let body: serde_json::Value =
+            serde_json::from_slice(&batch_request.body).expect("body should be a valid json");
+        let message = &body[0];
+
+        let with_app_port = |raw_link: &str| {
+            let mut link =
+                reqwest::Url::parse(raw_link).expect("unsubscribe links should be valid urls");
+            assert_eq!(
+                link.host_str().expect("host string should be available"),
+                "127.0.0.1"
+            );
+            link.set_port(Some(self.port))
+                .expect("link's port should be updated");
+            link
+        };
+
+        let footer = message["TextBody"]
+            .as_str()
+            .expect("text body should be a string")
+            .rsplit("Unsubscribe: ")
+            .next()
+            .expect("the text body should end with an unsubscribe link");
+        let header = message["Headers"]
+            .as_array()
+            .expect("headers should be an array")
+            .iter()
+            .find(|header| header["Name"] == "List-Unsubscribe")
+            .expect("the List-Unsubscribe header should be present")["Value"]
+            .as_str()
+            .expect("the header value should be a string");
+
+        UnsubscribeLinks {
+            page: with_app_port(footer),
+            one_click: with_app_port(header.trim_start_matches('<').trim_end_matches('>')),
+        }
+    }
+}
+
+/// Use the public API of the application under test to create
+/// an unconfirmed subscriber.
+pub async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
+    let body = serde_json::json!({"name": "bulbasaur", "email": "bulbasaur@example.com"});
+
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .named("Create unconfirmed subscriber")
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .expect("the request should succeed");
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("the vector of requests should exist")
+        .pop()
+        .expect("there should be at least a request");
+
+    app.get_confirmation_links(&email_request)
+}
+
+/// Create a confirmed subscriber and return their id.
+pub async fn create_confirmed_subscriber(app: &TestApp) -> Uuid {
+    // We can then reuse the same helper and just add
+    // an extra step to actually call the confirmation link!
+    let confirmation_link = create_unconfirmed_subscriber(app).await;
+    reqwest::get(confirmation_link.html)
+        .await
+        .expect("the request should succeed")
+        .error_for_status()
+        .expect("the status should be success");
+
+    sqlx::query!("select id from subscriptions where email = 'bulbasaur@example.com'")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("the subscriber should be stored")
+        .id
+}
+
+/// A Postmark `/email/batch` response accepting every message of the batch.
+pub fn postmark_batch_response(n_messages: usize) -> ResponseTemplate {
+    let results = (0..n_messages)
+        .map(|_| {
+            serde_json::json!({
+                "ErrorCode": 0,
+                "Message": "OK",
+                "MessageID": Uuid::new_v4(),
+            })
+        })
+        .collect::<Vec<_>>();
+    ResponseTemplate::new(200).set_body_json(results)
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawn the application after adjusting its configuration.
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     Lazy::force(&TRACING);
 
     let email_server = MockServer::start().await;
     let mut config = get_configuration().expect("the configuration should be available");
     config.application.port = 0;
     config.database.database_name = Uuid::new_v4().to_string();
+    config.email_client.transport = EmailTransportKind::Postmark;
     config.email_client.base_url = email_server.uri();
+    // Every test application shares the same Redis, but not the same rate limit counters.
+    config.rate_limits.namespace = Uuid::new_v4().to_string();
+    configure(&mut config);
 
     let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
-    let http_client = ClientBuilder::new(reqwest::Client::new())
-        .with(TracingMiddleware::default())
-        .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-        .build();
+    let http_client = ClientBuilder::new(
+        reqwest::Client::builder()
+            .cookie_store(true)
+            .build()
+            .expect("the http client should be built"),
+    )
+    .with(TracingMiddleware::default())
+    .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+    .build();
 
     let db = configure_database(&config.database).await;
-    let cache = configure_cache(&config.redis_uri.expose_secret()).await;
+    let cache = configure_cache(config.redis_uri.expose_secret()).await;
+    let worker = IssueDeliveryWorker::with(db.clone(), &config)
+        .expect("the issue delivery worker should be available");
+    let cleanup_worker = SubscriptionCleanupWorker::with(db.clone(), &config);
+    let scheduling_worker = IssueSchedulingWorker::with(db.clone());
+    let retry_settings = config.email_client.retry.clone();
+    let hmac_key = config.application.hmac_key.clone();
+    let webhook_secret = config.email_client.webhook_secret.clone();
     let app = App::with(config).await;
 
+    let test_user = TestUser::generate();
+    test_user.store(&db).await;
+
     let test_app = TestApp {
         addr: format!("http://127.0.0.1:{}", app.port()),
         db_pool: db.clone(),
         http_client,
         email_server,
         port: app.port(),
+        test_user,
+        worker,
+        cleanup_worker,
+        scheduling_worker,
+        retry_settings,
+        hmac_key,
+        webhook_secret,
     };
 
     tokio::spawn(async move {
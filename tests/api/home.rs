@@ -0,0 +1,70 @@
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helper::spawn_app;
+
+#[tokio::test]
+async fn the_home_page_shows_a_subscription_form() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(&app.addr).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains(r#"hx-post="/subscribe""#));
+    assert!(html.contains(r#"hx-ext="submitjson""#));
+}
+
+#[tokio::test]
+async fn subscribing_from_the_home_page_asks_to_check_the_inbox() {
+    let app = spawn_app().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscribe_form(serde_json::json!({
+            "name": "bulbasaur",
+            "email": "bulbasaur@example.com",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Check your inbox"));
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn the_subscription_form_shows_inline_validation_errors() {
+    let app = spawn_app().await;
+
+    Mock::given(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscribe_form(serde_json::json!({
+            "name": "",
+            "email": "definitely-not-an-email",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("Invalid name: name is empty."));
+    assert!(html.contains("Invalid email: invalid email."));
+    // The submitted values are kept in the form.
+    assert!(html.contains(r#"value="definitely-not-an-email""#));
+}
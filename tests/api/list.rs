@@ -0,0 +1,208 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helper::{create_confirmed_subscriber, postmark_batch_response, spawn_app, TestApp};
+
+async fn create_weekly_list(app: &TestApp) {
+    let response = app
+        .post_lists(serde_json::json!({"slug": "weekly", "name": "Weekly digest"}))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+/// Subscribe bulbasaur to a list and click the confirmation link sent for it.
+async fn join_list(app: &TestApp, list: &str) {
+    let mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    app.post_subscriptions(serde_json::json!({
+        "name": "bulbasaur",
+        "email": "bulbasaur@example.com",
+        "list": list,
+    }))
+    .await
+    .error_for_status()
+    .unwrap();
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    // Confirming sends a welcome email, which is not the one expected above.
+    drop(mock_guard);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn lists_can_be_created_and_listed() {
+    let app = spawn_app().await;
+
+    create_weekly_list(&app).await;
+
+    let body: serde_json::Value = app.get_lists().await.json().await.unwrap();
+    let slugs: Vec<&str> = body["lists"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|list| list["slug"].as_str().unwrap())
+        .collect();
+    assert_eq!(slugs, ["newsletter", "weekly"]);
+}
+
+#[tokio::test]
+async fn list_slugs_must_be_unique() {
+    let app = spawn_app().await;
+    create_weekly_list(&app).await;
+
+    let response = app
+        .post_lists(serde_json::json!({"slug": "weekly", "name": "Another digest"}))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["details"][0]["field"], "slug");
+    assert_eq!(error["details"][0]["code"], "taken");
+}
+
+#[tokio::test]
+async fn list_slugs_are_validated() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_lists(serde_json::json!({"slug": "Weekly Digest", "name": "Weekly digest"}))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["details"][0]["code"], "invalid_characters");
+}
+
+#[tokio::test]
+async fn subscribing_to_an_unknown_list_is_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_subscriptions(serde_json::json!({
+            "name": "bulbasaur",
+            "email": "bulbasaur@example.com",
+            "list": "unknown",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["details"][0]["field"], "list");
+    assert_eq!(error["details"][0]["code"], "unknown_list");
+}
+
+#[tokio::test]
+async fn subscriptions_are_confirmed_per_list() {
+    let app = spawn_app().await;
+    create_weekly_list(&app).await;
+    create_confirmed_subscriber(&app).await;
+
+    join_list(&app, "weekly").await;
+
+    let statuses = sqlx::query!(
+        r#"
+        select l.slug, m.status
+        from list_memberships m
+        join lists l on l.list_id = m.list_id
+        order by l.slug
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    let statuses: Vec<(&str, &str)> = statuses
+        .iter()
+        .map(|row| (row.slug.as_str(), row.status.as_str()))
+        .collect();
+    assert_eq!(
+        statuses,
+        [("newsletter", "confirmed"), ("weekly", "confirmed")]
+    );
+}
+
+#[tokio::test]
+async fn issues_are_only_sent_to_the_subscribers_of_their_lists() {
+    let app = spawn_app().await;
+    create_weekly_list(&app).await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(postmark_batch_response(1))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_newsletters(serde_json::json!({
+            "title": "Weekly digest",
+            "content": {"text": "Digest", "html": "<p>Digest</p>"},
+            "lists": ["weekly"],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 202);
+    app.dispatch_all_pending_emails().await;
+
+    let body: serde_json::Value = app.get_newsletter_issues().await.json().await.unwrap();
+    assert_eq!(body["issues"][0]["lists"], serde_json::json!(["weekly"]));
+    assert_eq!(body["issues"][0]["recipient_count"], 0);
+}
+
+#[tokio::test]
+async fn subscribers_of_several_lists_receive_an_issue_once() {
+    let app = spawn_app().await;
+    create_weekly_list(&app).await;
+    create_confirmed_subscriber(&app).await;
+    join_list(&app, "weekly").await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(postmark_batch_response(1))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(serde_json::json!({
+        "title": "Newsletter title",
+        "content": {"text": "Newsletter body", "html": "<p>Newsletter body</p>"},
+        "lists": ["newsletter", "weekly"],
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    let body: serde_json::Value = app.get_newsletter_issues().await.json().await.unwrap();
+    assert_eq!(body["issues"][0]["recipient_count"], 1);
+}
+
+#[tokio::test]
+async fn issues_can_not_target_unknown_lists() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "content": {"text": "Newsletter body", "html": "<p>Newsletter body</p>"},
+            "lists": ["unknown"],
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["details"][0]["field"], "lists");
+}
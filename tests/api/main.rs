@@ -1,4 +1,14 @@
+mod admin;
+mod archive;
+mod delivery;
 mod health;
 mod helper;
+mod home;
+mod list;
 mod newsletter;
+mod personal_data;
+mod rate_limit;
+mod subscriber;
 mod subscription;
+mod tracking;
+mod webhook;
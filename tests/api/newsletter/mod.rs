@@ -1,7 +1,16 @@
-use crate::helper::{spawn_app, ConfirmationLinks, TestApp};
+mod scheduling;
+
+use std::time::Duration;
+
+use uuid::Uuid;
 use wiremock::matchers::{any, method, path};
 use wiremock::{Mock, ResponseTemplate};
 
+use crate::helper::{
+    create_confirmed_subscriber, create_unconfirmed_subscriber, postmark_batch_response, spawn_app,
+    TestApp,
+};
+
 #[tokio::test]
 async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
     let app = spawn_app().await;
@@ -25,7 +34,8 @@ async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
     });
     let response = app.post_newsletters(newsletter_request_body).await;
 
-    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.status().as_u16(), 202);
+    app.dispatch_all_pending_emails().await;
 }
 
 #[tokio::test]
@@ -33,9 +43,9 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     let app = spawn_app().await;
     create_confirmed_subscriber(&app).await;
 
-    Mock::given(path("/email"))
+    Mock::given(path("/email/batch"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(postmark_batch_response(1))
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -49,7 +59,81 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     });
     let response = app.post_newsletters(newsletter_request_body).await;
 
+    assert_eq!(response.status().as_u16(), 202);
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_unsubscribed_subscribers() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_newsletters(newsletter_request_body()).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    // The subscriber leaves after the issue was queued, but before it is delivered.
+    app.post_unsubscribe(subscriber_id, &app.unsubscribe_token(subscriber_id))
+        .await
+        .error_for_status()
+        .expect("the subscriber should be unsubscribed");
+    app.dispatch_all_pending_emails().await;
+
+    let queued = sqlx::query!("SELECT subscriber_email FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("the delivery queue should be readable");
+    assert!(queued.is_empty());
+}
+
+#[tokio::test]
+async fn newsletters_carry_unsubscribe_links() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(postmark_batch_response(1))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(newsletter_request_body()).await;
+    app.dispatch_all_pending_emails().await;
+
+    let batch_request = find_batch_request(&app).await;
+    let body: serde_json::Value = serde_json::from_slice(&batch_request.body).unwrap();
+    let headers = body[0]["Headers"].as_array().unwrap();
+    assert!(headers
+        .iter()
+        .any(|header| header["Name"] == "List-Unsubscribe-Post"
+            && header["Value"] == "List-Unsubscribe=One-Click"));
+    assert!(body[0]["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains("/unsubscribe?subscriber_id="));
+
+    let links = app.get_unsubscribe_links(&batch_request);
+    assert_eq!(links.page.path(), "/unsubscribe");
+    let response = app
+        .http_client
+        .post(links.one_click)
+        .body("List-Unsubscribe=One-Click")
+        .send()
+        .await
+        .expect("the request should succeed");
     assert_eq!(response.status().as_u16(), 200);
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("the subscriber should be stored");
+    assert_eq!(saved.status, "unsubscribed");
 }
 
 #[tokio::test]
@@ -83,42 +167,662 @@ async fn newsletters_returns_422_for_invalid_data() {
     }
 }
 
-/// Use the public API of the application under test to create
-/// an unconfirmed subscriber.
-async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
-    let body = serde_json::json!({"name": "bulbasaur", "email": "bulbasaur@example.com"});
+#[tokio::test]
+async fn newsletters_report_every_invalid_field() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_newsletters(serde_json::json!({
+            "title": " ",
+            "content": {
+                "text": "",
+                "html": "<p>Newsletter body as HTML</p>",
+            }
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], 1000);
+    let rejected: Vec<(&str, &str)> = error["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| (d["field"].as_str().unwrap(), d["code"].as_str().unwrap()))
+        .collect();
+    assert_eq!(rejected, [("title", "empty"), ("content.text", "empty")]);
+}
+
+#[tokio::test]
+async fn requests_missing_authorization_are_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/api/v1/newsletters", &app.addr))
+        .json(&serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+            }
+        }))
+        .send()
+        .await
+        .expect("the request should succeed");
+
+    assert_eq!(401, response.status().as_u16());
+    assert!(response.headers().contains_key("WWW-Authenticate"));
+}
+
+#[tokio::test]
+async fn non_existing_user_is_rejected() {
+    let app = spawn_app().await;
+    let username = Uuid::new_v4().to_string();
+    let password = Uuid::new_v4().to_string();
+
+    let response = app
+        .http_client
+        .post(format!("{}/api/v1/newsletters", &app.addr))
+        .basic_auth(username, Some(password))
+        .json(&serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+            }
+        }))
+        .send()
+        .await
+        .expect("the request should succeed");
 
-    let _mock_guard = Mock::given(path("/email"))
+    assert_eq!(401, response.status().as_u16());
+    assert!(response.headers().contains_key("WWW-Authenticate"));
+}
+
+#[tokio::test]
+async fn invalid_password_is_rejected() {
+    let app = spawn_app().await;
+    let username = &app.test_user.username;
+    let password = Uuid::new_v4().to_string();
+    assert_ne!(app.test_user.password, password);
+
+    let response = app
+        .http_client
+        .post(format!("{}/api/v1/newsletters", &app.addr))
+        .basic_auth(username, Some(password))
+        .json(&serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+            }
+        }))
+        .send()
+        .await
+        .expect("the request should succeed");
+
+    assert_eq!(401, response.status().as_u16());
+    assert!(response.headers().contains_key("WWW-Authenticate"));
+}
+
+#[tokio::test]
+async fn invalid_bearer_token_is_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/api/v1/newsletters", &app.addr))
+        .bearer_auth("not-a-valid-token")
+        .json(&serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+            }
+        }))
+        .send()
+        .await
+        .expect("the request should succeed");
+
+    assert_eq!(401, response.status().as_u16());
+    assert!(response.headers().contains_key("WWW-Authenticate"));
+}
+
+#[tokio::test]
+async fn a_valid_bearer_token_is_accepted() {
+    let app = spawn_app().await;
+    let token = app.login_user().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/api/v1/newsletters", &app.addr))
+        .bearer_auth(token)
+        .json(&serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+            }
+        }))
+        .send()
+        .await
+        .expect("the request should succeed");
+
+    assert_eq!(202, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn newsletter_creation_is_idempotent() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
-        .named("Create unconfirmed subscriber")
+        .respond_with(postmark_batch_response(1))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    });
+    let idempotency_key = Uuid::new_v4().to_string();
+
+    let response = app
+        .post_newsletters_with_idempotency_key(newsletter_request_body.clone(), &idempotency_key)
+        .await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    // Submit the same newsletter again.
+    let response = app
+        .post_newsletters_with_idempotency_key(newsletter_request_body, &idempotency_key)
+        .await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn concurrent_newsletter_submissions_are_handled_gracefully() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        // Setting a long delay to ensure that the second request
+        // arrives before the first one completes.
+        .respond_with(postmark_batch_response(1).set_delay(Duration::from_secs(2)))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    });
+    let idempotency_key = Uuid::new_v4().to_string();
+
+    let response1 = app
+        .post_newsletters_with_idempotency_key(newsletter_request_body.clone(), &idempotency_key);
+    let response2 =
+        app.post_newsletters_with_idempotency_key(newsletter_request_body, &idempotency_key);
+    let (response1, response2) = tokio::join!(response1, response2);
+
+    assert_eq!(response1.status(), response2.status());
+    assert_eq!(
+        response1.text().await.unwrap(),
+        response2.text().await.unwrap()
+    );
+
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn newsletters_reject_an_invalid_idempotency_key() {
+    let app = spawn_app().await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    });
+    let response = app
+        .post_newsletters_with_idempotency_key(newsletter_request_body, &"a".repeat(50))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["details"][0]["field"], "Idempotency-Key");
+    assert_eq!(error["details"][0]["code"], "too_long");
+}
+
+#[tokio::test]
+async fn publishing_returns_before_the_issue_is_delivered() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(postmark_batch_response(1))
+        .expect(0)
+        .named("Delivery before dispatch")
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    });
+    let response = app.post_newsletters(newsletter_request_body).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    let queued = sqlx::query!("SELECT subscriber_email FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("the delivery queue should be readable");
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].subscriber_email, "bulbasaur@example.com");
+}
+
+#[tokio::test]
+async fn transient_delivery_failures_are_retried_later() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
         .expect(1)
-        .mount_as_scoped(&app.email_server)
+        .mount(&app.email_server)
         .await;
 
-    app.post_subscriptions(body)
+    let response = app.post_newsletters(newsletter_request_body()).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    app.dispatch_all_pending_emails().await;
+
+    let queued = sqlx::query!(
+        "SELECT n_attempts, last_error, execute_after > now() AS \"postponed!\" FROM issue_delivery_queue"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("the delivery task should still be queued");
+    assert_eq!(queued.n_attempts, 1);
+    assert!(queued.last_error.is_some());
+    assert!(queued.postponed);
+}
+
+#[tokio::test]
+async fn permanent_delivery_failures_are_dead_lettered() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(422))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_newsletters(newsletter_request_body()).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    app.dispatch_all_pending_emails().await;
+
+    let queued = sqlx::query!("SELECT subscriber_email FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("the delivery queue should be readable");
+    assert!(queued.is_empty());
+
+    let body: serde_json::Value = app
+        .get_dead_letters()
         .await
         .error_for_status()
+        .expect("dead letters should be listed")
+        .json()
+        .await
+        .expect("the response body should be valid json");
+    let dead_letters = body["dead_letters"]
+        .as_array()
+        .expect("dead letters should be an array");
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0]["subscriber_email"], "bulbasaur@example.com");
+    assert_eq!(dead_letters[0]["n_attempts"], 1);
+}
+
+#[tokio::test]
+async fn messages_rejected_within_a_batch_are_dead_lettered() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                "ErrorCode": 406,
+                "Message": "You tried to send to a recipient that has been marked as inactive.",
+            }])),
+        )
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_newsletters(newsletter_request_body()).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    app.dispatch_all_pending_emails().await;
+
+    let dead_letter =
+        sqlx::query!("SELECT subscriber_email, last_error FROM issue_delivery_dead_letters")
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("the delivery task should be dead-lettered");
+    assert_eq!(dead_letter.subscriber_email, "bulbasaur@example.com");
+    assert!(dead_letter.last_error.contains("406"));
+}
+
+#[tokio::test]
+async fn deliveries_are_dead_lettered_after_the_last_attempt() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_newsletters(newsletter_request_body()).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    // Pretend every attempt but the last one already failed.
+    sqlx::query!(
+        "UPDATE issue_delivery_queue SET n_attempts = $1",
+        app.retry_settings.max_attempts as i32 - 1
+    )
+    .execute(&app.db_pool)
+    .await
+    .expect("the delivery task should be updated");
+
+    app.dispatch_all_pending_emails().await;
+
+    let dead_letter = sqlx::query!("SELECT n_attempts FROM issue_delivery_dead_letters")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("the delivery task should be dead-lettered");
+    assert_eq!(
+        dead_letter.n_attempts,
+        app.retry_settings.max_attempts as i32
+    );
+}
+
+#[tokio::test]
+async fn dead_letters_can_be_redriven() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    let response = {
+        let _mock_guard = Mock::given(path("/email/batch"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount_as_scoped(&app.email_server)
+            .await;
+
+        let response = app.post_newsletters(newsletter_request_body()).await;
+        app.dispatch_all_pending_emails().await;
+        response
+    };
+    assert_eq!(response.status().as_u16(), 202);
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(postmark_batch_response(1))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body: serde_json::Value = app
+        .post_redrive_dead_letters(serde_json::json!({}))
+        .await
+        .error_for_status()
+        .expect("dead letters should be redriven")
+        .json()
+        .await
+        .expect("the response body should be valid json");
+    assert_eq!(body["redriven"], 1);
+
+    app.dispatch_all_pending_emails().await;
+
+    let dead_letters = sqlx::query!("SELECT subscriber_email FROM issue_delivery_dead_letters")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("the dead letters should be readable");
+    assert!(dead_letters.is_empty());
+}
+
+#[tokio::test]
+async fn dead_letters_require_authentication() {
+    let app = spawn_app().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/api/v1/newsletters/dead_letters", &app.addr))
+        .send()
+        .await
         .expect("the request should succeed");
 
-    let email_request = app
-        .email_server
+    assert_eq!(401, response.status().as_u16());
+}
+
+/// The first batch sent to Postmark, ignoring the emails sent while subscribing.
+async fn find_batch_request(app: &TestApp) -> wiremock::Request {
+    app.email_server
         .received_requests()
         .await
-        .expect("the vector of requests should exist")
-        .pop()
-        .expect("there should be at least a request");
+        .unwrap()
+        .into_iter()
+        .find(|request| request.url.path() == "/email/batch")
+        .expect("a batch should have been sent")
+}
 
-    app.get_confirmation_links(&email_request)
+fn newsletter_request_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    })
 }
 
-async fn create_confirmed_subscriber(app: &TestApp) {
-    // We can then reuse the same helper and just add
-    // an extra step to actually call the confirmation link!
-    let confirmation_link = create_unconfirmed_subscriber(app).await;
-    reqwest::get(confirmation_link.html)
-        .await
-        .expect("the request should succeed")
-        .error_for_status()
-        .expect("the status should be success");
+#[tokio::test]
+async fn published_issues_are_recorded_with_their_delivery_counts() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(postmark_batch_response(1))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(newsletter_request_body()).await;
+    app.dispatch_all_pending_emails().await;
+
+    let body: serde_json::Value = app.get_newsletter_issues().await.json().await.unwrap();
+    let issues = body["issues"].as_array().unwrap();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0]["title"], "Newsletter title");
+    assert_eq!(issues[0]["author"], app.test_user.username.as_str());
+    assert_eq!(issues[0]["recipient_count"], 1);
+    assert_eq!(issues[0]["delivered_count"], 1);
+    assert_eq!(issues[0]["failed_count"], 0);
+
+    let id: Uuid = issues[0]["newsletter_issue_id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let issue: serde_json::Value = app.get_newsletter_issue(id).await.json().await.unwrap();
+    assert_eq!(issue["html_content"], "<p>Newsletter body as HTML</p>");
+    assert_eq!(issue["text_content"], "Newsletter body as plain text");
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_counted_until_redriven() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(422))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(newsletter_request_body()).await;
+    app.dispatch_all_pending_emails().await;
+
+    let body: serde_json::Value = app.get_newsletter_issues().await.json().await.unwrap();
+    assert_eq!(body["issues"][0]["failed_count"], 1);
+
+    app.post_redrive_dead_letters(serde_json::json!({})).await;
+
+    let body: serde_json::Value = app.get_newsletter_issues().await.json().await.unwrap();
+    assert_eq!(body["issues"][0]["failed_count"], 0);
+}
+
+#[tokio::test]
+async fn unknown_issues_are_not_found() {
+    let app = spawn_app().await;
+
+    let response = app.get_newsletter_issue(Uuid::new_v4()).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn markdown_issues_are_rendered_to_html_and_text() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "markdown": "Read the [docs](https://example.com).\n\n<script>alert(1)</script>",
+            }
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    let body: serde_json::Value = app.get_newsletter_issues().await.json().await.unwrap();
+    let id: Uuid = body["issues"][0]["newsletter_issue_id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let issue: serde_json::Value = app.get_newsletter_issue(id).await.json().await.unwrap();
+    let html = issue["html_content"].as_str().unwrap();
+    assert!(html.contains(r#"<a href="https://example.com""#));
+    assert!(!html.contains("script"));
+    assert_eq!(
+        issue["text_content"],
+        "Read the docs (https://example.com)."
+    );
+    assert_eq!(
+        issue["markdown_content"],
+        "Read the [docs](https://example.com).\n\n<script>alert(1)</script>"
+    );
+}
+
+#[tokio::test]
+async fn markdown_can_not_be_combined_with_html_or_text() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "markdown": "Newsletter body",
+                "html": "<p>Newsletter body as HTML</p>",
+            }
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["details"][0]["field"], "content");
+    assert_eq!(error["details"][0]["code"], "conflicting");
+}
+
+#[tokio::test]
+async fn newsletters_are_personalised_for_each_subscriber() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(postmark_batch_response(1))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(serde_json::json!({
+        "title": "News for {{ name }}",
+        "content": {
+            "text": "Hello {{ name }}!",
+            "html": "<p>Hello {{ name }}!</p>",
+        }
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    let batch_request = find_batch_request(&app).await;
+    let body: serde_json::Value = serde_json::from_slice(&batch_request.body).unwrap();
+    assert_eq!(body[0]["Subject"], "News for bulbasaur");
+    assert!(body[0]["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains("<p>Hello bulbasaur!</p>"));
+    assert!(body[0]["TextBody"]
+        .as_str()
+        .unwrap()
+        .starts_with("Hello bulbasaur!"));
+}
+
+#[tokio::test]
+async fn newsletters_reject_unknown_variables() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Hello {{ first_name }}!",
+                "html": "<p>Hello {{ name }}!</p>",
+            }
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["details"][0]["field"], "content.text");
+    assert_eq!(error["details"][0]["code"], "unknown_variable");
 }
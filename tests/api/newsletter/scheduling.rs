@@ -0,0 +1,189 @@
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::Mock;
+
+use crate::helper::{create_confirmed_subscriber, postmark_batch_response, spawn_app, TestApp};
+
+fn compose_form(scheduled_for: &str) -> serde_json::Value {
+    serde_json::json!({
+        "idempotency-key": Uuid::new_v4().to_string(),
+        "title": "Newsletter title",
+        "html-content": "<p>Newsletter body as HTML</p>",
+        "text-content": "Newsletter body as plain text",
+        "scheduled-for": scheduled_for,
+    })
+}
+
+fn tomorrow() -> String {
+    (Utc::now() + Duration::days(1))
+        .format("%Y-%m-%dT%H:%M")
+        .to_string()
+}
+
+struct IssueState {
+    newsletter_issue_id: Uuid,
+    title: String,
+    status: String,
+}
+
+async fn issues(app: &TestApp) -> Vec<IssueState> {
+    sqlx::query_as!(
+        IssueState,
+        "select newsletter_issue_id, title, status from newsletter_issues"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("the newsletter issues should be readable")
+}
+
+async fn make_due(app: &TestApp) {
+    sqlx::query!("update newsletter_issues set scheduled_for = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .expect("the schedule should be updated");
+}
+
+async fn queued_tasks(app: &TestApp) -> i64 {
+    sqlx::query!("select count(*) as \"count!\" from issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("the delivery queue should be readable")
+        .count
+}
+
+#[tokio::test]
+async fn drafts_are_saved_without_being_delivered() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_admin().await;
+
+    let mut form = compose_form("");
+    form["text-content"] = "".into();
+    let response = app.post_compose_form("/draft", form).await;
+
+    let issues = issues(&app).await;
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].status, "draft");
+    assert_eq!(
+        response.headers().get("HX-Redirect").unwrap(),
+        format!("/app/newsletters/{}", issues[0].newsletter_issue_id).as_str()
+    );
+    assert_eq!(queued_tasks(&app).await, 0);
+}
+
+#[tokio::test]
+async fn drafts_can_be_edited() {
+    let app = spawn_app().await;
+    app.login_admin().await;
+    app.post_compose_form("/draft", compose_form("")).await;
+    let id = issues(&app).await[0].newsletter_issue_id;
+
+    let html = app
+        .http_client
+        .get(format!("{}/app/newsletters/{}", &app.addr, id))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains(r#"value="Newsletter title""#));
+
+    let mut form = compose_form("");
+    form["newsletter-issue-id"] = id.to_string().into();
+    form["title"] = "A better title".into();
+    app.post_compose_form("/draft", form).await;
+
+    let issues = issues(&app).await;
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].title, "A better title");
+}
+
+#[tokio::test]
+async fn scheduled_issues_are_published_once_due() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_admin().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(postmark_batch_response(1))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_compose_form("/schedule", compose_form(&tomorrow()))
+        .await;
+    assert_eq!(issues(&app).await[0].status, "scheduled");
+    assert_eq!(app.scheduling_worker.publish_due_issues().await.unwrap(), 0);
+
+    make_due(&app).await;
+    assert_eq!(app.scheduling_worker.publish_due_issues().await.unwrap(), 1);
+    assert_eq!(issues(&app).await[0].status, "sending");
+    assert_eq!(queued_tasks(&app).await, 1);
+
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(issues(&app).await[0].status, "sent");
+}
+
+#[tokio::test]
+async fn scheduling_requires_a_date_in_the_future() {
+    let app = spawn_app().await;
+    app.login_admin().await;
+
+    let yesterday = (Utc::now() - Duration::days(1))
+        .format("%Y-%m-%dT%H:%M")
+        .to_string();
+    for scheduled_for in ["", yesterday.as_str()] {
+        let response = app
+            .post_compose_form("/schedule", compose_form(scheduled_for))
+            .await;
+
+        assert!(response
+            .text()
+            .await
+            .unwrap()
+            .contains("Please pick a publication date"));
+    }
+    assert!(issues(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn cancelled_issues_are_never_published() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_admin().await;
+    app.post_compose_form("/schedule", compose_form(&tomorrow()))
+        .await;
+    let id = issues(&app).await[0].newsletter_issue_id;
+
+    app.post_cancel_issue(id).await;
+    make_due(&app).await;
+
+    assert_eq!(app.scheduling_worker.publish_due_issues().await.unwrap(), 0);
+    assert_eq!(issues(&app).await[0].status, "cancelled");
+    assert_eq!(queued_tasks(&app).await, 0);
+}
+
+#[tokio::test]
+async fn published_issues_cannot_be_cancelled() {
+    let app = spawn_app().await;
+    app.login_admin().await;
+    app.post_compose_form("", compose_form("")).await;
+    let id = issues(&app).await[0].newsletter_issue_id;
+
+    app.post_cancel_issue(id).await;
+
+    assert_eq!(issues(&app).await[0].status, "sent");
+    let html = app
+        .http_client
+        .get(format!("{}/app/issues", &app.addr))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("This issue can no longer be cancelled"));
+}
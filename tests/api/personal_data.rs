@@ -0,0 +1,174 @@
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helper::{create_confirmed_subscriber, spawn_app, TestApp};
+
+/// Ask for the data of bulbasaur and return the query of the emailed link.
+async fn request_data_link(app: &TestApp) -> String {
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    app.post_data_request("bulbasaur@example.com")
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    // The HTML body escapes the `&` between the query parameters.
+    let link = app.get_confirmation_links(&email_request).plain_text;
+    assert_eq!(link.path(), "/my-data");
+    link.query().unwrap().to_owned()
+}
+
+async fn erase(app: &TestApp, query: &str) -> reqwest::Response {
+    app.http_client
+        .post(format!(
+            "{}/api/v1/subscriptions/data/erase?{}",
+            app.addr, query
+        ))
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn subscribers_are_emailed_a_link_to_their_data() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    let query = request_data_link(&app).await;
+
+    let response = reqwest::get(format!("{}/my-data?{}", app.addr, query))
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Download"));
+}
+
+#[tokio::test]
+async fn unknown_addresses_are_not_revealed() {
+    let app = spawn_app().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_data_request("nobody@example.com").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn personal_data_can_be_downloaded() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let query = request_data_link(&app).await;
+
+    let response = reqwest::get(format!("{}/api/v1/subscriptions/data?{}", app.addr, query))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let data: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(data["subscriber"]["email"], "bulbasaur@example.com");
+    assert_eq!(data["subscriber"]["lists"][0]["slug"], "newsletter");
+    assert_eq!(data["subscription_tokens"].as_array().unwrap().len(), 1);
+    assert_eq!(data["subscription_tokens"][0]["list"], "newsletter");
+    assert_eq!(data["deliveries"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn links_with_an_invalid_token_are_rejected() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+    let query = format!("subscriber_id={}&token=1.{}", subscriber_id, "a".repeat(64));
+
+    let response = reqwest::get(format!("{}/api/v1/subscriptions/data?{}", app.addr, query))
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = reqwest::get(format!("{}/my-data?{}", app.addr, query))
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn erasure_deletes_the_subscriber_and_keeps_them_out_of_imports() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let query = request_data_link(&app).await;
+
+    let response = erase(&app, &query).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let subscribers = sqlx::query_scalar!(r#"select count(*) as "count!" from subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(subscribers, 0);
+    let tokens = sqlx::query_scalar!(r#"select count(*) as "count!" from subscription_tokens"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(tokens, 0);
+    let suppression = sqlx::query_scalar!("select email_hash from suppressions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(!suppression.contains("bulbasaur"));
+
+    let report: serde_json::Value = app
+        .post_subscribers_import("email,name\nBulbasaur@example.com,bulbasaur\n", &[])
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(report["imported"], 0);
+    assert_eq!(report["suppressed"], 1);
+}
+
+#[tokio::test]
+async fn erased_subscribers_can_subscribe_again() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let query = request_data_link(&app).await;
+    erase(&app, &query).await.error_for_status().unwrap();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({"name": "bulbasaur", "email": "bulbasaur@example.com"});
+    let response = app.post_subscriptions(body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    assert_eq!(
+        confirmation_links.plain_text.path(),
+        "/subscriptions/confirm"
+    );
+}
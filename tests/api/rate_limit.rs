@@ -0,0 +1,176 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::config::RateLimit;
+
+use crate::helper::{spawn_app_with, TestApp};
+
+const GENEROUS: RateLimit = RateLimit {
+    max_requests: 100,
+    window_seconds: 3600,
+};
+
+fn limit(max_requests: u32) -> RateLimit {
+    RateLimit {
+        max_requests,
+        window_seconds: 3600,
+    }
+}
+
+/// Post without the retries of the test client, which would retry `429` responses.
+async fn post(
+    app: &TestApp,
+    endpoint: &str,
+    body: serde_json::Value,
+    headers: &[(&str, &str)],
+) -> reqwest::Response {
+    let mut request = reqwest::Client::new()
+        .post(format!("{}{}", &app.addr, endpoint))
+        .json(&body);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    request.send().await.expect("the request should succeed")
+}
+
+async fn subscribe(app: &TestApp, email: &str) -> reqwest::Response {
+    post(
+        app,
+        "/api/v1/subscriptions",
+        serde_json::json!({"name": "bulbasaur", "email": email}),
+        &[],
+    )
+    .await
+}
+
+/// Log in through a proxy which received the request from `forwarded_for`.
+async fn login_from(
+    app: &TestApp,
+    credentials: &serde_json::Value,
+    forwarded_for: &str,
+) -> reqwest::Response {
+    post(
+        app,
+        "/api/v1/users/login",
+        credentials.clone(),
+        &[("x-forwarded-for", forwarded_for)],
+    )
+    .await
+}
+
+async fn mock_email_server(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+#[tokio::test]
+async fn clients_over_the_limit_are_told_when_to_retry() {
+    let app = spawn_app_with(|config| {
+        config.rate_limits.subscribe.per_client = limit(2);
+        config.rate_limits.subscribe.per_target = GENEROUS;
+    })
+    .await;
+    mock_email_server(&app).await;
+
+    for email in ["bulbasaur@example.com", "ivysaur@example.com"] {
+        assert_eq!(subscribe(&app, email).await.status().as_u16(), 200);
+    }
+    let response = subscribe(&app, "venusaur@example.com").await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    let retry_after: u64 = response.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=3600).contains(&retry_after));
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["code"], 1005);
+}
+
+#[tokio::test]
+async fn an_address_cannot_be_sent_unlimited_confirmation_emails() {
+    let app = spawn_app_with(|config| {
+        config.rate_limits.subscribe.per_client = GENEROUS;
+        config.rate_limits.subscribe.per_target = limit(1);
+    })
+    .await;
+    mock_email_server(&app).await;
+
+    assert_eq!(
+        subscribe(&app, "bulbasaur@example.com")
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+    let response = subscribe(&app, "Bulbasaur@example.com").await;
+    assert_eq!(response.status().as_u16(), 429);
+    let response = post(
+        &app,
+        "/api/v1/subscriptions/confirm/resend",
+        serde_json::json!({"email": "bulbasaur@example.com"}),
+        &[],
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    // Other addresses are not affected.
+    assert_eq!(
+        subscribe(&app, "ivysaur@example.com")
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+}
+
+#[tokio::test]
+async fn login_attempts_are_limited_per_username() {
+    let app = spawn_app_with(|config| {
+        config.rate_limits.login.per_client = GENEROUS;
+        config.rate_limits.login.per_target = limit(2);
+    })
+    .await;
+    let wrong_credentials = serde_json::json!({
+        "username": &app.test_user.username,
+        "password": "not-the-password",
+    });
+    for _ in 0..2 {
+        let response = post(&app, "/login", wrong_credentials.clone(), &[]).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    // Even the right password is rejected until the window ends.
+    let credentials = serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password,
+    });
+    let response = post(&app, "/api/v1/users/login", credentials, &[]).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+}
+
+#[tokio::test]
+async fn clients_are_told_apart_by_the_proxy_header() {
+    let app = spawn_app_with(|config| {
+        config.rate_limits.client_ip_header = Some("x-forwarded-for".to_owned());
+        config.rate_limits.login.per_client = limit(1);
+        config.rate_limits.login.per_target = GENEROUS;
+    })
+    .await;
+    let credentials = serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password,
+    });
+
+    let response = login_from(&app, &credentials, "203.0.113.1").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = login_from(&app, &credentials, "203.0.113.2").await;
+    assert_eq!(response.status().as_u16(), 200);
+    // Only the address appended by the proxy is trusted.
+    let response = login_from(&app, &credentials, "198.51.100.7, 203.0.113.1").await;
+    assert_eq!(response.status().as_u16(), 429);
+}
@@ -0,0 +1,483 @@
+use secrecy::ExposeSecret;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helper::{
+    create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app, TestApp,
+};
+
+/// Subscribe someone without confirming, and return their id.
+async fn create_pending_subscriber(app: &TestApp, name: &str, email: &str) -> Uuid {
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    app.post_subscriptions(serde_json::json!({"name": name, "email": email}))
+        .await
+        .error_for_status()
+        .unwrap();
+
+    sqlx::query!("select id from subscriptions where email = $1", email)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id
+}
+
+fn emails(body: &serde_json::Value) -> Vec<&str> {
+    body["subscribers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|subscriber| subscriber["email"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn subscribers_are_listed_with_their_status() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_pending_subscriber(&app, "charmander", "charmander@example.com").await;
+
+    let body: serde_json::Value = app.get_subscribers(&[]).await.json().await.unwrap();
+
+    assert_eq!(body["total"], 2);
+    assert_eq!(
+        emails(&body),
+        ["charmander@example.com", "bulbasaur@example.com"]
+    );
+
+    let body: serde_json::Value = app
+        .get_subscribers(&[("status", "confirmed")])
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(emails(&body), ["bulbasaur@example.com"]);
+}
+
+#[tokio::test]
+async fn subscribers_can_be_searched_by_email_or_name() {
+    let app = spawn_app().await;
+    create_pending_subscriber(&app, "charmander", "charmander@example.com").await;
+    create_pending_subscriber(&app, "Squirtle", "turtle@example.com").await;
+
+    let body: serde_json::Value = app
+        .get_subscribers(&[("search", "squirt")])
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(emails(&body), ["turtle@example.com"]);
+
+    let body: serde_json::Value = app
+        .get_subscribers(&[("search", "CHARMANDER@")])
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(emails(&body), ["charmander@example.com"]);
+
+    // Wildcards are matched literally.
+    let body: serde_json::Value = app
+        .get_subscribers(&[("search", "%")])
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["total"], 0);
+}
+
+#[tokio::test]
+async fn subscribers_are_paginated() {
+    let app = spawn_app().await;
+    for name in ["charmander", "squirtle", "pikachu"] {
+        create_pending_subscriber(&app, name, &format!("{}@example.com", name)).await;
+    }
+
+    let body: serde_json::Value = app
+        .get_subscribers(&[("per_page", "2"), ("page", "2")])
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(body["total"], 3);
+    assert_eq!(body["page"], 2);
+    assert_eq!(emails(&body), ["charmander@example.com"]);
+}
+
+#[tokio::test]
+async fn invalid_list_parameters_are_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .get_subscribers(&[("status", "gone"), ("per_page", "1000")])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    let rejected: Vec<(&str, &str)> = error["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| (d["field"].as_str().unwrap(), d["code"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        rejected,
+        [("status", "unknown_status"), ("per_page", "out_of_range")]
+    );
+}
+
+#[tokio::test]
+async fn a_subscriber_is_returned_with_their_lists() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+
+    let body: serde_json::Value = app
+        .get_subscriber(subscriber_id)
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(body["email"], "bulbasaur@example.com");
+    assert_eq!(body["status"], "confirmed");
+    assert_eq!(body["lists"][0]["slug"], "newsletter");
+    assert_eq!(body["lists"][0]["status"], "confirmed");
+}
+
+#[tokio::test]
+async fn unknown_subscribers_are_not_found() {
+    let app = spawn_app().await;
+
+    let response = app.get_subscriber(Uuid::new_v4()).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn the_name_of_a_subscriber_can_be_updated() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+
+    let response = app
+        .patch_subscriber(subscriber_id, serde_json::json!({"name": "ivysaur"}))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["name"], "ivysaur");
+}
+
+#[tokio::test]
+async fn invalid_names_are_rejected() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+
+    let response = app
+        .patch_subscriber(subscriber_id, serde_json::json!({"name": "<script>"}))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["details"][0]["field"], "name");
+}
+
+#[tokio::test]
+async fn pending_subscribers_can_be_confirmed_manually() {
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    let subscriber_id = sqlx::query!("select id from subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+
+    let response = app.post_subscriber_action(subscriber_id, "confirm").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "confirmed");
+    assert_eq!(body["lists"][0]["status"], "confirmed");
+
+    // The link sent to the subscriber is no longer needed.
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn subscribers_can_be_unsubscribed_manually() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+
+    let response = app
+        .post_subscriber_action(subscriber_id, "unsubscribe")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "unsubscribed");
+    assert_eq!(body["lists"][0]["status"], "unsubscribed");
+}
+
+#[tokio::test]
+async fn subscribers_can_be_deleted() {
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    let subscriber_id = sqlx::query!("select id from subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+
+    let response = app.delete_subscriber(subscriber_id).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.get_subscriber(subscriber_id).await;
+    assert_eq!(response.status().as_u16(), 404);
+    let response = app.delete_subscriber(subscriber_id).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn subscriber_management_requires_authentication() {
+    let app = spawn_app().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/api/v1/subscribers", &app.addr))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn valid_rows_are_imported_and_invalid_ones_reported() {
+    let app = spawn_app().await;
+    let csv = "\
+email,name,status,consented_at
+charmander@example.com,charmander,,2024-01-15T10:00:00Z
+squirtle@example.com,squirtle,unsubscribed,
+not-an-email,pikachu,confirmed,yesterday
+";
+
+    let response = app.post_subscribers_import(csv, &[]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["imported"], 2);
+    assert_eq!(report["existing"], 0);
+    let rejected: Vec<(u64, &str, &str)> = report["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| {
+            (
+                e["row"].as_u64().unwrap(),
+                e["field"].as_str().unwrap(),
+                e["code"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        rejected,
+        [
+            (3, "email", "invalid_format"),
+            (3, "consented_at", "invalid_format")
+        ]
+    );
+
+    let saved = sqlx::query!(
+        r#"
+        select s.email, s.status, s.subscribed_at, m.status as membership_status
+        from subscriptions s
+        join list_memberships m on m.subscriber_id = s.id
+        order by s.email
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(saved.len(), 2);
+    assert_eq!(saved[0].email, "charmander@example.com");
+    assert_eq!(saved[0].status, "confirmed");
+    assert_eq!(saved[0].membership_status, "confirmed");
+    assert_eq!(
+        saved[0].subscribed_at.to_rfc3339(),
+        "2024-01-15T10:00:00+00:00"
+    );
+    assert_eq!(saved[1].status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn subscribers_pending_confirmation_are_not_imported() {
+    let app = spawn_app().await;
+
+    let report: serde_json::Value = app
+        .post_subscribers_import(
+            "email,name,status\ncharmander@example.com,charmander,pending_confirmation\n",
+            &[],
+        )
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(report["imported"], 0);
+    assert_eq!(report["errors"][0]["field"], "status");
+    assert_eq!(report["errors"][0]["code"], "unconfirmed");
+}
+
+#[tokio::test]
+async fn dry_runs_store_nothing() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_subscribers_import(
+            "email,name\ncharmander@example.com,charmander\n",
+            &[("dry_run", "true")],
+        )
+        .await;
+
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["dry_run"], true);
+    assert_eq!(report["imported"], 1);
+    let count = sqlx::query_scalar!(r#"select count(*) as "count!" from subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn imports_skip_known_and_repeated_emails() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let csv = "\
+email,name
+Bulbasaur@Example.com,someone else
+charmander@example.com,charmander
+CHARMANDER@example.com,charmander again
+";
+
+    let report: serde_json::Value = app
+        .post_subscribers_import(csv, &[])
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(report["imported"], 1);
+    assert_eq!(report["existing"], 1);
+    assert_eq!(report["errors"][0]["row"], 3);
+    assert_eq!(report["errors"][0]["code"], "duplicate");
+    let name =
+        sqlx::query_scalar!("select name from subscriptions where email = 'bulbasaur@example.com'")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(name, "bulbasaur");
+}
+
+#[tokio::test]
+async fn imports_without_the_required_columns_are_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_subscribers_import("email\ncharmander@example.com\n", &[])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["details"][0]["field"], "csv");
+    assert_eq!(error["details"][0]["code"], "missing_column");
+}
+
+#[tokio::test]
+async fn exported_subscribers_can_be_imported_back() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    let response = app.get_subscribers_export().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/csv; charset=utf-8"
+    );
+    let csv = response.text().await.unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("email,name,status,consented_at"));
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("bulbasaur@example.com,bulbasaur,confirmed,"));
+    assert_eq!(lines.next(), None);
+
+    let report: serde_json::Value = app
+        .post_subscribers_import(&csv, &[("dry_run", "true")])
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(report["existing"], 1);
+    assert_eq!(report["errors"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn exports_can_be_imported_into_another_instance() {
+    let source = spawn_app().await;
+    create_confirmed_subscriber(&source).await;
+    let csv = "\
+email,name,status,consented_at
+charmander@example.com,charmander,unsubscribed,2024-01-15T10:00:00Z
+squirtle@example.com,squirtle,complained,2024-02-15T10:00:00Z
+";
+    source
+        .post_subscribers_import(csv, &[])
+        .await
+        .error_for_status()
+        .unwrap();
+    source
+        .post_postmark_webhook(
+            serde_json::json!({
+                "RecordType": "Bounce",
+                "Type": "HardBounce",
+                "Email": "bulbasaur@example.com",
+            }),
+            source.webhook_secret.expose_secret(),
+        )
+        .await
+        .error_for_status()
+        .unwrap();
+    let exported = source.get_subscribers_export().await.text().await.unwrap();
+
+    let target = spawn_app().await;
+    let report: serde_json::Value = target
+        .post_subscribers_import(&exported, &[])
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(report["imported"], 3);
+    assert_eq!(report["errors"], serde_json::json!([]));
+    let reexported = target.get_subscribers_export().await.text().await.unwrap();
+    let sorted_lines = |csv: &str| {
+        let mut lines: Vec<String> = csv.lines().map(str::to_owned).collect();
+        lines.sort();
+        lines
+    };
+    assert_eq!(sorted_lines(&reexported), sorted_lines(&exported));
+    let n_suppressions = sqlx::query_scalar!(r#"select count(*) as "count!" from suppressions"#)
+        .fetch_one(&target.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(n_suppressions, 2);
+}
@@ -0,0 +1,50 @@
+use crate::helper::{create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app};
+
+#[tokio::test]
+async fn expired_confirmation_tokens_are_purged() {
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscription_tokens SET expires_at = now() - interval '1 hour'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let outcome = app.cleanup_worker.purge().await.unwrap();
+
+    assert_eq!(outcome.tokens, 1);
+    // The subscriber can still ask for a new link until the retention period is over.
+    assert_eq!(outcome.subscribers, 0);
+}
+
+#[tokio::test]
+async fn subscribers_never_confirmed_are_purged_after_the_retention_period() {
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscriptions SET subscribed_at = now() - interval '365 days'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let outcome = app.cleanup_worker.purge().await.unwrap();
+
+    assert_eq!(outcome.subscribers, 1);
+    let remaining = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(remaining.is_empty());
+}
+
+#[tokio::test]
+async fn confirmed_subscribers_are_never_purged() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscriptions SET subscribed_at = now() - interval '365 days'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let outcome = app.cleanup_worker.purge().await.unwrap();
+
+    assert_eq!(outcome.subscribers, 0);
+}
@@ -1,2 +1,4 @@
+pub mod cleanup;
 pub mod confirm;
 pub mod subscribe;
+pub mod unsubscribe;
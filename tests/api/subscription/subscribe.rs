@@ -3,7 +3,7 @@ use wiremock::{
     Mock, ResponseTemplate,
 };
 
-use crate::helper::spawn_app;
+use crate::helper::{create_confirmed_subscriber, spawn_app};
 
 #[tokio::test]
 async fn subscribe_returns_a_200_for_valid_form_data() {
@@ -107,6 +107,61 @@ async fn subscribe_returns_a_400_when_fields_are_present_but_invalid() {
     }
 }
 
+#[tokio::test]
+async fn subscribe_reports_every_invalid_field() {
+    let app = spawn_app().await;
+
+    let body = serde_json::json!({"name": "", "email": "definitely-not-an-email"});
+    let response = app.post_subscriptions(body).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], 1000);
+    assert_eq!(
+        error["details"],
+        serde_json::json!([
+            {"field": "name", "code": "empty", "message": "name is empty"},
+            {"field": "email", "code": "invalid_format", "message": "invalid email"},
+        ])
+    );
+}
+
+#[tokio::test]
+async fn subscribe_describes_json_rejections() {
+    let app = spawn_app().await;
+    let url = format!("{}/api/v1/subscriptions", &app.addr);
+
+    let missing_field = app
+        .post_subscriptions(serde_json::json!({"name": "bulbasaur"}))
+        .await;
+    let malformed = app
+        .http_client
+        .post(&url)
+        .header("Content-Type", "application/json")
+        .body(r#"{"name": "bulbasaur","#)
+        .send()
+        .await
+        .unwrap();
+    let wrong_content_type = app
+        .http_client
+        .post(&url)
+        .form(&[("name", "bulbasaur"), ("email", "bulbasaur@example.com")])
+        .send()
+        .await
+        .unwrap();
+
+    for (response, status, code) in [
+        (missing_field, 422, 1002),
+        (malformed, 400, 1001),
+        (wrong_content_type, 415, 1003),
+    ] {
+        assert_eq!(response.status().as_u16(), status);
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(error["code"], code);
+        assert!(error["message"].as_str().is_some_and(|m| !m.is_empty()));
+    }
+}
+
 #[tokio::test]
 async fn subscribe_sends_a_confirmation_email_for_valid_data() {
     let app = spawn_app().await;
@@ -136,21 +191,144 @@ async fn subscribe_sends_a_confirmation_email_with_a_link() {
     app.post_subscriptions(body).await;
 
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
-    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
-    // Extract the link from one of the request fields.
-    let get_link = |s: &str| {
-        let links: Vec<_> = linkify::LinkFinder::new()
-            .links(s)
-            .filter(|l| *l.kind() == linkify::LinkKind::Url)
-            .collect();
-        assert_eq!(links.len(), 1);
-        links[0].as_str().to_owned()
-    };
-
-    let html_link = get_link(body["HtmlBody"].as_str().unwrap());
-    let text_link = get_link(body["TextBody"].as_str().unwrap());
+    let confirmation_links = app.get_confirmation_links(email_request);
     // The two links should be identical
-    assert_eq!(html_link, text_link);
+    assert_eq!(confirmation_links.html, confirmation_links.plain_text);
+}
+
+#[tokio::test]
+async fn subscribing_twice_while_pending_sends_a_new_confirmation_link() {
+    let app = spawn_app().await;
+    let body = serde_json::json!({"name": "bulbasaur", "email": "bulbasaur@example.com"});
+
+    // Two confirmation emails, then the welcome email once confirmed.
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body.clone()).await;
+    let response = app.post_subscriptions(body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let first_links = app.get_confirmation_links(&email_requests[0]);
+    let second_links = app.get_confirmation_links(&email_requests[1]);
+    assert_ne!(first_links.html, second_links.html);
+
+    // Only the latest link can confirm the subscription.
+    let response = reqwest::get(first_links.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+    let response = reqwest::get(second_links.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["Subject"], "Your subscription is confirmed");
+}
+
+#[tokio::test]
+async fn concurrent_subscriptions_with_the_same_email_both_succeed() {
+    let app = spawn_app().await;
+    let body = serde_json::json!({"name": "bulbasaur", "email": "bulbasaur@example.com"});
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response1 = app.post_subscriptions(body.clone());
+    let response2 = app.post_subscriptions(body);
+    let (response1, response2) = tokio::join!(response1, response2);
+
+    assert_eq!(response1.status().as_u16(), 200);
+    assert_eq!(response2.status().as_u16(), 200);
+    let n_subscribers = sqlx::query_scalar!("SELECT count(*) FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(n_subscribers, Some(1));
+}
+
+#[tokio::test]
+async fn subscribing_again_once_confirmed_succeeds_without_sending_an_email() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({"name": "bulbasaur", "email": "bulbasaur@example.com"});
+    let response = app.post_subscriptions(body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn subscribing_again_once_unsubscribed_requires_a_new_confirmation() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+    app.post_unsubscribe(subscriber_id, &app.unsubscribe_token(subscriber_id))
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // The confirmation email, then the welcome email once confirmed.
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({"name": "bulbasaur", "email": "bulbasaur@example.com"});
+    let response = app.post_subscriptions(body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "pending_confirmation");
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
 }
 
 #[tokio::test]
@@ -0,0 +1,125 @@
+use uuid::Uuid;
+
+use crate::helper::{create_confirmed_subscriber, spawn_app, TestApp};
+
+async fn subscriber_status(app: &TestApp) -> String {
+    sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("the subscriber should be stored")
+        .status
+}
+
+#[tokio::test]
+async fn unsubscribe_without_parameters_is_rejected_with_a_400() {
+    let app = spawn_app().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/api/v1/subscriptions/unsubscribe", app.addr))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn unsubscribe_with_an_invalid_token_is_rejected_with_a_401() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+
+    let test_cases = vec![
+        (
+            app.unsubscribe_token(Uuid::new_v4()),
+            "a token for another subscriber",
+        ),
+        ("a".repeat(64), "a forged token"),
+        ("not-a-token".to_owned(), "a malformed token"),
+    ];
+
+    for (token, description) in test_cases {
+        let response = app.post_unsubscribe(subscriber_id, &token).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "The API did not reject {}.",
+            description
+        );
+    }
+    assert_eq!(subscriber_status(&app).await, "confirmed");
+}
+
+#[tokio::test]
+async fn one_click_unsubscribe_unsubscribes_a_subscriber() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+    let token = app.unsubscribe_token(subscriber_id);
+
+    let response = app.post_unsubscribe(subscriber_id, &token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, "unsubscribed");
+}
+
+#[tokio::test]
+async fn unsubscribing_twice_succeeds() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+    let token = app.unsubscribe_token(subscriber_id);
+
+    app.post_unsubscribe(subscriber_id, &token).await;
+    let response = app.post_unsubscribe(subscriber_id, &token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, "unsubscribed");
+}
+
+#[tokio::test]
+async fn the_unsubscribe_page_asks_for_a_confirmation() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+    let token = app.unsubscribe_token(subscriber_id);
+
+    let response = app.get_unsubscribe_page(subscriber_id, &token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("hx-post=\"/unsubscribe?"));
+    // Following the link alone must not unsubscribe anyone.
+    assert_eq!(subscriber_status(&app).await, "confirmed");
+}
+
+#[tokio::test]
+async fn the_unsubscribe_page_rejects_an_invalid_token() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+
+    let response = app
+        .get_unsubscribe_page(subscriber_id, &app.unsubscribe_token(Uuid::new_v4()))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(response.text().await.unwrap().contains("link is invalid"));
+}
+
+#[tokio::test]
+async fn confirming_on_the_unsubscribe_page_unsubscribes_a_subscriber() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+    let token = app.unsubscribe_token(subscriber_id);
+
+    let response = app.post_unsubscribe_page(subscriber_id, &token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("You have been unsubscribed"));
+    assert_eq!(subscriber_status(&app).await, "unsubscribed");
+}
@@ -0,0 +1,202 @@
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::Mock;
+
+use crate::helper::{create_confirmed_subscriber, postmark_batch_response, spawn_app, TestApp};
+
+const LINK: &str = "https://example.com/posts/1";
+
+/// Publish and deliver an issue linking to [`LINK`], returning its id and the HTML body
+/// sent to the subscriber.
+async fn deliver_issue(app: &TestApp, tracking: bool) -> (Uuid, String) {
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(postmark_batch_response(1))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body",
+                "html": format!(r#"<p>Read <a href="{LINK}">the post</a></p>"#),
+            },
+            "tracking": tracking,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 202);
+    app.dispatch_all_pending_emails().await;
+
+    let batch = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|request| request.url.path() == "/email/batch")
+        .expect("the issue should be sent");
+    let body: serde_json::Value = serde_json::from_slice(&batch.body).unwrap();
+    let html = body[0]["HtmlBody"].as_str().unwrap().to_owned();
+
+    let issues: serde_json::Value = app.get_newsletter_issues().await.json().await.unwrap();
+    let newsletter_issue_id = issues["issues"][0]["newsletter_issue_id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    (newsletter_issue_id, html)
+}
+
+async fn issue_stats(app: &TestApp, newsletter_issue_id: Uuid) -> (i64, i64) {
+    let issue: serde_json::Value = app
+        .get_newsletter_issue(newsletter_issue_id)
+        .await
+        .json()
+        .await
+        .unwrap();
+    (
+        issue["unique_opens"].as_i64().unwrap(),
+        issue["clicks"].as_i64().unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn tracked_issues_embed_a_pixel_and_rewrite_their_links() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    let (_, html) = deliver_issue(&app, true).await;
+
+    assert!(html.contains("/api/v1/tracking/open?"));
+    assert!(html.contains("/api/v1/tracking/click?"));
+    assert!(!html.contains(&format!(r#"href="{LINK}""#)));
+    assert!(html.contains("/tracking?subscriber_id="));
+}
+
+#[tokio::test]
+async fn untracked_issues_are_sent_as_written() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    let (_, html) = deliver_issue(&app, false).await;
+
+    assert!(!html.contains("/tracking"));
+    assert!(html.contains(&format!(r#"href="{LINK}""#)));
+}
+
+#[tokio::test]
+async fn opens_are_counted_once_per_subscriber() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+    let (newsletter_issue_id, _) = deliver_issue(&app, true).await;
+
+    for _ in 0..2 {
+        let response = app
+            .get_tracking_pixel(newsletter_issue_id, subscriber_id)
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["content-type"], "image/gif");
+    }
+
+    assert_eq!(issue_stats(&app, newsletter_issue_id).await, (1, 0));
+}
+
+#[tokio::test]
+async fn clicks_are_counted_and_redirect_to_the_link() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+    let (newsletter_issue_id, _) = deliver_issue(&app, true).await;
+    let token = app.click_token(newsletter_issue_id, subscriber_id, LINK);
+
+    for _ in 0..2 {
+        let response = app
+            .get_tracked_link(newsletter_issue_id, subscriber_id, LINK, &token)
+            .await;
+        assert_eq!(response.status().as_u16(), 303);
+        assert_eq!(response.headers()["location"], LINK);
+    }
+
+    // Following a link counts as an open.
+    assert_eq!(issue_stats(&app, newsletter_issue_id).await, (1, 2));
+    let body: serde_json::Value = app
+        .get_newsletter_issue_links(newsletter_issue_id)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        body["links"],
+        serde_json::json!([{"url": LINK, "clicks": 2, "unique_clicks": 1}])
+    );
+}
+
+#[tokio::test]
+async fn tracked_links_cannot_redirect_elsewhere() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+    let (newsletter_issue_id, _) = deliver_issue(&app, true).await;
+    let token = app.click_token(newsletter_issue_id, subscriber_id, LINK);
+
+    let response = app
+        .get_tracked_link(
+            newsletter_issue_id,
+            subscriber_id,
+            "https://evil.example.com",
+            &token,
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(issue_stats(&app, newsletter_issue_id).await, (0, 0));
+}
+
+#[tokio::test]
+async fn untracked_issues_record_nothing() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+    let (newsletter_issue_id, _) = deliver_issue(&app, false).await;
+
+    app.get_tracking_pixel(newsletter_issue_id, subscriber_id)
+        .await;
+
+    assert_eq!(issue_stats(&app, newsletter_issue_id).await, (0, 0));
+}
+
+#[tokio::test]
+async fn opting_out_erases_and_stops_the_tracking() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+    let (newsletter_issue_id, _) = deliver_issue(&app, true).await;
+    app.get_tracking_pixel(newsletter_issue_id, subscriber_id)
+        .await;
+    assert_eq!(issue_stats(&app, newsletter_issue_id).await, (1, 0));
+
+    let token = app.unsubscribe_token(subscriber_id);
+    let response = app.post_tracking_opt_out(subscriber_id, &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(issue_stats(&app, newsletter_issue_id).await, (0, 0));
+
+    app.get_tracking_pixel(newsletter_issue_id, subscriber_id)
+        .await;
+    assert_eq!(issue_stats(&app, newsletter_issue_id).await, (0, 0));
+}
+
+#[tokio::test]
+async fn opting_out_requires_a_valid_token() {
+    let app = spawn_app().await;
+    let subscriber_id = create_confirmed_subscriber(&app).await;
+
+    let response = app
+        .post_tracking_opt_out(subscriber_id, &"a".repeat(64))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    let opted_out = sqlx::query_scalar!("select tracking_opt_out from subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(!opted_out);
+}
@@ -0,0 +1,196 @@
+use secrecy::ExposeSecret;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::domain::subscriber::email::Email;
+
+use crate::helper::{create_confirmed_subscriber, postmark_batch_response, spawn_app, TestApp};
+
+fn hard_bounce() -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Bounce",
+        "Type": "HardBounce",
+        "TypeCode": 1,
+        "Email": "bulbasaur@example.com",
+        "BouncedAt": "2024-06-03T08:45:12Z",
+    })
+}
+
+fn spam_complaint() -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "SpamComplaint",
+        "Type": "SpamComplaint",
+        "TypeCode": 512,
+        "Email": "bulbasaur@example.com",
+        "BouncedAt": "2024-06-03T08:45:12Z",
+    })
+}
+
+async fn post_webhook(app: &TestApp, body: serde_json::Value) -> reqwest::Response {
+    app.post_postmark_webhook(body, app.webhook_secret.expose_secret())
+        .await
+}
+
+async fn subscriber_status(app: &TestApp) -> String {
+    sqlx::query_scalar!("select status from subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn webhooks_without_the_shared_secret_are_rejected() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    let response = app
+        .post_postmark_webhook(hard_bounce(), "not-the-secret")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(subscriber_status(&app).await, "confirmed");
+}
+
+#[tokio::test]
+async fn webhooks_are_authenticated_before_their_body_is_checked() {
+    let app = spawn_app().await;
+    let body = serde_json::json!({"RecordType": 42});
+
+    let response = app
+        .post_postmark_webhook(body.clone(), "not-the-secret")
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = post_webhook(&app, body).await;
+    assert_eq!(response.status().as_u16(), 422);
+}
+
+#[tokio::test]
+async fn hard_bounces_suppress_the_address_and_unsubscribe_it() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    let response = post_webhook(&app, hard_bounce()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, "bounced");
+    let membership = sqlx::query_scalar!("select status from list_memberships")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(membership, "unsubscribed");
+    let reason = sqlx::query_scalar!("select reason from suppressions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(reason, "hard_bounce");
+}
+
+#[tokio::test]
+async fn spam_complaints_suppress_the_address_and_unsubscribe_it() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    let response = post_webhook(&app, spam_complaint()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, "complained");
+    let reason = sqlx::query_scalar!("select reason from suppressions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(reason, "spam_complaint");
+}
+
+#[tokio::test]
+async fn soft_bounces_are_ignored() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let mut soft_bounce = hard_bounce();
+    soft_bounce["Type"] = "SoftBounce".into();
+
+    let response = post_webhook(&app, soft_bounce).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, "confirmed");
+    let suppressions = sqlx::query_scalar!(r#"select count(*) as "count!" from suppressions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(suppressions, 0);
+}
+
+#[tokio::test]
+async fn bounced_subscribers_can_be_filtered() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    post_webhook(&app, hard_bounce()).await;
+
+    let body: serde_json::Value = app
+        .get_subscribers(&[("status", "bounced")])
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["subscribers"][0]["email"], "bulbasaur@example.com");
+}
+
+#[tokio::test]
+async fn suppressed_addresses_are_not_sent_confirmation_emails() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    post_webhook(&app, spam_complaint()).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscriptions(serde_json::json!({
+            "name": "bulbasaur",
+            "email": "bulbasaur@example.com",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn issues_are_not_delivered_to_suppressed_addresses() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    // Suppress the address without unsubscribing it, as if it happened while the
+    // issue was being delivered.
+    let email = Email::try_from("bulbasaur@example.com".to_owned()).unwrap();
+    sqlx::query!(
+        "insert into suppressions (email_hash, reason) values ($1, 'hard_bounce')",
+        email.suppression_hash(&app.hmac_key),
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(postmark_batch_response(1))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "content": {"text": "Newsletter body", "html": "<p>Newsletter body</p>"},
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 202);
+    app.dispatch_all_pending_emails().await;
+
+    let body: serde_json::Value = app.get_newsletter_issues().await.json().await.unwrap();
+    assert_eq!(body["issues"][0]["status"], "sent");
+    assert_eq!(body["issues"][0]["delivered_count"], 0);
+    assert_eq!(body["issues"][0]["failed_count"], 0);
+}